@@ -0,0 +1,188 @@
+//! Node.js bindings, so Electron tools can drive lights with the same report model.
+//!
+//! ```js
+//! const { devices, Device } = require("hidlights");
+//!
+//! const device = Device.open(devices()[0].id);
+//! device.set("Caps Lock", 1);
+//! await device.writeAsync();
+//! ```
+//!
+//! The `*Async` methods write on the libuv thread pool, keeping the event loop free
+//! while slow devices take their time.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use hidlights::{DeviceHandle, DeviceId, HidLightError, HidLights};
+use napi::{bindgen_prelude::AsyncTask, Env, Error, Result, Task};
+use napi_derive::napi;
+
+thread_local! {
+    // Only used from the JS thread, the worker tasks get handles instead
+    static API: std::result::Result<HidLights, String> =
+        HidLights::new().map_err(|x| x.to_string());
+}
+
+type LibResult<T> = std::result::Result<T, HidLightError>;
+
+fn js_error(err: HidLightError) -> Error {
+    Error::from_reason(err.to_string())
+}
+
+fn with_api<T>(f: impl FnOnce(&HidLights) -> LibResult<T>) -> Result<T> {
+    API.with(|api| match api {
+        Ok(api) => f(api).map_err(js_error),
+        Err(err) => Err(Error::from_reason(err.clone())),
+    })
+}
+
+#[napi(object)]
+pub struct DeviceInfo {
+    /// Pass to `Device.open`, stays the same across reconnects.
+    pub id: String,
+    pub vid: u32,
+    pub pid: u32,
+    pub name: Option<String>,
+    pub manufacturer: Option<String>,
+    pub usage_page: u32,
+    pub usage_id: u32,
+    pub interface_number: i32,
+}
+
+#[napi(object)]
+pub struct Output {
+    pub index: u32,
+    pub key: String,
+    pub name: Option<String>,
+    /// As a fraction of the output's range.
+    pub value: f64,
+}
+
+/// Enumerates HID devices.
+#[napi]
+pub fn devices() -> Result<Vec<DeviceInfo>> {
+    with_api(|api| {
+        api.refresh()?;
+        Ok(api
+            .devices()
+            .into_iter()
+            .map(|x| DeviceInfo {
+                id: x.id().to_string(),
+                vid: x.vid.into(),
+                pid: x.pid.into(),
+                name: x.name.clone(),
+                manufacturer: x.manufacturer.clone(),
+                usage_page: x.usage_page.into(),
+                usage_id: x.usage_id.into(),
+                interface_number: x.interface_number,
+            })
+            .collect())
+    })
+}
+
+#[napi]
+pub struct Device {
+    handle: Arc<Mutex<DeviceHandle>>,
+}
+
+impl Device {
+    fn lock(&self) -> std::sync::MutexGuard<'_, DeviceHandle> {
+        self.handle.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[napi]
+impl Device {
+    /// Opens a device by the `id` from `devices()`.
+    #[napi(factory)]
+    pub fn open(id: String) -> Result<Device> {
+        let id: DeviceId = id.parse().map_err(js_error)?;
+        let handle = with_api(|api| {
+            api.find(|x| x.id() == id)
+                .ok_or(HidLightError::DeviceNotFound)?
+                .open()
+        })?;
+        Ok(Device {
+            handle: Arc::new(Mutex::new(handle)),
+        })
+    }
+
+    #[napi]
+    pub fn outputs(&self) -> Result<Vec<Output>> {
+        let mut handle = self.lock();
+        handle.resolve_names().map_err(js_error)?;
+        Ok(handle
+            .outputs()
+            .map_err(js_error)?
+            .enumerate()
+            .map(|(index, x)| Output {
+                index: index as u32,
+                key: x.key().to_string(),
+                name: x.name.clone(),
+                value: x.real_value.into(),
+            })
+            .collect())
+    }
+
+    /// Sets an output by key, index or name, sent on the next write.
+    #[napi]
+    pub fn set(&self, output: String, value: f64) -> Result<()> {
+        set(&mut self.lock(), &output, value).map_err(js_error)
+    }
+
+    /// Writes every output changed since the last write.
+    #[napi]
+    pub fn write(&self) -> Result<()> {
+        self.lock().flush().map_err(js_error)
+    }
+
+    /// Like `write`, on the thread pool.
+    #[napi]
+    pub fn write_async(&self) -> AsyncTask<WriteTask> {
+        AsyncTask::new(WriteTask {
+            handle: self.handle.clone(),
+            values: HashMap::new(),
+        })
+    }
+
+    /// Sets outputs, keyed like `set`, and writes them on the thread pool.
+    #[napi]
+    pub fn set_async(&self, values: HashMap<String, f64>) -> AsyncTask<WriteTask> {
+        AsyncTask::new(WriteTask {
+            handle: self.handle.clone(),
+            values,
+        })
+    }
+}
+
+fn set(handle: &mut DeviceHandle, output: &str, value: f64) -> LibResult<()> {
+    let id = handle
+        .find_output(output)?
+        .ok_or(HidLightError::InvalidArgument)?;
+    handle.set(id, value as f32)
+}
+
+pub struct WriteTask {
+    handle: Arc<Mutex<DeviceHandle>>,
+    values: HashMap<String, f64>,
+}
+
+impl Task for WriteTask {
+    type Output = ();
+    type JsValue = ();
+
+    fn compute(&mut self) -> Result<()> {
+        let mut handle = self.handle.lock().unwrap_or_else(PoisonError::into_inner);
+        for (output, value) in &self.values {
+            set(&mut handle, output, *value).map_err(js_error)?;
+        }
+        handle.flush().map_err(js_error)
+    }
+
+    fn resolve(&mut self, _env: Env, _output: ()) -> Result<()> {
+        Ok(())
+    }
+}
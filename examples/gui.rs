@@ -1,31 +1,206 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
 use eframe::{egui, App, NativeOptions};
+use hidlights::{
+    scene::{DeviceScene, Scene, SceneRegistry},
+    surface::rgb_lamps,
+    DeviceFilter, DeviceId, OutputId, Report, Value,
+};
+
+/// How often the device list is re-enumerated, so hotplugged devices show up.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+/// Saved presets, in the working directory. Without the `serde` feature presets
+/// only last until the window is closed.
+#[cfg(feature = "serde")]
+const PRESETS: &str = "hidlights-presets.json";
 
 struct HidLightGui {
-    _api: hidlights::HidLights,
+    api: hidlights::HidLights,
+    last_refresh: Instant,
     open_device: Option<hidlights::DeviceHandle>,
+    open_id: Option<DeviceId>,
     reports: Option<Vec<hidlights::Report>>,
-    devices: Vec<hidlights::DeviceInfo>,
+    devices: Vec<hidlights::PhysicalDevice>,
+    presets: SceneRegistry,
+    preset_name: String,
+    /// Hex being edited for each report, to send as is.
+    raw_edits: HashMap<u32, String>,
+    search: String,
+    vid_filter: String,
+    usage_page_filter: String,
+    controllable_only: bool,
+    /// Interfaces passing the filter, updated when it or the device list changes.
+    visible: HashSet<DeviceId>,
+}
+
+fn output_value(report: &Report, id: OutputId) -> f32 {
+    report
+        .outputs
+        .iter()
+        .find(|x| x.id() == id)
+        .map_or(0.0, |x| x.real_value)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    let bytes: Vec<_> = bytes.iter().map(|x| format!("{x:02X}")).collect();
+    bytes.join(" ")
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    text.split_whitespace()
+        .map(|x| u8::from_str_radix(x, 16).ok())
+        .collect()
+}
+
+/// A preset of every output's current value.
+fn capture(name: &str, id: &DeviceId, reports: &[Report]) -> Scene {
+    let mut device = DeviceScene::new(id.clone());
+    for out in reports.iter().flat_map(|x| &x.outputs) {
+        let value = match out.selected() {
+            Some(x) => Value::Select(x),
+            None if out.is_selector() => continue,
+            None => Value::Real(out.real_value),
+        };
+        device.outputs.push((out.key(), value));
+    }
+    let mut scene = Scene::new(name);
+    scene.devices.push(device);
+    scene
+}
+
+/// Sets the outputs a preset has values for, skipping any that no longer fit.
+fn restore(preset: &DeviceScene, reports: &mut [Report]) {
+    for (key, value) in &preset.outputs {
+        let out = reports
+            .iter_mut()
+            .flat_map(|x| &mut x.outputs)
+            .find(|x| x.key() == *key);
+        if let Some(out) = out {
+            _ = out.set_value(*value);
+        }
+    }
+}
+
+impl HidLightGui {
+    fn refresh(&mut self) {
+        let ids = |devices: &[hidlights::PhysicalDevice]| -> Vec<DeviceId> {
+            devices
+                .iter()
+                .flat_map(|x| &x.interfaces)
+                .map(|x| x.id())
+                .collect()
+        };
+        if self.api.refresh().is_ok() {
+            let devices = self.api.physical_devices();
+            // Filtering may open every device, so only redo it when something changed
+            let changed = ids(&devices) != ids(&self.devices);
+            self.devices = devices;
+            if changed {
+                self.apply_filter();
+            }
+        }
+        self.last_refresh = Instant::now();
+    }
+
+    fn apply_filter(&mut self) {
+        let hex = |x: &str| u16::from_str_radix(x.trim().trim_start_matches("0x"), 16).ok();
+        let mut filter = DeviceFilter::new().text(&self.search);
+        filter.vid = hex(&self.vid_filter);
+        filter.usage_page = hex(&self.usage_page_filter);
+        filter.controllable = self.controllable_only;
+
+        self.visible = self
+            .devices
+            .iter()
+            .flat_map(|x| &x.interfaces)
+            .filter(|x| filter.matches(x))
+            .map(|x| x.id())
+            .collect();
+    }
 }
 
 impl App for HidLightGui {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if self.open_device.is_none() {
+            if self.last_refresh.elapsed() >= REFRESH_INTERVAL {
+                self.refresh();
+            }
+            ctx.request_repaint_after(REFRESH_INTERVAL);
+        }
         egui::CentralPanel::default().show(ctx, |ui| {
             egui::ScrollArea::vertical().show(ui, |ui| {
                 ui.set_width(ctx.available_rect().width());
                 egui::Grid::new("devicelist").show(ui, |ui| {
-                    if let (Some(dev), Some(reports)) =
-                        (self.open_device.as_ref(), self.reports.as_mut())
-                    {
+                    if let (Some(dev), Some(id), Some(reports)) = (
+                        self.open_device.as_ref(),
+                        self.open_id.as_ref(),
+                        self.reports.as_mut(),
+                    ) {
                         for rep in reports.iter_mut() {
                             let mut changed = false;
+                            // Red, green and blue channels get one color picker per lamp
+                            let lamps = rgb_lamps(std::slice::from_ref(rep));
+                            let grouped: HashSet<_> = lamps.iter().flatten().copied().collect();
                             ui.collapsing(format!("Report {}", rep.id()), |ui| {
+                                for (i, lamp) in lamps.iter().enumerate() {
+                                    ui.label(format!("Color {}", i + 1));
+                                    let mut color = lamp
+                                        .map(|id| (output_value(rep, id) * 255.0).round() as u8);
+                                    if ui.color_edit_button_srgb(&mut color).changed() {
+                                        for (id, value) in lamp.iter().zip(color) {
+                                            if let Some(out) =
+                                                rep.outputs.iter_mut().find(|x| x.id() == *id)
+                                            {
+                                                out.real_value = value as f32 / 255.0;
+                                            }
+                                        }
+                                        changed = true;
+                                    }
+                                    ui.end_row();
+                                }
                                 for out in rep.outputs.iter_mut() {
+                                    if grouped.contains(&out.id()) {
+                                        continue;
+                                    }
                                     if let Some(name) = out.name.as_ref() {
                                         ui.label(name);
                                     } else {
                                         ui.label("Unknown");
                                     }
-                                    if out.is_toggle() {
+                                    if out.is_selector() {
+                                        let label = |i: Option<usize>| {
+                                            i.and_then(|i| out.options().get(i))
+                                                .map(|x| {
+                                                    x.name().unwrap_or_else(|| {
+                                                        format!("0x{:02X}:0x{:02X}", x.page, x.id)
+                                                    })
+                                                })
+                                                .unwrap_or_else(|| "None".into())
+                                        };
+                                        let mut selected = out.selected();
+                                        egui::ComboBox::from_id_salt(out.id())
+                                            .selected_text(label(selected))
+                                            .show_ui(ui, |ui| {
+                                                ui.selectable_value(&mut selected, None, "None");
+                                                for i in 0..out.options().len() {
+                                                    ui.selectable_value(
+                                                        &mut selected,
+                                                        Some(i),
+                                                        label(Some(i)),
+                                                    );
+                                                }
+                                            });
+                                        if selected != out.selected() {
+                                            match selected {
+                                                Some(i) => _ = out.select(i),
+                                                None => out.deselect(),
+                                            }
+                                            changed = true;
+                                        }
+                                    } else if out.is_toggle() {
                                         let mut checked = out.real_value > 0.0;
                                         changed |= ui.checkbox(&mut checked, ()).changed();
                                         out.real_value = checked.then_some(1.0).unwrap_or(0.0);
@@ -39,6 +214,28 @@ impl App for HidLightGui {
                                     }
                                     ui.end_row();
                                 }
+
+                                // What the next write sends, which can be edited and sent
+                                // as is when the descriptor doesn't match the device
+                                let packed = to_hex(&dev.packed_report(rep));
+                                ui.label("Bytes");
+                                ui.monospace(&packed);
+                                ui.end_row();
+                                ui.label("Raw");
+                                let text = self.raw_edits.entry(rep.id()).or_insert(packed);
+                                ui.add(
+                                    egui::TextEdit::singleline(text)
+                                        .font(egui::TextStyle::Monospace),
+                                );
+                                if ui.button("Send").clicked() {
+                                    if let Some(bytes) = from_hex(text) {
+                                        _ = dev.write_raw_report(&bytes);
+                                    }
+                                }
+                                if ui.button("Reset").clicked() {
+                                    self.raw_edits.remove(&rep.id());
+                                }
+                                ui.end_row();
                             });
                             if changed {
                                 _ = dev.write_report(rep);
@@ -46,40 +243,118 @@ impl App for HidLightGui {
                             ui.end_row();
                         }
 
+                        ui.horizontal(|ui| {
+                            ui.text_edit_singleline(&mut self.preset_name);
+                            if ui.button("Save preset").clicked() && !self.preset_name.is_empty() {
+                                self.presets.insert(capture(&self.preset_name, id, reports));
+                                #[cfg(feature = "serde")]
+                                {
+                                    _ = self.presets.save(PRESETS);
+                                }
+                            }
+                        });
+                        ui.end_row();
+                        let names: Vec<String> = self
+                            .presets
+                            .names()
+                            .filter(|x| self.presets.get(x).and_then(|x| x.device(id)).is_some())
+                            .map(String::from)
+                            .collect();
+                        for name in names {
+                            if ui.button(format!("Load {name}")).clicked() {
+                                if let Some(preset) =
+                                    self.presets.get(&name).and_then(|x| x.device(id))
+                                {
+                                    restore(preset, reports);
+                                    for rep in reports.iter() {
+                                        _ = dev.write_report(rep);
+                                    }
+                                }
+                            }
+                            ui.end_row();
+                        }
+
                         if ui.button("Close").clicked() {
                             self.open_device = None;
+                            self.open_id = None;
                             self.reports = None;
+                            self.raw_edits.clear();
                         }
                     } else {
+                        if ui.button("Refresh").clicked() {
+                            self.refresh();
+                        }
+                        ui.end_row();
+                        let mut filter_changed = false;
+                        ui.horizontal(|ui| {
+                            ui.label("Search");
+                            filter_changed |= ui.text_edit_singleline(&mut self.search).changed();
+                            ui.label("VID");
+                            filter_changed |= ui
+                                .add(
+                                    egui::TextEdit::singleline(&mut self.vid_filter)
+                                        .desired_width(40.0),
+                                )
+                                .changed();
+                            ui.label("Usage page");
+                            filter_changed |= ui
+                                .add(
+                                    egui::TextEdit::singleline(&mut self.usage_page_filter)
+                                        .desired_width(40.0),
+                                )
+                                .changed();
+                            filter_changed |= ui
+                                .checkbox(&mut self.controllable_only, "Has outputs")
+                                .changed();
+                        });
+                        if filter_changed {
+                            self.apply_filter();
+                        }
+                        ui.end_row();
                         ui.label("Name");
                         ui.label("Mfg");
                         ui.label("Usage");
                         ui.label("VID/PID");
                         ui.end_row();
-                        for dev in &self.devices {
-                            for ele in [
-                                dev.name.clone(),
-                                dev.manufacturer.clone(),
-                                dev.usage.as_ref().map(|x| x.name()),
-                            ]
-                            .into_iter()
+                        for phys in &self.devices {
+                            if !phys
+                                .interfaces
+                                .iter()
+                                .any(|x| self.visible.contains(&x.id()))
                             {
-                                ui.label(ele.unwrap_or_default());
+                                continue;
                             }
+                            ui.label(phys.name.clone().unwrap_or_default());
+                            ui.label(phys.manufacturer.clone().unwrap_or_default());
+                            ui.label("");
+                            ui.label(format!("{:04x}/{:04x}", phys.vid, phys.pid));
+                            ui.end_row();
 
-                            let vidpid = { format!("{:04x}/{:04x}", dev.vid, dev.pid) };
-                            ui.label(vidpid);
+                            for dev in &phys.interfaces {
+                                if !self.visible.contains(&dev.id()) {
+                                    continue;
+                                }
+                                ui.label("");
+                                ui.label("");
+                                ui.label(dev.usage.as_ref().map(|x| x.name()).unwrap_or_default());
+                                ui.label("");
 
-                            if ui.button("Select").clicked() {
-                                if let Ok(dev) = dev.open() {
-                                    if let Ok(reps) = dev.reports() {
-                                        self.reports = Some(reps);
-                                        self.open_device = Some(dev);
+                                if ui.button("Select").clicked() {
+                                    let id = dev.id();
+                                    if let Ok(dev) = dev.open() {
+                                        if let Ok(mut reps) = dev.reports() {
+                                            for rep in &mut reps {
+                                                rep.resolve_names(&dev);
+                                            }
+                                            self.reports = Some(reps);
+                                            self.open_device = Some(dev);
+                                            self.open_id = Some(id);
+                                        }
                                     }
                                 }
-                            }
 
-                            ui.end_row();
+                                ui.end_row();
+                            }
                         }
                     }
                 })
@@ -94,14 +369,29 @@ fn main() {
         NativeOptions::default(),
         Box::new(|_cc| {
             let api = hidlights::HidLights::new().unwrap();
-            let devices = api.devices();
+            let devices = api.physical_devices();
 
-            Ok(Box::new(HidLightGui {
-                _api: api,
+            let mut gui = HidLightGui {
+                api,
+                last_refresh: Instant::now(),
                 devices,
                 open_device: None,
+                open_id: None,
                 reports: None,
-            }))
+                #[cfg(feature = "serde")]
+                presets: SceneRegistry::load(PRESETS).unwrap_or_default(),
+                #[cfg(not(feature = "serde"))]
+                presets: SceneRegistry::default(),
+                preset_name: String::new(),
+                raw_edits: HashMap::new(),
+                search: String::new(),
+                vid_filter: String::new(),
+                usage_page_filter: String::new(),
+                controllable_only: false,
+                visible: HashSet::new(),
+            };
+            gui.apply_filter();
+            Ok(Box::new(gui))
         }),
     )
     .unwrap();
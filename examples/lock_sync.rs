@@ -0,0 +1,36 @@
+//! Mirrors the host's Caps, Num and Scroll Lock onto another device's indicators, e.g.
+//! a controller or macro pad with LED usages.
+//!
+//! `cargo run --example lock_sync -- <vid>:<pid>`
+//!
+//! The host state comes from [`HostLocks::read`], which only works on Linux.
+
+use std::{thread, time::Duration};
+
+use hidlights::{HidLightError, HidLights, HostLocks};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let target = std::env::args()
+        .nth(1)
+        .ok_or("usage: lock_sync <vid>:<pid>")?;
+    let (vid, pid) = target.split_once(':').ok_or("expected <vid>:<pid>")?;
+    let mut handle =
+        HidLights::new()?.open(u16::from_str_radix(vid, 16)?, u16::from_str_radix(pid, 16)?)?;
+
+    let mut last = None;
+    loop {
+        let locks = HostLocks::read()?;
+        if last != Some(locks) {
+            for (led, on) in locks.leds() {
+                match handle.set_led(led, on) {
+                    Ok(()) => println!("{led:?}: {}", if on { "on" } else { "off" }),
+                    // Devices often have only some of the three
+                    Err(HidLightError::UnsupportedDevice) => {}
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            last = Some(locks);
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+}
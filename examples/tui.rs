@@ -0,0 +1,223 @@
+//! The GUI example in a terminal, for headless machines and SSH sessions.
+//!
+//! Up and down pick a row and enter opens a device. Left and right change an output,
+//! holding shift for finer steps, space flips toggles, escape closes the device and
+//! q quits. Changes are written as they're made.
+
+use hidlights::{DeviceHandle, DeviceInfo, DeviceOutput, HidLights, Report};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    widgets::{Block, List, ListState, Paragraph},
+    DefaultTerminal, Frame,
+};
+
+struct OpenDevice {
+    title: String,
+    handle: DeviceHandle,
+    reports: Vec<Report>,
+    /// Report and output index of each row.
+    rows: Vec<(usize, usize)>,
+    list: ListState,
+}
+
+struct HidLightTui {
+    _api: HidLights,
+    /// Each interface of each physical device, with its label.
+    devices: Vec<(String, DeviceInfo)>,
+    list: ListState,
+    open_device: Option<OpenDevice>,
+    /// The last error, shown until the next key.
+    status: Option<String>,
+}
+
+impl HidLightTui {
+    fn new(api: HidLights) -> Self {
+        let mut devices = vec![];
+        for phys in api.physical_devices() {
+            let name = phys.name.unwrap_or_else(|| "Unknown".into());
+            for dev in phys.interfaces {
+                let usage = dev.usage.as_ref().map(|x| x.name()).unwrap_or_default();
+                let label = format!("{name:<32} {:04x}/{:04x}  {usage}", phys.vid, phys.pid);
+                devices.push((label, dev));
+            }
+        }
+        Self {
+            _api: api,
+            devices,
+            list: ListState::default().with_selected(Some(0)),
+            open_device: None,
+            status: None,
+        }
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> std::io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            self.status = None;
+            let step = if key.modifiers.contains(KeyModifiers::SHIFT) {
+                0.01
+            } else {
+                0.05
+            };
+
+            match (&mut self.open_device, key.code) {
+                (_, KeyCode::Char('q')) => return Ok(()),
+                (None, KeyCode::Up) => self.list.select_previous(),
+                (None, KeyCode::Down) => self.list.select_next(),
+                (None, KeyCode::Enter) => self.open(),
+                (Some(_), KeyCode::Esc) => self.open_device = None,
+                (Some(dev), KeyCode::Up) => dev.list.select_previous(),
+                (Some(dev), KeyCode::Down) => dev.list.select_next(),
+                (Some(dev), KeyCode::Left) => self.status = dev.change(|x| adjust(x, -step)),
+                (Some(dev), KeyCode::Right) => self.status = dev.change(|x| adjust(x, step)),
+                (Some(dev), KeyCode::Char(' ')) => self.status = dev.change(flip),
+                _ => {}
+            }
+        }
+    }
+
+    fn open(&mut self) {
+        let Some((label, info)) = self.list.selected().and_then(|x| self.devices.get(x)) else {
+            return;
+        };
+        let result = info.open().and_then(|handle| {
+            let mut reports = handle.reports()?;
+            for report in &mut reports {
+                report.resolve_names(&handle);
+            }
+            Ok((handle, reports))
+        });
+        match result {
+            Ok((handle, reports)) => {
+                let rows = reports
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(r, rep)| (0..rep.outputs.len()).map(move |o| (r, o)))
+                    .collect();
+                self.open_device = Some(OpenDevice {
+                    title: label.trim_end().into(),
+                    handle,
+                    reports,
+                    rows,
+                    list: ListState::default().with_selected(Some(0)),
+                });
+            }
+            Err(err) => self.status = Some(err.to_string()),
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let highlight = Style::new().add_modifier(Modifier::REVERSED);
+
+        if let Some(dev) = &mut self.open_device {
+            let rows = dev.rows.iter().map(|&(r, o)| {
+                let rep = &dev.reports[r];
+                format!("Report {:<3} {}", rep.id(), describe(&rep.outputs[o]))
+            });
+            let list = List::new(rows)
+                .block(Block::bordered().title(dev.title.as_str()))
+                .highlight_style(highlight);
+            frame.render_stateful_widget(list, main, &mut dev.list);
+        } else {
+            let list = List::new(self.devices.iter().map(|x| x.0.as_str()))
+                .block(Block::bordered().title("HID Lights"))
+                .highlight_style(highlight);
+            frame.render_stateful_widget(list, main, &mut self.list);
+        }
+
+        let help = match &self.open_device {
+            Some(_) => "←/→ change  space toggle  esc close  q quit",
+            None => "↑/↓ select  enter open  q quit",
+        };
+        frame.render_widget(
+            Paragraph::new(self.status.as_deref().unwrap_or(help)),
+            status,
+        );
+    }
+}
+
+impl OpenDevice {
+    /// Changes the selected output and writes its report, returning any error.
+    fn change(&mut self, f: impl FnOnce(&mut DeviceOutput)) -> Option<String> {
+        let &(r, o) = self.list.selected().and_then(|x| self.rows.get(x))?;
+        f(&mut self.reports[r].outputs[o]);
+        self.handle
+            .write_report(&self.reports[r])
+            .err()
+            .map(|x| x.to_string())
+    }
+}
+
+fn option_label(out: &DeviceOutput, i: Option<usize>) -> String {
+    i.and_then(|i| out.options().get(i))
+        .map(|x| {
+            x.name()
+                .unwrap_or_else(|| format!("0x{:02X}:0x{:02X}", x.page, x.id))
+        })
+        .unwrap_or_else(|| "None".into())
+}
+
+fn describe(out: &DeviceOutput) -> String {
+    let name = out.name.as_deref().unwrap_or("Unknown");
+    let value = if out.is_selector() {
+        format!("< {} >", option_label(out, out.selected()))
+    } else if out.is_toggle() {
+        let checked = if out.real_value > 0.0 { "[x]" } else { "[ ]" };
+        checked.into()
+    } else {
+        let filled = (out.real_value.clamp(0.0, 1.0) * 20.0).round() as usize;
+        format!(
+            "{}{} {:.2}",
+            "█".repeat(filled),
+            "░".repeat(20 - filled),
+            out.real_value
+        )
+    };
+    format!("{name:<32} {value}")
+}
+
+/// Moves a slider by `step`, or a selector to its next or previous option.
+fn adjust(out: &mut DeviceOutput, step: f32) {
+    if out.is_selector() {
+        // Position 0 is none selected, then one past each option
+        let count = out.options().len() + 1;
+        let position = out.selected().map_or(0, |x| x + 1);
+        let position = if step > 0.0 {
+            (position + 1) % count
+        } else {
+            (position + count - 1) % count
+        };
+        match position {
+            0 => out.deselect(),
+            x => _ = out.select(x - 1),
+        }
+    } else if out.is_toggle() {
+        out.real_value = if step > 0.0 { 1.0 } else { 0.0 };
+    } else {
+        out.real_value = (out.real_value + step).clamp(0.0, 1.0);
+    }
+}
+
+fn flip(out: &mut DeviceOutput) {
+    if out.is_toggle() {
+        out.real_value = if out.real_value <= 0.0 { 1.0 } else { 0.0 };
+    }
+}
+
+fn main() -> std::io::Result<()> {
+    let mut app = HidLightTui::new(HidLights::new().unwrap());
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+    result
+}
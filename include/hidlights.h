@@ -0,0 +1,109 @@
+#ifndef HIDLIGHTS_H
+#define HIDLIGHTS_H
+
+/* Generated by cbindgen from src/capi.rs, don't edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The library context, with a snapshot of the enumerated devices.
+typedef struct HidLightsContext HidLightsContext;
+
+// An open device.
+typedef struct HidLightsDevice HidLightsDevice;
+
+// Identifies an enumerated device.
+typedef struct HidLightsDeviceInfo {
+  uint16_t vid;
+  uint16_t pid;
+  uint16_t usage_page;
+  uint16_t usage_id;
+  int32_t interface_number;
+} HidLightsDeviceInfo;
+
+// The last error on this thread, or null. Valid until the next call on this thread.
+const char *hidlights_last_error(void);
+
+// Creates a context and enumerates devices. Null on failure.
+HidLightsContext *hidlights_new(void);
+
+// # Safety
+//
+// `context` must come from `hidlights_new`, or be null.
+void hidlights_free(HidLightsContext *context);
+
+// Enumerates devices again, setting `count` to how many there are. Device indices
+// refer to this enumeration until the next, on failure the previous one stays.
+//
+// # Safety
+//
+// `context` must come from `hidlights_new` and `count` be writable, or null.
+int32_t hidlights_refresh(HidLightsContext *context, size_t *count);
+
+// # Safety
+//
+// `context` must come from `hidlights_new`.
+size_t hidlights_device_count(const HidLightsContext *context);
+
+// # Safety
+//
+// `context` must come from `hidlights_new` and `info` be writable.
+int32_t hidlights_device_info(const HidLightsContext *context,
+                              size_t index,
+                              HidLightsDeviceInfo *info);
+
+// Copies a device's product name, empty if it has none.
+//
+// # Safety
+//
+// `context` must come from `hidlights_new` and `buffer` hold `len` bytes.
+size_t hidlights_device_name(const HidLightsContext *context,
+                             size_t index,
+                             char *buffer,
+                             size_t len);
+
+// Opens a device. Null on failure.
+//
+// # Safety
+//
+// `context` must come from `hidlights_new`.
+HidLightsDevice *hidlights_open(const HidLightsContext *context, size_t index);
+
+// # Safety
+//
+// `device` must come from `hidlights_open`, or be null.
+void hidlights_close(HidLightsDevice *device);
+
+// # Safety
+//
+// `device` must come from `hidlights_open`.
+size_t hidlights_output_count(const HidLightsDevice *device);
+
+// Copies an output's name, empty if it has none.
+//
+// # Safety
+//
+// `device` must come from `hidlights_open` and `buffer` hold `len` bytes.
+size_t hidlights_output_name(const HidLightsDevice *device,
+                             size_t output,
+                             char *buffer,
+                             size_t len);
+
+// Sets an output as a fraction of its range, sent on the next `hidlights_write`.
+//
+// # Safety
+//
+// `device` must come from `hidlights_open`.
+int32_t hidlights_set(HidLightsDevice *device, size_t output, float value);
+
+// Writes every output changed since the last write.
+//
+// # Safety
+//
+// `device` must come from `hidlights_open`.
+int32_t hidlights_write(HidLightsDevice *device);
+
+#endif /* HIDLIGHTS_H */
@@ -0,0 +1,160 @@
+//! Bias lighting: lamps take the color of the screen edge nearest them, as a strip
+//! behind a monitor would.
+//!
+//! Lamp positions come from the surface, as fractions of its bounds, and are read as
+//! fractions of the screen. A strip laid around the back of a monitor lines up
+//! without any mapping.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use crate::{
+    color::Rgb,
+    surface::{normalized_positions, LightSurface},
+    HidLightError, Result, Ticker,
+};
+
+/// Pixels sampled across each side of a region, so big screens cost the same as small.
+const SAMPLES: u32 = 16;
+
+/// Average color of the screen edge nearest each position, from RGBA pixels. `depth`
+/// is how far into the screen the edge reaches, as a fraction of its size.
+pub fn edge_colors(
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+    positions: &[[f32; 3]],
+    depth: f32,
+) -> Vec<Rgb> {
+    if width == 0 || height == 0 || pixels.len() < (width * height * 4) as usize {
+        return vec![Rgb::default(); positions.len()];
+    }
+    let depth = depth.clamp(0.01, 0.5);
+    // Each lamp covers its share of the edge, so neighbours blend into each other
+    let span = (1.0 / positions.len().max(1) as f32 * 4.0).clamp(0.02, 0.5);
+
+    positions
+        .iter()
+        .map(|&[x, y, _]| {
+            let (x, y) = (x.clamp(0.0, 1.0), y.clamp(0.0, 1.0));
+            let distances = [x, 1.0 - x, y, 1.0 - y];
+            let nearest = (0..4)
+                .min_by(|a, b| distances[*a].total_cmp(&distances[*b]))
+                .unwrap_or_default();
+            // The region as fractions of the screen, x0..x1 by y0..y1
+            let along = |center: f32| {
+                (
+                    (center - span / 2.0).max(0.0),
+                    (center + span / 2.0).min(1.0),
+                )
+            };
+            let ((x0, x1), (y0, y1)) = match nearest {
+                0 => ((0.0, depth), along(y)),
+                1 => ((1.0 - depth, 1.0), along(y)),
+                2 => (along(x), (0.0, depth)),
+                _ => (along(x), (1.0 - depth, 1.0)),
+            };
+            average(pixels, width, height, [x0, x1], [y0, y1])
+        })
+        .collect()
+}
+
+fn average(pixels: &[u8], width: u32, height: u32, x: [f32; 2], y: [f32; 2]) -> Rgb {
+    let mut sum = [0u32; 3];
+    for j in 0..SAMPLES {
+        for i in 0..SAMPLES {
+            let at = |range: [f32; 2], step: u32, size: u32| {
+                let t = range[0] + (range[1] - range[0]) * (step as f32 + 0.5) / SAMPLES as f32;
+                ((t * size as f32) as u32).min(size - 1)
+            };
+            let offset = ((at(y, j, height) * width + at(x, i, width)) * 4) as usize;
+            for (sum, value) in sum.iter_mut().zip(&pixels[offset..offset + 3]) {
+                *sum += *value as u32;
+            }
+        }
+    }
+    let [r, g, b] = sum.map(|x| (x / (SAMPLES * SAMPLES)) as u8);
+    Rgb::new(r, g, b)
+}
+
+/// Captures a monitor and lights a surface from its edges.
+pub struct ScreenAmbient {
+    monitor: xcap::Monitor,
+    depth: f32,
+    smoothing: f32,
+    last: Vec<Rgb>,
+}
+
+impl ScreenAmbient {
+    /// Captures the `index`th monitor, in the order the platform lists them.
+    pub fn new(index: usize) -> Result<Self> {
+        let monitor = xcap::Monitor::all()
+            .map_err(capture_error)?
+            .into_iter()
+            .nth(index)
+            .ok_or(HidLightError::InvalidArgument)?;
+        Ok(Self {
+            monitor,
+            depth: 0.1,
+            smoothing: 0.0,
+            last: vec![],
+        })
+    }
+
+    /// How far into the screen the edges reach, as a fraction of its size. 0.1 by
+    /// default.
+    pub fn depth(mut self, depth: f32) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// How much of the last frame's color each frame keeps, 0 to 1, to calm flicker
+    /// from fast moving content.
+    pub fn smoothing(mut self, smoothing: f32) -> Self {
+        self.smoothing = smoothing.clamp(0.0, 0.99);
+        self
+    }
+
+    /// Captures the screen once and presents the surface.
+    pub fn update(&mut self, surface: &mut dyn LightSurface) -> Result<()> {
+        let image = self.monitor.capture_image().map_err(capture_error)?;
+        let positions = normalized_positions(surface);
+        let colors = edge_colors(
+            image.as_raw(),
+            image.width(),
+            image.height(),
+            &positions,
+            self.depth,
+        );
+
+        if self.last.len() != colors.len() {
+            self.last = colors.clone();
+        }
+        for (lamp, (last, color)) in self.last.iter_mut().zip(colors).enumerate() {
+            *last = color.lerp(*last, self.smoothing);
+            surface.set(lamp, *last);
+        }
+        surface.present()
+    }
+
+    /// Updates the surface `fps` times a second until `stop` is set.
+    pub fn run(
+        &mut self,
+        surface: &mut dyn LightSurface,
+        fps: f32,
+        stop: &Arc<AtomicBool>,
+    ) -> Result<()> {
+        let mut ticker = Ticker::new(fps);
+        while !stop.load(Ordering::Relaxed) {
+            self.update(surface)?;
+            ticker.tick();
+        }
+        Ok(())
+    }
+}
+
+fn capture_error(err: impl std::fmt::Display) -> HidLightError {
+    HidLightError::Capture(err.to_string())
+}
@@ -0,0 +1,339 @@
+//! Lights that follow sound: audio is split into frequency bands whose energy drives
+//! effects, like a VU meter across button LEDs or a flash on every beat.
+//!
+//! [`AudioCapture`] records the default input and keeps [`Bands`] up to date.
+//! [`BandLevel`] and [`BeatFlash`] are [`Effect`]s reading them, played by a
+//! [`Player`] like any other effect.
+
+use std::{
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    Sample,
+};
+use rustfft::{num_complex::Complex, Fft, FftPlanner};
+
+use crate::{
+    effects::{Effect, LayerId, Player},
+    DeviceHandle, HidLightError, OutputId, Result,
+};
+
+const FFT_SIZE: usize = 1024;
+/// Samples between analyses, so frames overlap by half.
+const HOP: usize = FFT_SIZE / 2;
+const LOWEST: f32 = 40.0;
+const HIGHEST: f32 = 16_000.0;
+/// A band beats when it jumps this far over its recent average.
+const BEAT_RATIO: f32 = 1.6;
+
+/// The latest energy of each band, 0 to 1, and when each last beat. Clones share the
+/// same values.
+#[derive(Debug, Clone)]
+pub struct Bands {
+    levels: Arc<[AtomicU32]>,
+    beats: Arc<[AtomicU64]>,
+    epoch: Instant,
+}
+
+impl Bands {
+    fn new(count: usize) -> Self {
+        Self {
+            levels: (0..count).map(|_| AtomicU32::new(0)).collect(),
+            beats: (0..count).map(|_| AtomicU64::new(u64::MAX)).collect(),
+            epoch: Instant::now(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    /// A band's energy, lowest frequencies first. 0 for bands out of range.
+    pub fn level(&self, band: usize) -> f32 {
+        self.levels
+            .get(band)
+            .map_or(0.0, |x| f32::from_bits(x.load(Ordering::Relaxed)))
+    }
+
+    pub fn levels(&self) -> Vec<f32> {
+        (0..self.len()).map(|x| self.level(x)).collect()
+    }
+
+    /// Time since a band last beat, `None` if it never has.
+    pub fn since_beat(&self, band: usize) -> Option<Duration> {
+        let beat = self.beats.get(band)?.load(Ordering::Relaxed);
+        (beat != u64::MAX).then(|| {
+            self.epoch
+                .elapsed()
+                .saturating_sub(Duration::from_nanos(beat))
+        })
+    }
+}
+
+/// Splits mono audio into log spaced bands from 40Hz to 16kHz.
+pub struct BandAnalyzer {
+    fft: Arc<dyn Fft<f32>>,
+    /// FFT bins covered by each band.
+    bins: Vec<std::ops::Range<usize>>,
+    window: Vec<f32>,
+    samples: Vec<f32>,
+    /// Loudest recent energy per band, levels are relative to it.
+    peaks: Vec<f32>,
+    averages: Vec<f32>,
+    bands: Bands,
+}
+
+impl BandAnalyzer {
+    pub fn new(sample_rate: u32, band_count: usize) -> Self {
+        let band_count = band_count.max(1);
+        let bin_hz = sample_rate as f32 / FFT_SIZE as f32;
+        let edge = |i: usize| {
+            let hz = LOWEST * (HIGHEST / LOWEST).powf(i as f32 / band_count as f32);
+            ((hz / bin_hz) as usize).clamp(1, FFT_SIZE / 2)
+        };
+        let bins = (0..band_count)
+            .map(|i| edge(i)..edge(i + 1).max(edge(i) + 1))
+            .collect();
+        // Hann window, to keep loud bands from smearing into their neighbours
+        let window = (0..FFT_SIZE)
+            .map(|i| 0.5 - 0.5 * (std::f32::consts::TAU * i as f32 / FFT_SIZE as f32).cos())
+            .collect();
+
+        Self {
+            fft: FftPlanner::new().plan_fft_forward(FFT_SIZE),
+            bins,
+            window,
+            samples: Vec::with_capacity(FFT_SIZE * 2),
+            peaks: vec![1e-3; band_count],
+            averages: vec![0.0; band_count],
+            bands: Bands::new(band_count),
+        }
+    }
+
+    pub fn bands(&self) -> Bands {
+        self.bands.clone()
+    }
+
+    /// Adds mono samples, updating the bands for every full frame.
+    pub fn push(&mut self, samples: &[f32]) {
+        self.samples.extend_from_slice(samples);
+        while self.samples.len() >= FFT_SIZE {
+            self.analyze();
+            self.samples.drain(..HOP);
+        }
+    }
+
+    fn analyze(&mut self) {
+        let mut spectrum: Vec<_> = self.samples[..FFT_SIZE]
+            .iter()
+            .zip(&self.window)
+            .map(|(x, w)| Complex::new(x * w, 0.0))
+            .collect();
+        self.fft.process(&mut spectrum);
+
+        let now = self.bands.epoch.elapsed().as_nanos() as u64;
+        for (band, bins) in self.bins.iter().enumerate() {
+            let energy = (spectrum[bins.clone()]
+                .iter()
+                .map(|x| x.norm_sqr())
+                .sum::<f32>()
+                / bins.len() as f32)
+                .sqrt();
+
+            // Peaks fall slowly, so quiet passages still move the lights
+            let peak = &mut self.peaks[band];
+            *peak = energy.max(*peak * 0.998).max(1e-3);
+            let level = (energy / *peak).clamp(0.0, 1.0);
+
+            let average = &mut self.averages[band];
+            if level > 0.3 && level > *average * BEAT_RATIO {
+                self.bands.beats[band].store(now, Ordering::Relaxed);
+            }
+            *average = *average * 0.9 + level * 0.1;
+
+            // Rise at once, fall gradually
+            let last = self.bands.level(band);
+            let level = level.max(last * 0.85);
+            self.bands.levels[band].store(level.to_bits(), Ordering::Relaxed);
+        }
+    }
+}
+
+/// Records the default audio input into a [`BandAnalyzer`] until dropped.
+///
+/// Audio streams can't be moved between threads on every platform, so keep this on
+/// the thread that created it and hand out [`Self::bands`].
+pub struct AudioCapture {
+    _stream: cpal::Stream,
+    bands: Bands,
+}
+
+impl AudioCapture {
+    pub fn default_input(band_count: usize) -> Result<Self> {
+        let device = cpal::default_host()
+            .default_input_device()
+            .ok_or_else(|| audio_error("no audio input"))?;
+        let config = device.default_input_config().map_err(audio_error)?;
+        let analyzer = BandAnalyzer::new(config.sample_rate().0, band_count);
+        let bands = analyzer.bands();
+
+        let stream = match config.sample_format() {
+            cpal::SampleFormat::F32 => build::<f32>(&device, &config.into(), analyzer),
+            cpal::SampleFormat::I16 => build::<i16>(&device, &config.into(), analyzer),
+            cpal::SampleFormat::U16 => build::<u16>(&device, &config.into(), analyzer),
+            format => Err(audio_error(format_args!(
+                "unsupported sample format {format:?}"
+            ))),
+        }?;
+        stream.play().map_err(audio_error)?;
+
+        Ok(Self {
+            _stream: stream,
+            bands,
+        })
+    }
+
+    pub fn bands(&self) -> Bands {
+        self.bands.clone()
+    }
+}
+
+fn build<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mut analyzer: BandAnalyzer,
+) -> Result<cpal::Stream>
+where
+    T: cpal::SizedSample,
+    f32: cpal::FromSample<T>,
+{
+    let channels = config.channels.max(1) as usize;
+    let mut mono = vec![];
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _: &cpal::InputCallbackInfo| {
+                mono.clear();
+                mono.extend(data.chunks(channels).map(|frame| {
+                    frame.iter().map(|x| x.to_sample::<f32>()).sum::<f32>() / channels as f32
+                }));
+                analyzer.push(&mono);
+            },
+            |_err| {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(%_err, "audio input failed");
+            },
+            None,
+        )
+        .map_err(audio_error)
+}
+
+/// Follows a band's energy, times `gain`.
+#[derive(Debug, Clone)]
+pub struct BandLevel {
+    bands: Bands,
+    band: usize,
+    gain: f32,
+    threshold: Option<f32>,
+}
+
+impl BandLevel {
+    pub fn new(bands: &Bands, band: usize) -> Self {
+        Self {
+            bands: bands.clone(),
+            band,
+            gain: 1.0,
+            threshold: None,
+        }
+    }
+
+    pub fn gain(mut self, gain: f32) -> Self {
+        self.gain = gain;
+        self
+    }
+
+    /// Fully on while the level is at least `threshold`, off otherwise.
+    pub fn threshold(mut self, threshold: f32) -> Self {
+        self.threshold = Some(threshold);
+        self
+    }
+}
+
+impl Effect for BandLevel {
+    fn sample(&self, _t: Duration, _initial: f32) -> Option<f32> {
+        let level = (self.bands.level(self.band) * self.gain).clamp(0.0, 1.0);
+        Some(match self.threshold {
+            Some(threshold) => (level >= threshold) as u8 as f32,
+            None => level,
+        })
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Full on every beat in a band, fading out over `decay`.
+#[derive(Debug, Clone)]
+pub struct BeatFlash {
+    bands: Bands,
+    band: usize,
+    decay: Duration,
+}
+
+impl BeatFlash {
+    pub fn new(bands: &Bands, band: usize, decay: Duration) -> Self {
+        Self {
+            bands: bands.clone(),
+            band,
+            decay,
+        }
+    }
+}
+
+impl Effect for BeatFlash {
+    fn sample(&self, _t: Duration, _initial: f32) -> Option<f32> {
+        let since = self.bands.since_beat(self.band).unwrap_or(Duration::MAX);
+        let fade = 1.0 - since.as_secs_f32() / self.decay.as_secs_f32().max(f32::EPSILON);
+        Some(fade.max(0.0))
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl Player {
+    /// Plays a VU meter of a band across `outputs`, the first lighting first.
+    pub fn add_vu_meter(
+        &mut self,
+        handle: &mut DeviceHandle,
+        outputs: &[OutputId],
+        bands: &Bands,
+        band: usize,
+    ) -> Result<Vec<LayerId>> {
+        outputs
+            .iter()
+            .enumerate()
+            .map(|(i, output)| {
+                let threshold = (i + 1) as f32 / (outputs.len() + 1) as f32;
+                let effect = BandLevel::new(bands, band).threshold(threshold);
+                self.add(handle, std::slice::from_ref(output), effect)
+            })
+            .collect()
+    }
+}
+
+fn audio_error(err: impl std::fmt::Display) -> HidLightError {
+    HidLightError::Audio(err.to_string())
+}
@@ -0,0 +1,355 @@
+//! Testing hardware and scripting lights from the shell.
+//!
+//! Devices are given as their index in `hidlights list`, as `vid:pid` in hex, or as
+//! their full ID. Outputs are given as their index, name or key, see
+//! [`DeviceHandle::find_output`].
+//!
+//! With `--json`, `list` and `outputs` print a JSON array of [`DeviceJson`] or
+//! [`OutputJson`]. Fields may be added, but won't be renamed or removed.
+//!
+//! `hidlights watch` prints devices as they come and go, which is the first thing to
+//! check when a device isn't detected.
+
+use std::{
+    collections::{HashMap, HashSet},
+    process::ExitCode,
+    time::{Duration, Instant},
+};
+
+use clap::{Parser, Subcommand};
+use hidlights::{
+    descriptor, input::InputField, DeviceHandle, DeviceId, DeviceInfo, DeviceOutput,
+    DeviceOutputValue, HidLightError, HidLights,
+};
+use serde::Serialize;
+
+#[derive(Parser)]
+#[command(version, about = "Control lighting peripherals over USB HID")]
+struct Cli {
+    /// Print `list` and `outputs` as JSON.
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List HID devices.
+    List,
+    /// List a device's outputs and their values.
+    Outputs { device: String },
+    /// Set an output, as a fraction of its range.
+    Set {
+        device: String,
+        output: String,
+        value: f32,
+    },
+    /// Turn off every output of a device, or of every device that looks like a light.
+    Off { device: Option<String> },
+    /// Print a device's report descriptor.
+    DumpDescriptor {
+        device: String,
+        /// Print the parsed reports instead of hex.
+        #[arg(long)]
+        tree: bool,
+    },
+    /// Print devices as they're plugged in and out, until interrupted.
+    Watch {
+        /// Also print a device's input values as they change.
+        #[arg(long)]
+        inputs: Option<String>,
+        /// How often to look for devices, in milliseconds.
+        #[arg(long, default_value_t = 500)]
+        interval: u64,
+    },
+}
+
+#[derive(Serialize)]
+struct DeviceJson {
+    index: usize,
+    id: String,
+    vid: u16,
+    pid: u16,
+    usage_page: u16,
+    usage_id: u16,
+    interface_number: i32,
+    name: Option<String>,
+    manufacturer: Option<String>,
+    serial: Option<String>,
+}
+
+impl DeviceJson {
+    fn new(index: usize, device: &DeviceInfo) -> Self {
+        Self {
+            index,
+            id: device.id().to_string(),
+            vid: device.vid,
+            pid: device.pid,
+            usage_page: device.usage_page,
+            usage_id: device.usage_id,
+            interface_number: device.interface_number,
+            name: device.name.clone(),
+            manufacturer: device.manufacturer.clone(),
+            serial: device.serial.clone(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct OutputJson {
+    index: usize,
+    key: String,
+    name: Option<String>,
+    /// `toggle`, `signed`, `unsigned` or `selector`.
+    kind: &'static str,
+    logical_minimum: i32,
+    logical_maximum: i32,
+    /// As a fraction of the logical range.
+    value: f32,
+}
+
+impl OutputJson {
+    fn new(index: usize, output: &DeviceOutput) -> Self {
+        let range = output.logical_range();
+        Self {
+            index,
+            key: output.key().to_string(),
+            name: output.name.clone(),
+            kind: match output.kind() {
+                DeviceOutputValue::Toggle => "toggle",
+                DeviceOutputValue::Signed(_) => "signed",
+                DeviceOutputValue::Unsigned(_) => "unsigned",
+                DeviceOutputValue::Selector { .. } => "selector",
+            },
+            logical_minimum: *range.start(),
+            logical_maximum: *range.end(),
+            value: output.real_value,
+        }
+    }
+}
+
+fn print_json(value: &impl Serialize) -> Result<(), HidLightError> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+fn find_device(api: &HidLights, selector: &str) -> Result<DeviceInfo, HidLightError> {
+    let devices = api.devices();
+    let found = if let Ok(index) = selector.parse::<usize>() {
+        devices.into_iter().nth(index)
+    } else if let Ok(id) = selector.parse::<DeviceId>() {
+        devices.into_iter().find(|x| x.id() == id)
+    } else {
+        let (vid, pid) = selector
+            .split_once(':')
+            .ok_or(HidLightError::InvalidArgument)?;
+        let hex = |x: &str| u16::from_str_radix(x, 16).map_err(|_| HidLightError::InvalidArgument);
+        let (vid, pid) = (hex(vid)?, hex(pid)?);
+        devices.into_iter().find(|x| x.vid == vid && x.pid == pid)
+    };
+    found.ok_or(HidLightError::DeviceNotFound)
+}
+
+fn turn_off(handle: &mut DeviceHandle) -> Result<(), HidLightError> {
+    let ids: Vec<_> = handle.outputs()?.map(|x| x.id()).collect();
+    for id in ids {
+        handle.set(id, 0.0)?;
+    }
+    handle.flush()
+}
+
+fn describe(device: &DeviceInfo) -> String {
+    format!(
+        "{:04x}:{:04x}  usage {:04x}:{:04x}  if {:<2}  {}  {}",
+        device.vid,
+        device.pid,
+        device.usage_page,
+        device.usage_id,
+        device.interface_number,
+        device.name.as_deref().unwrap_or("(unnamed)"),
+        device.id(),
+    )
+}
+
+/// An open device whose input values are printed as they change.
+struct InputWatch {
+    id: DeviceId,
+    handle: DeviceHandle,
+    fields: Vec<InputField>,
+    numbered: bool,
+    values: HashMap<usize, i32>,
+}
+
+impl InputWatch {
+    fn open(device: &DeviceInfo) -> Result<Self, HidLightError> {
+        let handle = device.open()?;
+        Ok(Self {
+            id: device.id(),
+            fields: handle.inputs()?,
+            numbered: handle.numbered_inputs()?,
+            handle,
+            values: HashMap::new(),
+        })
+    }
+
+    /// Reads one report, waiting up to `timeout_ms`, and prints what changed.
+    fn poll(&mut self, timeout_ms: i32) -> Result<(), HidLightError> {
+        let Some(data) = self.handle.read_input(timeout_ms)? else {
+            return Ok(());
+        };
+        let quirks = self.handle.quirks();
+        for (index, field) in self.fields.iter().enumerate() {
+            let Some(value) = field.decode(&data, self.numbered, &quirks) else {
+                continue;
+            };
+            if self.values.insert(index, value) != Some(value) {
+                println!("  {} = {value}", field.name);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn watch(api: &HidLights, inputs: Option<String>, interval: Duration) -> Result<(), HidLightError> {
+    // Resolved once, so the same device is watched again after it's replugged
+    let input_id = inputs
+        .map(|x| find_device(api, &x).map(|x| x.id()))
+        .transpose()?;
+    let mut input_watch = None;
+    let mut known = HashSet::new();
+    let mut first = true;
+
+    loop {
+        api.refresh()?;
+        let devices = api.devices();
+        let ids: HashSet<_> = devices.iter().map(|x| x.id()).collect();
+        for device in &devices {
+            if !known.contains(&device.id()) {
+                let event = if first { "present" } else { "added" };
+                println!("{event:<8} {}", describe(device));
+            }
+        }
+        for id in known.difference(&ids) {
+            println!("{:<8} {id}", "removed");
+        }
+        known = ids;
+        first = false;
+
+        if let Some(id) = &input_id {
+            if input_watch.is_none() {
+                if let Some(device) = devices.iter().find(|x| x.id() == *id) {
+                    match InputWatch::open(device) {
+                        Ok(x) => input_watch = Some(x),
+                        Err(err) => eprintln!("{id}: {err}"),
+                    }
+                }
+            }
+        }
+
+        // Read inputs between enumerations, or just wait when there are none to read
+        let next = Instant::now() + interval;
+        while let Some(left) = next.checked_duration_since(Instant::now()) {
+            let Some(watch) = &mut input_watch else {
+                std::thread::sleep(left);
+                break;
+            };
+            if let Err(err) = watch.poll(left.as_millis().min(100) as i32) {
+                // Reopened on the next enumeration that finds it
+                eprintln!("{}: {err}", watch.id);
+                input_watch = None;
+            }
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<(), HidLightError> {
+    let api = HidLights::new()?;
+
+    match cli.command {
+        Command::List if cli.json => {
+            let devices: Vec<_> = api
+                .devices()
+                .iter()
+                .enumerate()
+                .map(|(index, x)| DeviceJson::new(index, x))
+                .collect();
+            print_json(&devices)?;
+        }
+        Command::List => {
+            for (index, device) in api.devices().iter().enumerate() {
+                println!("{index:>3}  {}", describe(device));
+            }
+        }
+        Command::Outputs { device } => {
+            let mut handle = find_device(&api, &device)?.open()?;
+            handle.resolve_names()?;
+            if cli.json {
+                let outputs: Vec<_> = handle
+                    .outputs()?
+                    .enumerate()
+                    .map(|(index, x)| OutputJson::new(index, x))
+                    .collect();
+                return print_json(&outputs);
+            }
+            for (index, output) in handle.outputs()?.enumerate() {
+                println!(
+                    "{index:>3}  {:<24}  {:.3}  {}",
+                    output.name.as_deref().unwrap_or("(unnamed)"),
+                    output.real_value,
+                    output.key(),
+                );
+            }
+        }
+        Command::Set {
+            device,
+            output,
+            value,
+        } => {
+            let mut handle = find_device(&api, &device)?.open()?;
+            let id = handle
+                .find_output(&output)?
+                .ok_or(HidLightError::InvalidArgument)?;
+            handle.set(id, value)?;
+            handle.flush()?;
+        }
+        Command::Off {
+            device: Some(device),
+        } => {
+            turn_off(&mut find_device(&api, &device)?.open()?)?;
+        }
+        Command::Off { device: None } => {
+            // Only likely lights, zeroing power devices or force feedback does harm.
+            // Devices that can't be opened, or have no outputs, are nothing to turn off
+            for device in api.light_devices() {
+                if let Ok(mut handle) = device.open() {
+                    if let Err(err) = turn_off(&mut handle) {
+                        eprintln!("{}: {err}", device.name.as_deref().unwrap_or("(unnamed)"));
+                    }
+                }
+            }
+        }
+        Command::DumpDescriptor { device, tree } => {
+            let bytes = find_device(&api, &device)?.open()?.descriptor_bytes()?;
+            if tree {
+                print!("{}", descriptor::dump(&bytes)?);
+            } else {
+                print!("{}", descriptor::to_hex(&bytes));
+            }
+        }
+        Command::Watch { inputs, interval } => {
+            watch(&api, inputs, Duration::from_millis(interval.max(1)))?;
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! A local broker owning HID lighting devices, so several applications can share them
+//! over HTTP instead of fighting over opening them.
+//!
+//! ```text
+//! hidlightsd [--bind 127.0.0.1:7420] [--token <token>]
+//! ```
+//!
+//! Every request needs `Authorization: Bearer <token>`, the token coming from `--token`
+//! or `HIDLIGHTSD_TOKEN`. Devices are addressed by their [`DeviceId`] string and
+//! outputs by key, index or name, see [`DeviceHandle::find_output`].
+//!
+//! ```text
+//! GET  /devices                   enumerated devices
+//! GET  /devices/<id>/outputs      a device's outputs and their values
+//! POST /devices/<id>/outputs      { "<output>": value, ... }, sets and flushes
+//! ```
+//!
+//! Sharing only works while every application goes through the broker. Clients must
+//! not open the devices themselves, the broker has no say over writes it doesn't make.
+//!
+//! A device that's unplugged gets a 503 and is opened again on the next request, once
+//! it's back.
+
+use std::{collections::HashMap, io::Read};
+
+use hidlights::{DeviceHandle, DeviceId, HidLightError, HidLights};
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+const USAGE: &str = "usage: hidlightsd [--bind <addr>] [--token <token>]";
+
+struct Broker {
+    api: HidLights,
+    token: String,
+    handles: HashMap<DeviceId, DeviceHandle>,
+}
+
+impl Broker {
+    fn handle(&mut self, mut request: Request) {
+        let (status, body) = if !self.authorized(&request) {
+            (401, json!({ "error": "missing or wrong bearer token" }))
+        } else {
+            let mut body = String::new();
+            match request.as_reader().read_to_string(&mut body) {
+                Ok(_) => self.route(request.method(), request.url(), &body),
+                Err(err) => (400, json!({ "error": err.to_string() })),
+            }
+        };
+
+        let response = Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(
+                Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                    .expect("static header"),
+            );
+        _ = request.respond(response);
+    }
+
+    fn authorized(&self, request: &Request) -> bool {
+        request.headers().iter().any(|x| {
+            x.field.equiv("Authorization")
+                && x.value.as_str().strip_prefix("Bearer ") == Some(self.token.as_str())
+        })
+    }
+
+    fn route(&mut self, method: &Method, url: &str, body: &str) -> (u16, Value) {
+        let path: Vec<_> = url
+            .split('?')
+            .next()
+            .unwrap_or_default()
+            .split('/')
+            .filter(|x| !x.is_empty())
+            .collect();
+
+        let result = match (method, &path[..]) {
+            (Method::Get, ["devices"]) => self.devices(),
+            (Method::Get, ["devices", id, "outputs"]) => self.outputs(id),
+            (Method::Post, ["devices", id, "outputs"]) => self.set(id, body),
+            _ => return (404, json!({ "error": "no such endpoint" })),
+        };
+        // Dead handles are dropped, so a replugged device is opened afresh
+        self.handles.retain(|_, x| !x.is_disconnected());
+        match result {
+            Ok(body) => (200, body),
+            Err(err) => {
+                let status = match err {
+                    HidLightError::DeviceNotFound => 404,
+                    HidLightError::Disconnected => 503,
+                    HidLightError::InvalidArgument | HidLightError::Json(_) => 400,
+                    _ => 500,
+                };
+                (status, json!({ "error": err.to_string() }))
+            }
+        }
+    }
+
+    fn devices(&self) -> Result<Value, HidLightError> {
+        self.api.refresh()?;
+        Ok(self
+            .api
+            .devices()
+            .iter()
+            .map(|x| {
+                json!({
+                    "id": x.id().to_string(),
+                    "vid": x.vid,
+                    "pid": x.pid,
+                    "name": x.name,
+                    "manufacturer": x.manufacturer,
+                    "usage_page": x.usage_page,
+                    "usage_id": x.usage_id,
+                    "interface_number": x.interface_number,
+                    "open": self.handles.contains_key(&x.id()),
+                })
+            })
+            .collect())
+    }
+
+    fn outputs(&mut self, id: &str) -> Result<Value, HidLightError> {
+        let handle = self.open(id)?;
+        handle.resolve_names()?;
+        Ok(handle
+            .outputs()?
+            .enumerate()
+            .map(|(index, x)| {
+                json!({
+                    "index": index,
+                    "key": x.key().to_string(),
+                    "name": x.name,
+                    "value": x.real_value,
+                })
+            })
+            .collect())
+    }
+
+    fn set(&mut self, id: &str, body: &str) -> Result<Value, HidLightError> {
+        let values: HashMap<String, f32> = serde_json::from_str(body)?;
+        let handle = self.open(id)?;
+
+        let mut resolved = vec![];
+        for (output, value) in values {
+            let id = handle
+                .find_output(&output)?
+                .ok_or(HidLightError::InvalidArgument)?;
+            resolved.push((id, value));
+        }
+        for (id, value) in resolved {
+            handle.set(id, value)?;
+        }
+        handle.flush()?;
+        Ok(json!({}))
+    }
+
+    /// The broker's handle for a device, opening it the first time it's used.
+    fn open(&mut self, id: &str) -> Result<&mut DeviceHandle, HidLightError> {
+        let id: DeviceId = id.parse()?;
+        if !self.handles.contains_key(&id) {
+            self.api.refresh()?;
+            let handle = self
+                .api
+                .find(|x| x.id() == id)
+                .ok_or(HidLightError::DeviceNotFound)?
+                .open()?;
+            self.handles.insert(id.clone(), handle);
+        }
+        Ok(self.handles.get_mut(&id).expect("just inserted"))
+    }
+}
+
+fn main() {
+    let mut bind = "127.0.0.1:7420".to_string();
+    let mut token = std::env::var("HIDLIGHTSD_TOKEN").ok();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--bind", Some(value)) => bind = value,
+            ("--token", Some(value)) => token = Some(value),
+            _ => {
+                eprintln!("{USAGE}");
+                std::process::exit(2);
+            }
+        }
+    }
+    let Some(token) = token.filter(|x| !x.is_empty()) else {
+        eprintln!("a token is needed, from --token or HIDLIGHTSD_TOKEN\n{USAGE}");
+        std::process::exit(2);
+    };
+
+    let api = HidLights::new().unwrap_or_else(|err| {
+        eprintln!("initialising hidapi failed: {err}");
+        std::process::exit(1);
+    });
+    let server = Server::http(&bind).unwrap_or_else(|err| {
+        eprintln!("listening on {bind} failed: {err}");
+        std::process::exit(1);
+    });
+    eprintln!("listening on {bind}");
+
+    let mut broker = Broker {
+        api,
+        token,
+        handles: HashMap::new(),
+    };
+    // One request at a time, so writes from different clients never interleave
+    for request in server.incoming_requests() {
+        broker.handle(request);
+    }
+}
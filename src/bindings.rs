@@ -0,0 +1,281 @@
+//! Lights that react to the device's own inputs, e.g. lighting an arcade button while
+//! it's pressed, without the game knowing anything about it.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{input::InputField, DeviceHandle, HidLightError, OutputId, OutputKey, Result, Usage};
+
+/// An input, by name (ignoring case) or usage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InputRef {
+    Name(String),
+    Usage { page: u16, id: u16 },
+}
+
+impl From<&str> for InputRef {
+    fn from(name: &str) -> Self {
+        InputRef::Name(name.into())
+    }
+}
+
+impl InputRef {
+    fn matches(&self, input: &InputField) -> bool {
+        match self {
+            InputRef::Name(name) => input.name.eq_ignore_ascii_case(name),
+            InputRef::Usage { page, id } => {
+                input.usage()
+                    == Usage {
+                        page: *page,
+                        id: *id,
+                    }
+            }
+        }
+    }
+}
+
+/// An output, by name (ignoring case) or key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OutputRef {
+    Name(String),
+    Key(OutputKey),
+}
+
+impl From<&str> for OutputRef {
+    fn from(name: &str) -> Self {
+        OutputRef::Name(name.into())
+    }
+}
+
+impl OutputRef {
+    pub(crate) fn resolve(&self, handle: &mut DeviceHandle) -> Result<OutputId> {
+        match self {
+            OutputRef::Key(key) => Ok(handle.output_by_key(key)?.id()),
+            OutputRef::Name(name) => {
+                // Matched against the names the device gives, as they're listed
+                handle.resolve_names()?;
+                handle
+                    .outputs()?
+                    .find(|x| {
+                        x.name
+                            .as_ref()
+                            .is_some_and(|x| x.eq_ignore_ascii_case(name))
+                    })
+                    .map(|x| x.id())
+                    .ok_or(HidLightError::InvalidArgument)
+            }
+        }
+    }
+}
+
+impl DeviceHandle {
+    /// Finds an output by its [`OutputKey`], its index, or its name ignoring case, as
+    /// the network servers address them.
+    pub fn find_output(&mut self, selector: &str) -> Result<Option<OutputId>> {
+        if let Ok(key) = selector.parse::<OutputKey>() {
+            return Ok(self.output_by_key(&key).ok().map(|x| x.id()));
+        }
+        Ok(match selector.parse::<usize>() {
+            Ok(index) => self.outputs()?.nth(index).map(|x| x.id()),
+            Err(_) => OutputRef::Name(selector.into()).resolve(self).ok(),
+        })
+    }
+}
+
+impl From<OutputKey> for OutputRef {
+    fn from(key: OutputKey) -> Self {
+        OutputRef::Key(key)
+    }
+}
+
+/// How long a binding holds its output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Hold {
+    /// Until the input is released.
+    WhileHeld,
+    /// For a fixed time after each press, whenever the input is released.
+    For(Duration),
+}
+
+/// When `input` is pressed, set `output` to `value` for as long as `hold` says, then
+/// put it back.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Binding {
+    pub input: InputRef,
+    pub output: OutputRef,
+    pub value: f32,
+    pub hold: Hold,
+}
+
+impl Binding {
+    /// Lights `output` fully while `input` is held.
+    pub fn new(input: impl Into<InputRef>, output: impl Into<OutputRef>) -> Self {
+        Self {
+            input: input.into(),
+            output: output.into(),
+            value: 1.0,
+            hold: Hold::WhileHeld,
+        }
+    }
+
+    pub fn value(mut self, value: f32) -> Self {
+        self.value = value;
+        self
+    }
+
+    pub fn for_duration(mut self, duration: Duration) -> Self {
+        self.hold = Hold::For(duration);
+        self
+    }
+}
+
+/// A binding resolved against the device.
+struct Bound {
+    input: usize,
+    output: OutputId,
+    value: f32,
+    hold: Hold,
+    /// The value to put back and, for timed holds, when.
+    active: Option<(f32, Option<Instant>)>,
+}
+
+/// Reads the device's input reports and applies a table of [`Binding`]s.
+pub struct BindingEngine {
+    handle: DeviceHandle,
+    inputs: Vec<InputField>,
+    numbered: bool,
+    pressed: Vec<bool>,
+    bindings: Vec<Bound>,
+    stop: Arc<AtomicBool>,
+}
+
+impl BindingEngine {
+    /// Fails if a binding names an input or output the device doesn't have.
+    pub fn new(mut handle: DeviceHandle, bindings: &[Binding]) -> Result<Self> {
+        let inputs = handle.inputs()?;
+        let numbered = handle.numbered_inputs()?;
+
+        let mut bound = vec![];
+        for binding in bindings {
+            let input = inputs
+                .iter()
+                .position(|x| binding.input.matches(x))
+                .ok_or(HidLightError::InvalidArgument)?;
+            let output = binding.output.resolve(&mut handle)?;
+            bound.push(Bound {
+                input,
+                output,
+                value: binding.value,
+                hold: binding.hold,
+                active: None,
+            });
+        }
+
+        Ok(Self {
+            handle,
+            pressed: vec![false; inputs.len()],
+            inputs,
+            numbered,
+            bindings: bound,
+            stop: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// Set to stop [`Self::run`] from another thread.
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    /// Handles input until the stop flag is set.
+    pub fn run(&mut self) -> Result<()> {
+        while !self.stop.load(Ordering::Relaxed) {
+            self.poll(Duration::from_millis(100))?;
+        }
+        Ok(())
+    }
+
+    /// Waits up to `timeout` for an input report, less if a timed hold ends sooner,
+    /// and updates the outputs.
+    pub fn poll(&mut self, timeout: Duration) -> Result<()> {
+        let now = Instant::now();
+        let timeout = self
+            .bindings
+            .iter()
+            .filter_map(|x| x.active.and_then(|x| x.1))
+            .map(|x| x.saturating_duration_since(now))
+            .fold(timeout, Duration::min);
+
+        if let Some(report) = self.handle.read_input(timeout.as_millis() as i32)? {
+            self.handle_report(&report)?;
+        }
+        self.expire(Instant::now())?;
+        self.handle.flush()
+    }
+
+    fn handle_report(&mut self, report: &[u8]) -> Result<()> {
+        let quirks = self.handle.quirks();
+        for (i, input) in self.inputs.iter().enumerate() {
+            let Some(value) = input.decode(report, self.numbered, &quirks) else {
+                continue;
+            };
+            let pressed = input.is_active(value);
+            if pressed == self.pressed[i] {
+                continue;
+            }
+            self.pressed[i] = pressed;
+
+            for binding in self.bindings.iter_mut().filter(|x| x.input == i) {
+                match (pressed, binding.hold, binding.active) {
+                    (true, hold, active) => {
+                        let restore = match active {
+                            Some((restore, _)) => restore,
+                            None => self.handle.output(binding.output)?.real_value,
+                        };
+                        let until = match hold {
+                            Hold::For(duration) => Some(Instant::now() + duration),
+                            Hold::WhileHeld => None,
+                        };
+                        binding.active = Some((restore, until));
+                        self.handle.set(binding.output, binding.value)?;
+                    }
+                    (false, Hold::WhileHeld, Some((restore, _))) => {
+                        binding.active = None;
+                        self.handle.set(binding.output, restore)?;
+                    }
+                    (false, ..) => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Puts back outputs whose timed hold has run out.
+    fn expire(&mut self, now: Instant) -> Result<()> {
+        for binding in &mut self.bindings {
+            if let Some((restore, Some(until))) = binding.active {
+                if until <= now {
+                    binding.active = None;
+                    self.handle.set(binding.output, restore)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn inputs(&self) -> &[InputField] {
+        &self.inputs
+    }
+
+    pub fn into_inner(self) -> DeviceHandle {
+        self.handle
+    }
+}
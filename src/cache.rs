@@ -0,0 +1,73 @@
+use std::{
+    collections::HashMap,
+    ffi::{CStr, CString},
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+use crate::Report;
+
+/// VID, PID and descriptor hash. Interfaces of a composite device share a VID/PID but
+/// not a descriptor, so they're cached apart.
+type CacheKey = (u16, u16, u64);
+
+/// Parsed report layouts, shared by every handle opened from the same [`crate::HidLights`].
+#[derive(Default)]
+pub(crate) struct DescriptorCache {
+    entries: Mutex<Entries>,
+}
+
+#[derive(Default)]
+struct Entries {
+    reports: HashMap<CacheKey, Vec<Report>>,
+    /// The layout each interface, by path, was last seen with.
+    interfaces: HashMap<CString, CacheKey>,
+}
+
+impl DescriptorCache {
+    fn entries(&self) -> MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The layout cached for the descriptor, noting it as the one at `path`.
+    pub fn get(
+        &self,
+        path: &CStr,
+        vid: u16,
+        pid: u16,
+        descriptor_hash: u64,
+    ) -> Option<Vec<Report>> {
+        let key = (vid, pid, descriptor_hash);
+        let mut entries = self.entries();
+        let reports = entries.reports.get(&key).cloned()?;
+        entries.interfaces.insert(path.to_owned(), key);
+        Some(reports)
+    }
+
+    /// Whether the interface at `path` was last seen with a layout that has outputs.
+    pub fn has_outputs(&self, path: &CStr) -> bool {
+        let entries = self.entries();
+        entries
+            .interfaces
+            .get(path)
+            .and_then(|x| entries.reports.get(x))
+            .is_some_and(|x| x.iter().any(|x| !x.outputs.is_empty()))
+    }
+
+    pub fn insert(
+        &self,
+        path: &CStr,
+        vid: u16,
+        pid: u16,
+        descriptor_hash: u64,
+        reports: Vec<Report>,
+    ) {
+        let key = (vid, pid, descriptor_hash);
+        let mut entries = self.entries();
+        entries.reports.insert(key, reports);
+        entries.interfaces.insert(path.to_owned(), key);
+    }
+
+    pub fn clear(&self) {
+        *self.entries() = Entries::default();
+    }
+}
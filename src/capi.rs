@@ -0,0 +1,265 @@
+//! A C API for games and engines, see `include/hidlights.h`.
+//!
+//! Functions returning `int32_t` give 0 on success and -1 on failure, with the reason
+//! from `hidlights_last_error` on the same thread. Strings are copied into caller
+//! buffers, nul terminated and truncated to fit, and the functions copying them return
+//! the untruncated length so a bigger buffer can be tried.
+//!
+//! The header is generated with
+//! `cbindgen --config cbindgen.toml --output include/hidlights.h`, and the library
+//! built with `cargo rustc --release --features capi --crate-type cdylib`, or
+//! `staticlib`, so crates depending on hidlights only build the rlib.
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CString},
+    ptr,
+};
+
+use crate::{DeviceHandle, DeviceInfo, HidLightError, HidLights, OutputId, Result};
+
+/// The library context, with a snapshot of the enumerated devices.
+pub struct Context {
+    api: HidLights,
+    devices: Vec<DeviceInfo>,
+}
+
+/// An open device.
+pub struct Device {
+    handle: DeviceHandle,
+    outputs: Vec<(OutputId, Option<String>)>,
+}
+
+/// Identifies an enumerated device.
+#[repr(C)]
+pub struct DeviceInfoC {
+    pub vid: u16,
+    pub pid: u16,
+    pub usage_page: u16,
+    pub usage_id: u16,
+    pub interface_number: i32,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn status(result: Result<()>) -> i32 {
+    match result {
+        Ok(()) => 0,
+        Err(err) => {
+            set_error(&err);
+            -1
+        }
+    }
+}
+
+fn set_error(err: &HidLightError) {
+    let message = CString::new(err.to_string()).unwrap_or_default();
+    LAST_ERROR.with(|x| *x.borrow_mut() = Some(message));
+}
+
+/// Copies `value` into `buffer`, returning its full length.
+unsafe fn copy_string(value: &str, buffer: *mut c_char, len: usize) -> usize {
+    if !buffer.is_null() && len > 0 {
+        let copied = value.len().min(len - 1);
+        ptr::copy_nonoverlapping(value.as_ptr().cast(), buffer, copied);
+        *buffer.add(copied) = 0;
+    }
+    value.len()
+}
+
+/// The last error on this thread, or null. Valid until the next call on this thread.
+#[no_mangle]
+pub extern "C" fn hidlights_last_error() -> *const c_char {
+    LAST_ERROR.with(|x| x.borrow().as_ref().map_or(ptr::null(), |x| x.as_ptr()))
+}
+
+/// Creates a context and enumerates devices. Null on failure.
+#[no_mangle]
+pub extern "C" fn hidlights_new() -> *mut Context {
+    match HidLights::new() {
+        Ok(api) => {
+            let devices = api.devices();
+            Box::into_raw(Box::new(Context { api, devices }))
+        }
+        Err(err) => {
+            set_error(&err);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+///
+/// `context` must come from `hidlights_new`, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn hidlights_free(context: *mut Context) {
+    if !context.is_null() {
+        drop(Box::from_raw(context));
+    }
+}
+
+/// Enumerates devices again, setting `count` to how many there are. Device indices
+/// refer to this enumeration until the next, on failure the previous one stays.
+///
+/// # Safety
+///
+/// `context` must come from `hidlights_new` and `count` be writable, or null.
+#[no_mangle]
+pub unsafe extern "C" fn hidlights_refresh(context: *mut Context, count: *mut usize) -> i32 {
+    let context = &mut *context;
+    let result = context.api.refresh();
+    if result.is_ok() {
+        context.devices = context.api.devices();
+        if !count.is_null() {
+            *count = context.devices.len();
+        }
+    }
+    status(result)
+}
+
+/// # Safety
+///
+/// `context` must come from `hidlights_new`.
+#[no_mangle]
+pub unsafe extern "C" fn hidlights_device_count(context: *const Context) -> usize {
+    (*context).devices.len()
+}
+
+/// # Safety
+///
+/// `context` must come from `hidlights_new` and `info` be writable.
+#[no_mangle]
+pub unsafe extern "C" fn hidlights_device_info(
+    context: *const Context,
+    index: usize,
+    info: *mut DeviceInfoC,
+) -> i32 {
+    status(
+        (*context)
+            .devices
+            .get(index)
+            .map_or(Err(HidLightError::DeviceNotFound), |x| {
+                *info = DeviceInfoC {
+                    vid: x.vid,
+                    pid: x.pid,
+                    usage_page: x.usage_page,
+                    usage_id: x.usage_id,
+                    interface_number: x.interface_number,
+                };
+                Ok(())
+            }),
+    )
+}
+
+/// Copies a device's product name, empty if it has none.
+///
+/// # Safety
+///
+/// `context` must come from `hidlights_new` and `buffer` hold `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn hidlights_device_name(
+    context: *const Context,
+    index: usize,
+    buffer: *mut c_char,
+    len: usize,
+) -> usize {
+    let name = (*context)
+        .devices
+        .get(index)
+        .and_then(|x| x.name.as_deref())
+        .unwrap_or_default();
+    copy_string(name, buffer, len)
+}
+
+/// Opens a device. Null on failure.
+///
+/// # Safety
+///
+/// `context` must come from `hidlights_new`.
+#[no_mangle]
+pub unsafe extern "C" fn hidlights_open(context: *const Context, index: usize) -> *mut Device {
+    let result = (*context)
+        .devices
+        .get(index)
+        .ok_or(HidLightError::DeviceNotFound)
+        .and_then(|x| x.open())
+        .and_then(|mut handle| {
+            handle.resolve_names()?;
+            let outputs = handle
+                .outputs()?
+                .map(|x| (x.id(), x.name.clone()))
+                .collect();
+            Ok(Device { handle, outputs })
+        });
+    match result {
+        Ok(device) => Box::into_raw(Box::new(device)),
+        Err(err) => {
+            set_error(&err);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+///
+/// `device` must come from `hidlights_open`, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn hidlights_close(device: *mut Device) {
+    if !device.is_null() {
+        drop(Box::from_raw(device));
+    }
+}
+
+/// # Safety
+///
+/// `device` must come from `hidlights_open`.
+#[no_mangle]
+pub unsafe extern "C" fn hidlights_output_count(device: *const Device) -> usize {
+    (*device).outputs.len()
+}
+
+/// Copies an output's name, empty if it has none.
+///
+/// # Safety
+///
+/// `device` must come from `hidlights_open` and `buffer` hold `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn hidlights_output_name(
+    device: *const Device,
+    output: usize,
+    buffer: *mut c_char,
+    len: usize,
+) -> usize {
+    let name = (*device)
+        .outputs
+        .get(output)
+        .and_then(|x| x.1.as_deref())
+        .unwrap_or_default();
+    copy_string(name, buffer, len)
+}
+
+/// Sets an output as a fraction of its range, sent on the next `hidlights_write`.
+///
+/// # Safety
+///
+/// `device` must come from `hidlights_open`.
+#[no_mangle]
+pub unsafe extern "C" fn hidlights_set(device: *mut Device, output: usize, value: f32) -> i32 {
+    let device = &mut *device;
+    status(match device.outputs.get(output) {
+        Some((id, _)) => device.handle.set(*id, value),
+        None => Err(HidLightError::InvalidArgument),
+    })
+}
+
+/// Writes every output changed since the last write.
+///
+/// # Safety
+///
+/// `device` must come from `hidlights_open`.
+#[no_mangle]
+pub unsafe extern "C" fn hidlights_write(device: *mut Device) -> i32 {
+    status((*device).handle.flush())
+}
@@ -0,0 +1,297 @@
+//! Saving, loading and inspecting raw report descriptors, for bug reports and offline analysis.
+
+use std::{collections::HashMap, fmt::Write, ops::Range, path::Path};
+
+use hidparser::{report_data_types::Usage, Report as ParsedReport, ReportCollection, ReportField};
+
+use crate::{descriptor_error, is_vendor_field_usage, HidLightError, Result};
+
+/// Formats bytes as the usual hex dump, 16 space separated bytes per line.
+pub fn to_hex(descriptor: &[u8]) -> String {
+    let mut out = String::with_capacity(descriptor.len() * 3);
+    for line in descriptor.chunks(16) {
+        for (i, byte) in line.iter().enumerate() {
+            if i > 0 {
+                out.push(' ');
+            }
+            _ = write!(out, "{byte:02x}");
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Parses a hex dump, accepting `0x` prefixes, commas and `#` comments so output
+/// from most descriptor tools can be pasted in directly.
+pub fn from_hex(text: &str) -> Result<Vec<u8>> {
+    let mut bytes = vec![];
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        for token in line.split(|c: char| c.is_whitespace() || c == ',') {
+            if token.is_empty() {
+                continue;
+            }
+            let token = token
+                .strip_prefix("0x")
+                .or_else(|| token.strip_prefix("0X"))
+                .unwrap_or(token);
+            if token.len() != 2 {
+                return Err(HidLightError::InvalidHex);
+            }
+            bytes.push(u8::from_str_radix(token, 16).map_err(|_| HidLightError::InvalidHex)?);
+        }
+    }
+    Ok(bytes)
+}
+
+pub fn save_hex(path: impl AsRef<Path>, descriptor: &[u8]) -> Result<()> {
+    Ok(std::fs::write(path, to_hex(descriptor))?)
+}
+
+pub fn load_hex(path: impl AsRef<Path>) -> Result<Vec<u8>> {
+    from_hex(&std::fs::read_to_string(path)?)
+}
+
+pub fn save_binary(path: impl AsRef<Path>, descriptor: &[u8]) -> Result<()> {
+    Ok(std::fs::write(path, descriptor)?)
+}
+
+pub fn load_binary(path: impl AsRef<Path>) -> Result<Vec<u8>> {
+    Ok(std::fs::read(path)?)
+}
+
+/// The kind of main item a field was declared by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum MainKind {
+    Input,
+    Output,
+    Feature,
+}
+
+/// A main item whose usages were declared as a Usage Minimum/Maximum range, which
+/// hidparser doesn't keep apart from usages that were listed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct UsageRange {
+    pub kind: MainKind,
+    pub report_id: u32,
+    /// Bits of the report the main item covers, not counting the report ID.
+    pub bits: Range<u32>,
+    pub minimum: u16,
+    pub maximum: u16,
+}
+
+impl UsageRange {
+    /// Usage ID of the element starting at bit `start` of the range, elements past
+    /// the end of it repeating the maximum as the HID spec says.
+    pub fn element_id(&self, start: u32, size: u32) -> u16 {
+        let index = (start - self.bits.start) / size.max(1);
+        u16::try_from(index)
+            .ok()
+            .and_then(|x| self.minimum.checked_add(x))
+            .filter(|x| *x <= self.maximum)
+            .unwrap_or(self.maximum)
+    }
+}
+
+/// Walks the raw descriptor for the main items declared with a usage range.
+pub(crate) fn usage_ranges(descriptor: &[u8]) -> Vec<UsageRange> {
+    let mut ranges = vec![];
+    let mut offsets: HashMap<(MainKind, u32), u32> = HashMap::new();
+    let (mut size, mut count, mut report_id) = (0u32, 0u32, 0u32);
+    // Globals saved by Push
+    let mut stack = vec![];
+    let mut minimum = None;
+    let mut maximum = None;
+
+    let mut i = 0;
+    while i < descriptor.len() {
+        let prefix = descriptor[i];
+        if prefix == 0xfe {
+            // Long item, data size in the next byte and a tag after it
+            let len = descriptor.get(i + 1).copied().unwrap_or(0) as usize;
+            i += 3 + len;
+            continue;
+        }
+        let len = match prefix & 3 {
+            3 => 4,
+            x => x as usize,
+        };
+        let Some(data) = descriptor.get(i + 1..i + 1 + len) else {
+            break;
+        };
+        i += 1 + len;
+        let value = data.iter().rev().fold(0u32, |acc, x| acc << 8 | *x as u32);
+
+        match (prefix >> 2 & 3, prefix >> 4) {
+            (0, tag @ (0x8 | 0x9 | 0xb)) => {
+                let kind = match tag {
+                    0x8 => MainKind::Input,
+                    0x9 => MainKind::Output,
+                    _ => MainKind::Feature,
+                };
+                let offset = offsets.entry((kind, report_id)).or_default();
+                let bits = *offset..offset.saturating_add(size.saturating_mul(count));
+                *offset = bits.end;
+                if let (Some(minimum), Some(maximum)) = (minimum, maximum) {
+                    ranges.push(UsageRange {
+                        kind,
+                        report_id,
+                        bits,
+                        // Extended usages carry the page in the upper half
+                        minimum: minimum as u16,
+                        maximum: maximum as u16,
+                    });
+                }
+                (minimum, maximum) = (None, None);
+            }
+            // Collections end the local items too
+            (0, _) => (minimum, maximum) = (None, None),
+            (1, 0x7) => size = value,
+            (1, 0x8) => report_id = value,
+            (1, 0x9) => count = value,
+            (1, 0xa) => stack.push((size, count, report_id)),
+            (1, 0xb) => {
+                if let Some(globals) = stack.pop() {
+                    (size, count, report_id) = globals;
+                }
+            }
+            (2, 0x1) => minimum = Some(value),
+            (2, 0x2) => maximum = Some(value),
+            _ => {}
+        }
+    }
+    ranges
+}
+
+/// Renders the parsed descriptor as a tree of reports, collections and fields,
+/// noting fields that aren't exposed as outputs and why.
+pub fn dump(descriptor: &[u8]) -> Result<String> {
+    let parsed = hidparser::parse_report_descriptor(descriptor).map_err(descriptor_error)?;
+
+    let mut out = String::new();
+    let kinds = [
+        ("Input", &parsed.input_reports),
+        ("Output", &parsed.output_reports),
+        ("Feature", &parsed.features),
+    ];
+    for (kind, reports) in kinds {
+        for report in reports {
+            dump_report(&mut out, kind, report);
+        }
+    }
+    Ok(out)
+}
+
+fn dump_report(out: &mut String, kind: &str, report: &ParsedReport) {
+    match report.report_id {
+        Some(id) => {
+            let id: u32 = id.into();
+            _ = writeln!(out, "{kind} report {id} ({} bits)", report.size_in_bits);
+        }
+        None => {
+            _ = writeln!(
+                out,
+                "{kind} report, unnumbered ({} bits)",
+                report.size_in_bits
+            )
+        }
+    }
+
+    let mut current: Vec<(u16, u16)> = vec![];
+    for field in &report.fields {
+        let collections = match field {
+            ReportField::Variable(x) => Some(&x.member_of),
+            ReportField::Array(x) => Some(&x.member_of),
+            ReportField::Padding(_) => None,
+        };
+
+        if let Some(collections) = collections {
+            let usages: Vec<_> = collections
+                .iter()
+                .map(|x| (x.usage.page(), x.usage.id()))
+                .collect();
+            let common = current
+                .iter()
+                .zip(&usages)
+                .take_while(|(a, b)| a == b)
+                .count();
+            for (depth, collection) in collections.iter().enumerate().skip(common) {
+                dump_collection(out, depth, collection);
+            }
+            current = usages;
+        }
+
+        let indent = 2 + current.len() * 2;
+        match field {
+            ReportField::Variable(x) => {
+                let note = if x.attributes.constant {
+                    " (constant)"
+                } else if !x.attributes.variable {
+                    " (not variable, ignored)"
+                } else if is_vendor_field_usage(x.usage) {
+                    " (vendor usage, ignored)"
+                } else {
+                    ""
+                };
+                let min: i32 = x.logical_minimum.into();
+                let max: i32 = x.logical_maximum.into();
+                _ = writeln!(
+                    out,
+                    "{:indent$}bits {:>4}..{:<4} Variable {} logical {min}..={max}{note}",
+                    "",
+                    x.bits.start,
+                    x.bits.end,
+                    usage_label(x.usage),
+                );
+            }
+            ReportField::Array(x) => {
+                let min: i32 = x.logical_minimum.into();
+                let max: i32 = x.logical_maximum.into();
+                _ = writeln!(
+                    out,
+                    "{:indent$}bits {:>4}..{:<4} Array logical {min}..={max}",
+                    "", x.bits.start, x.bits.end,
+                );
+                for range in &x.usage_list {
+                    let start = usage_label(Usage::from(range.start()));
+                    let end = usage_label(Usage::from(range.end()));
+                    let note = if is_vendor_field_usage(Usage::from(range.start())) {
+                        " (vendor usage, ignored)"
+                    } else {
+                        ""
+                    };
+                    if start == end {
+                        _ = writeln!(out, "{:indent$}  {start}{note}", "");
+                    } else {
+                        _ = writeln!(out, "{:indent$}  {start} to {end}{note}", "");
+                    }
+                }
+            }
+            ReportField::Padding(x) => {
+                _ = writeln!(
+                    out,
+                    "{:indent$}bits {:>4}..{:<4} Padding",
+                    "", x.bits.start, x.bits.end,
+                );
+            }
+        }
+    }
+}
+
+fn dump_collection(out: &mut String, depth: usize, collection: &ReportCollection) {
+    let indent = 2 + depth * 2;
+    _ = writeln!(
+        out,
+        "{:indent$}Collection {}",
+        "",
+        usage_label(collection.usage)
+    );
+}
+
+fn usage_label(usage: Usage) -> String {
+    let (page, id) = (usage.page(), usage.id());
+    match hut::Usage::new_from_page_and_id(page, id) {
+        Ok(x) => format!("{} (0x{page:02X}:0x{id:02X})", x.name()),
+        Err(_) => format!("0x{page:02X}:0x{id:02X}"),
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+use crate::{physical::location_key, DeviceInfo, HidLightError};
+
+/// Identity of an interface that stays the same across re-enumeration and reboots,
+/// as long as the device reports a serial number or stays on the same port.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceId {
+    pub vid: u16,
+    pub pid: u16,
+    pub interface_number: i32,
+    pub usage_page: u16,
+    pub usage_id: u16,
+    pub instance: DeviceInstance,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DeviceInstance {
+    Serial(String),
+    /// Stable hash of the port the device is attached to, or of its path where
+    /// the platform doesn't expose that.
+    Location(u64),
+}
+
+impl DeviceId {
+    /// Worked out once at enumeration, as finding the location can touch the
+    /// filesystem and fails once the device is gone.
+    pub(crate) fn for_device(info: &hidapi::DeviceInfo) -> Self {
+        // Empty serials would make every serial-less device the same one
+        let instance = match info.serial_number().filter(|x| !x.is_empty()) {
+            Some(serial) => DeviceInstance::Serial(serial.into()),
+            None => {
+                let location = location_key(info.path())
+                    .unwrap_or_else(|| info.path().to_string_lossy().into_owned());
+                DeviceInstance::Location(fnv1a(location.as_bytes()))
+            }
+        };
+
+        Self {
+            vid: info.vendor_id(),
+            pid: info.product_id(),
+            interface_number: info.interface_number(),
+            usage_page: info.usage_page(),
+            usage_id: info.usage(),
+            instance,
+        }
+    }
+}
+
+impl DeviceInfo {
+    pub fn id(&self) -> DeviceId {
+        self.id.clone()
+    }
+}
+
+/// Formats as `vid:pid:interface:usage page:usage id:instance` in hex, the instance
+/// being `s-<serial>` or `l-<location hash>`, e.g. `046d:c33c:1:ff43:0602:s-A1B2`.
+impl fmt::Display for DeviceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04x}:{:04x}:{}:{:04x}:{:04x}:",
+            self.vid, self.pid, self.interface_number, self.usage_page, self.usage_id
+        )?;
+        match &self.instance {
+            DeviceInstance::Serial(serial) => write!(f, "s-{serial}"),
+            DeviceInstance::Location(location) => write!(f, "l-{location:016x}"),
+        }
+    }
+}
+
+impl FromStr for DeviceId {
+    type Err = HidLightError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<_> = s.splitn(6, ':').collect();
+        let [vid, pid, interface_number, usage_page, usage_id, instance] = parts[..] else {
+            return Err(HidLightError::InvalidArgument);
+        };
+        let hex = |x: &str| u16::from_str_radix(x, 16).map_err(|_| HidLightError::InvalidArgument);
+
+        let instance = if let Some(serial) = instance.strip_prefix("s-") {
+            DeviceInstance::Serial(serial.into())
+        } else if let Some(location) = instance.strip_prefix("l-") {
+            DeviceInstance::Location(
+                u64::from_str_radix(location, 16).map_err(|_| HidLightError::InvalidArgument)?,
+            )
+        } else {
+            return Err(HidLightError::InvalidArgument);
+        };
+        Ok(Self {
+            vid: hex(vid)?,
+            pid: hex(pid)?,
+            interface_number: interface_number
+                .parse()
+                .map_err(|_| HidLightError::InvalidArgument)?,
+            usage_page: hex(usage_page)?,
+            usage_id: hex(usage_id)?,
+            instance,
+        })
+    }
+}
+
+impl PartialEq for DeviceInfo {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for DeviceInfo {}
+
+impl Hash for DeviceInfo {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+// std's hasher isn't guaranteed to be stable between releases
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
@@ -0,0 +1,165 @@
+//! Receiving DMX over Art-Net or sACN (E1.31), so lighting consoles and tools like
+//! QLC+ can drive outputs.
+//!
+//! Devices are patched with a [`DmxMap`] saying which channels drive which outputs.
+
+use std::{
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crate::{DeviceHandle, DmxMap, ResolvedDmxMap, Result};
+
+pub const ARTNET_PORT: u16 = 6454;
+pub const SACN_PORT: u16 = 5568;
+
+const ARTNET_ID: &[u8; 8] = b"Art-Net\0";
+const ARTNET_OP_DMX: u16 = 0x5000;
+const SACN_ID: &[u8; 12] = b"ASC-E1.17\0\0\0";
+const SACN_OPTION_PREVIEW: u8 = 0x40;
+
+/// A universe's channel values from an ArtDmx packet, `None` for other packets.
+pub fn parse_artnet(packet: &[u8]) -> Option<(u16, &[u8])> {
+    if packet.len() < 18 || &packet[..8] != ARTNET_ID {
+        return None;
+    }
+    if u16::from_le_bytes([packet[8], packet[9]]) != ARTNET_OP_DMX {
+        return None;
+    }
+    let universe = u16::from_le_bytes([packet[14], packet[15]]) & 0x7fff;
+    let len = u16::from_be_bytes([packet[16], packet[17]]) as usize;
+    Some((universe, packet.get(18..18 + len)?))
+}
+
+/// A universe's channel values from an E1.31 data packet, `None` for other packets
+/// and preview data.
+pub fn parse_sacn(packet: &[u8]) -> Option<(u16, &[u8])> {
+    if packet.len() < 126 || &packet[4..16] != SACN_ID {
+        return None;
+    }
+    let options = packet[112];
+    // Only the null start code carries channel levels
+    if options & SACN_OPTION_PREVIEW != 0 || packet[125] != 0 {
+        return None;
+    }
+    let universe = u16::from_be_bytes([packet[113], packet[114]]);
+    // The count includes the start code
+    let count = u16::from_be_bytes([packet[123], packet[124]]) as usize;
+    Some((universe, packet.get(126..125 + count.max(1))?))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DmxProtocol {
+    ArtNet,
+    Sacn,
+}
+
+struct Patched {
+    handle: DeviceHandle,
+    map: ResolvedDmxMap,
+}
+
+/// Listens for DMX and applies it to patched devices.
+pub struct DmxReceiver {
+    socket: UdpSocket,
+    protocol: DmxProtocol,
+    devices: Vec<Patched>,
+    stop: Arc<AtomicBool>,
+}
+
+impl DmxReceiver {
+    /// Listens for Art-Net broadcasts on every interface.
+    pub fn artnet() -> Result<Self> {
+        Self::bind(
+            DmxProtocol::ArtNet,
+            (Ipv4Addr::UNSPECIFIED, ARTNET_PORT).into(),
+        )
+    }
+
+    /// Listens for sACN, joining the multicast groups of `universes`, e.g. from
+    /// [`DmxMap::universes`].
+    pub fn sacn(universes: &[u16]) -> Result<Self> {
+        let receiver = Self::bind(DmxProtocol::Sacn, (Ipv4Addr::UNSPECIFIED, SACN_PORT).into())?;
+        for universe in universes {
+            let [hi, lo] = universe.to_be_bytes();
+            receiver
+                .socket
+                .join_multicast_v4(&Ipv4Addr::new(239, 255, hi, lo), &Ipv4Addr::UNSPECIFIED)?;
+        }
+        Ok(receiver)
+    }
+
+    /// Listens on a specific address, e.g. for unicast sACN.
+    pub fn bind(protocol: DmxProtocol, addr: SocketAddr) -> Result<Self> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_read_timeout(Some(Duration::from_millis(100)))?;
+        Ok(Self {
+            socket,
+            protocol,
+            devices: vec![],
+            stop: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// Patches a device. Fails if the map names an output the device doesn't have.
+    pub fn patch(&mut self, mut handle: DeviceHandle, map: &DmxMap) -> Result<()> {
+        let map = map.resolve(&mut handle)?;
+        self.devices.push(Patched { handle, map });
+        Ok(())
+    }
+
+    /// Set to stop [`Self::run`] from another thread.
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    /// Applies DMX until the stop flag is set.
+    pub fn run(&mut self) -> Result<()> {
+        while !self.stop.load(Ordering::Relaxed) {
+            self.poll()?;
+        }
+        Ok(())
+    }
+
+    /// Waits a short while for a packet and applies it.
+    pub fn poll(&mut self) -> Result<()> {
+        let mut buffer = [0; 1024];
+        let len = match self.socket.recv(&mut buffer) {
+            Ok(len) => len,
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                return Ok(());
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let parsed = match self.protocol {
+            DmxProtocol::ArtNet => parse_artnet(&buffer[..len]),
+            DmxProtocol::Sacn => parse_sacn(&buffer[..len]),
+        };
+        match parsed {
+            Some((universe, levels)) => self.apply(universe, levels),
+            None => Ok(()),
+        }
+    }
+
+    /// Applies one universe's levels, flushing the devices patched into it.
+    pub fn apply(&mut self, universe: u16, levels: &[u8]) -> Result<()> {
+        for device in &mut self.devices {
+            device.map.apply(&mut device.handle, universe, levels)?;
+        }
+        Ok(())
+    }
+
+    /// Stops listening, giving back the devices.
+    pub fn into_devices(self) -> Vec<DeviceHandle> {
+        self.devices.into_iter().map(|x| x.handle).collect()
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{bindings::OutputRef, DeviceHandle, HidLightError, OutputId, Result};
+
+/// A DMX channel driving an output, like a fixture profile's channel.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DmxChannel {
+    pub universe: u16,
+    /// Numbered from 1, as consoles do.
+    pub channel: u16,
+    /// The fine channel of a 16 bit level, the low byte to `channel`'s high byte.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub fine: Option<u16>,
+    pub output: OutputRef,
+    /// Output values, as fractions of its range, at level 0 and at full.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub min: f32,
+    #[cfg_attr(feature = "serde", serde(default = "full"))]
+    pub max: f32,
+}
+
+#[cfg(feature = "serde")]
+fn full() -> f32 {
+    1.0
+}
+
+impl DmxChannel {
+    /// The level's fraction of full, from the universe's levels.
+    fn level(&self, levels: &[u8]) -> Option<f32> {
+        let coarse = *levels.get(self.channel as usize - 1)?;
+        Some(match self.fine {
+            Some(fine) => {
+                let fine = *levels.get(fine as usize - 1)?;
+                u16::from_be_bytes([coarse, fine]) as f32 / u16::MAX as f32
+            }
+            None => coarse as f32 / 255.0,
+        })
+    }
+}
+
+/// Which DMX channels drive which outputs of a device, and how they're scaled.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DmxMap {
+    pub channels: Vec<DmxChannel>,
+}
+
+impl DmxMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drives `output` from a channel over its whole range.
+    pub fn channel(self, universe: u16, channel: u16, output: impl Into<OutputRef>) -> Self {
+        self.scaled(universe, channel, output, 0.0, 1.0)
+    }
+
+    /// Drives `output` from a channel, level 0 setting `min` and full setting `max`.
+    pub fn scaled(
+        mut self,
+        universe: u16,
+        channel: u16,
+        output: impl Into<OutputRef>,
+        min: f32,
+        max: f32,
+    ) -> Self {
+        self.channels.push(DmxChannel {
+            universe,
+            channel,
+            fine: None,
+            output: output.into(),
+            min,
+            max,
+        });
+        self
+    }
+
+    /// Lays out consecutive channels from `start`, one per output, as fixture
+    /// profiles usually do.
+    pub fn consecutive(
+        mut self,
+        universe: u16,
+        start: u16,
+        outputs: impl IntoIterator<Item = impl Into<OutputRef>>,
+    ) -> Self {
+        for (channel, output) in (start..).zip(outputs) {
+            self = self.channel(universe, channel, output);
+        }
+        self
+    }
+
+    pub fn universes(&self) -> Vec<u16> {
+        let mut universes: Vec<_> = self.channels.iter().map(|x| x.universe).collect();
+        universes.sort_unstable();
+        universes.dedup();
+        universes
+    }
+
+    /// Finds the outputs on a device. Fails if one isn't there or a channel isn't
+    /// between 1 and 512.
+    pub fn resolve(&self, handle: &mut DeviceHandle) -> Result<ResolvedDmxMap> {
+        let channels = self
+            .channels
+            .iter()
+            .map(|x| {
+                let valid = |channel: u16| (1..=512).contains(&channel);
+                if !valid(x.channel) || x.fine.is_some_and(|x| !valid(x)) {
+                    return Err(HidLightError::InvalidArgument);
+                }
+                Ok((x.clone(), x.output.resolve(handle)?))
+            })
+            .collect::<Result<_>>()?;
+        Ok(ResolvedDmxMap { channels })
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        Ok(std::fs::write(path, self.to_json()?)?)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+}
+
+/// A [`DmxMap`] with its outputs found on a device.
+#[derive(Debug, Clone)]
+pub struct ResolvedDmxMap {
+    channels: Vec<(DmxChannel, OutputId)>,
+}
+
+impl ResolvedDmxMap {
+    /// The output values a universe's levels give, for the channels in it.
+    pub fn values(&self, universe: u16, levels: &[u8]) -> Vec<(OutputId, f32)> {
+        self.channels
+            .iter()
+            .filter(|x| x.0.universe == universe)
+            .filter_map(|(channel, id)| {
+                let level = channel.level(levels)?;
+                Some((*id, channel.min + (channel.max - channel.min) * level))
+            })
+            .collect()
+    }
+
+    /// Sets the outputs a universe's levels drive and flushes the device, if any.
+    /// Returns whether any did.
+    pub fn apply(&self, handle: &mut DeviceHandle, universe: u16, levels: &[u8]) -> Result<bool> {
+        let values = self.values(universe, levels);
+        for (id, value) in &values {
+            handle.set(*id, *value)?;
+        }
+        if !values.is_empty() {
+            handle.flush()?;
+        }
+        Ok(!values.is_empty())
+    }
+}
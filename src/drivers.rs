@@ -0,0 +1,44 @@
+//! Drivers for devices whose lights aren't described by standard HID output fields.
+
+use crate::{DeviceHandle, DeviceInfo, HidLightError, Result};
+
+pub mod blink1;
+pub mod blynclight;
+pub mod busylight;
+pub mod litra;
+pub mod luxafor;
+pub mod saitek;
+pub mod switch;
+pub mod ultimarc;
+
+pub trait Driver: Sized {
+    /// VID/PID pairs handled by this driver.
+    const DEVICES: &'static [(u16, u16)];
+
+    fn from_handle(handle: DeviceHandle) -> Result<Self>;
+
+    fn supports(info: &DeviceInfo) -> bool {
+        Self::DEVICES.contains(&(info.vid, info.pid))
+    }
+
+    fn open(info: &DeviceInfo) -> Result<Self> {
+        if !Self::supports(info) {
+            return Err(HidLightError::UnsupportedDevice);
+        }
+        Self::from_handle(info.open()?)
+    }
+}
+
+/// Whether any driver in this module can handle the device.
+pub fn is_supported(info: &DeviceInfo) -> bool {
+    blink1::Blink1::supports(info)
+        || blynclight::Blynclight::supports(info)
+        || busylight::Busylight::supports(info)
+        || litra::Litra::supports(info)
+        || luxafor::Luxafor::supports(info)
+        || saitek::MultiPanel::supports(info)
+        || saitek::SwitchPanel::supports(info)
+        || switch::SwitchController::supports(info)
+        || ultimarc::PacDrive::supports(info)
+        || ultimarc::PacLed::supports(info)
+}
@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+use crate::{
+    color::{ColorOutput, Rgb},
+    DeviceHandle, HidLightError, Result,
+};
+
+use super::Driver;
+
+pub const THINGM_VID: u16 = 0x27b8;
+pub const BLINK1_PID: u16 = 0x01ed;
+
+const REPORT_ID: u8 = 0x01;
+const REPORT_SIZE: usize = 9;
+
+/// Number of lines in the pattern memory of a blink(1) mk2 or newer.
+pub const PATTERN_LINES: u8 = 32;
+
+/// A single step of a blink(1) pattern.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PatternLine {
+    pub color: Rgb,
+    pub fade: Duration,
+}
+
+/// Which LED a command applies to, mk2 and newer have one on each side.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Led {
+    #[default]
+    All,
+    Top,
+    Bottom,
+}
+
+pub struct Blink1 {
+    handle: DeviceHandle,
+}
+
+impl Driver for Blink1 {
+    const DEVICES: &'static [(u16, u16)] = &[(THINGM_VID, BLINK1_PID)];
+
+    fn from_handle(handle: DeviceHandle) -> Result<Self> {
+        Ok(Self { handle })
+    }
+}
+
+impl Blink1 {
+    pub fn set_rgb(&self, color: Rgb) -> Result<()> {
+        self.command(b'n', [color.r, color.g, color.b, 0, 0, 0, 0])
+    }
+
+    pub fn fade_to_rgb(&self, color: Rgb, fade: Duration, led: Led) -> Result<()> {
+        let [th, tl] = fade_time(fade);
+        self.command(b'c', [color.r, color.g, color.b, th, tl, led as u8, 0])
+    }
+
+    pub fn play_pattern(&self, start: u8, end: u8, count: u8) -> Result<()> {
+        self.command(b'p', [1, start, end, count, 0, 0, 0])
+    }
+
+    pub fn stop_pattern(&self) -> Result<()> {
+        self.command(b'p', [0; 7])
+    }
+
+    pub fn write_pattern_line(&self, position: u8, line: PatternLine) -> Result<()> {
+        let [th, tl] = fade_time(line.fade);
+        let Rgb { r, g, b } = line.color;
+        self.command(b'P', [r, g, b, th, tl, position, 0])
+    }
+
+    pub fn read_pattern_line(&self, position: u8) -> Result<PatternLine> {
+        self.command(b'R', [0, 0, 0, 0, 0, position, 0])?;
+        let response = self.response()?;
+        let fade = u16::from_be_bytes([response[5], response[6]]);
+        Ok(PatternLine {
+            color: Rgb::new(response[2], response[3], response[4]),
+            fade: Duration::from_millis(fade as u64 * 10),
+        })
+    }
+
+    /// Writes the whole pattern starting at line 0, without persisting it.
+    pub fn set_pattern(&self, lines: &[PatternLine]) -> Result<()> {
+        if lines.len() > PATTERN_LINES as usize {
+            return Err(HidLightError::InvalidArgument);
+        }
+        for (position, line) in lines.iter().enumerate() {
+            self.write_pattern_line(position as u8, *line)?;
+        }
+        Ok(())
+    }
+
+    /// Persists the pattern memory so it plays when the device is powered without a host.
+    pub fn save_patterns(&self) -> Result<()> {
+        self.command(b'W', [0xbe, 0xef, 0xca, 0xfe, 0, 0, 0])
+    }
+
+    pub fn current_rgb(&self, led: Led) -> Result<Rgb> {
+        self.command(b'r', [0, 0, 0, 0, 0, led as u8, 0])?;
+        let response = self.response()?;
+        Ok(Rgb::new(response[2], response[3], response[4]))
+    }
+
+    pub fn firmware_version(&self) -> Result<u16> {
+        self.command(b'v', [0; 7])?;
+        let response = self.response()?;
+        let digit = |x: u8| (x as char).to_digit(10).unwrap_or_default() as u16;
+        Ok(digit(response[3]) * 100 + digit(response[4]))
+    }
+
+    pub fn into_inner(self) -> DeviceHandle {
+        self.handle
+    }
+
+    fn command(&self, command: u8, args: [u8; 7]) -> Result<()> {
+        let mut buffer = [0u8; REPORT_SIZE];
+        buffer[0] = REPORT_ID;
+        buffer[1] = command;
+        buffer[2..].copy_from_slice(&args);
+        self.handle.send_feature_raw(&buffer)
+    }
+
+    fn response(&self) -> Result<[u8; REPORT_SIZE]> {
+        let mut buffer = [0u8; REPORT_SIZE];
+        buffer[0] = REPORT_ID;
+        self.handle.get_feature_raw(&mut buffer)?;
+        Ok(buffer)
+    }
+}
+
+impl ColorOutput for Blink1 {
+    fn set_color(&mut self, color: Rgb) -> Result<()> {
+        self.set_rgb(color)
+    }
+}
+
+fn fade_time(fade: Duration) -> [u8; 2] {
+    // Fade times are sent in units of 10ms
+    let units = (fade.as_millis() / 10).min(u16::MAX as u128) as u16;
+    units.to_be_bytes()
+}
@@ -0,0 +1,98 @@
+use crate::{
+    color::{ColorOutput, Rgb},
+    DeviceHandle, Result,
+};
+
+use super::Driver;
+
+pub const EMBRAVA_VID: u16 = 0x2c0d;
+pub const TENX_VID: u16 = 0x0e53;
+
+const FLAG_OFF: u8 = 1 << 0;
+const FLAG_DIM: u8 = 1 << 1;
+const FLAG_FLASH: u8 = 1 << 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlashSpeed {
+    Slow = 1,
+    Medium = 2,
+    Fast = 4,
+}
+
+/// Embrava Blynclight, Blynclight Plus and Blynclight Mini.
+pub struct Blynclight {
+    handle: DeviceHandle,
+    color: Rgb,
+    dim: bool,
+    flash: Option<FlashSpeed>,
+}
+
+impl Driver for Blynclight {
+    const DEVICES: &'static [(u16, u16)] = &[
+        (TENX_VID, 0x2516),
+        (TENX_VID, 0x2517),
+        (EMBRAVA_VID, 0x0001),
+        (EMBRAVA_VID, 0x000a),
+        (EMBRAVA_VID, 0x000c),
+        (EMBRAVA_VID, 0x0010),
+    ];
+
+    fn from_handle(handle: DeviceHandle) -> Result<Self> {
+        Ok(Self {
+            handle,
+            color: Rgb::BLACK,
+            dim: false,
+            flash: None,
+        })
+    }
+}
+
+impl Blynclight {
+    pub fn set_solid(&mut self, color: Rgb) -> Result<()> {
+        self.color = color;
+        self.update()
+    }
+
+    pub fn set_flash(&mut self, flash: Option<FlashSpeed>) -> Result<()> {
+        self.flash = flash;
+        self.update()
+    }
+
+    pub fn set_dim(&mut self, dim: bool) -> Result<()> {
+        self.dim = dim;
+        self.update()
+    }
+
+    pub fn color(&self) -> Rgb {
+        self.color
+    }
+
+    pub fn into_inner(self) -> DeviceHandle {
+        self.handle
+    }
+
+    fn update(&self) -> Result<()> {
+        let mut flags = 0u8;
+        if self.color == Rgb::BLACK {
+            flags |= FLAG_OFF;
+        }
+        if self.dim {
+            flags |= FLAG_DIM;
+        }
+        if let Some(speed) = self.flash {
+            flags |= FLAG_FLASH | ((speed as u8) << 3);
+        }
+
+        // Channels are sent in red, blue, green order followed by the sound
+        // settings, which are left off, and a fixed footer
+        let Rgb { r, g, b } = self.color;
+        self.handle
+            .write_raw(&[0x00, r, b, g, flags, 0x00, 0x00, 0xff, 0x22])
+    }
+}
+
+impl ColorOutput for Blynclight {
+    fn set_color(&mut self, color: Rgb) -> Result<()> {
+        self.set_solid(color)
+    }
+}
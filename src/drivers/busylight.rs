@@ -0,0 +1,156 @@
+use std::{
+    sync::{mpsc, Arc, Mutex, PoisonError},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use crate::{
+    color::{ColorOutput, Rgb},
+    DeviceHandle, Result,
+};
+
+use super::Driver;
+
+pub const PLENOM_VID: u16 = 0x27bb;
+pub const MICROCHIP_VID: u16 = 0x04d8;
+
+const REPORT_SIZE: usize = 64;
+const STEP_SIZE: usize = 8;
+
+const OP_JUMP: u8 = 0x10;
+const OP_KEEP_ALIVE: u8 = 0x80;
+
+/// The device turns itself off if it doesn't hear from the host within this timeout.
+const KEEP_ALIVE_TIMEOUT_SECS: u8 = 15;
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Kuando Busylight Alpha and Omega.
+///
+/// A background thread sends keep-alive reports for as long as this is alive.
+pub struct Busylight {
+    handle: Arc<Mutex<DeviceHandle>>,
+    keep_alive: Option<KeepAlive>,
+}
+
+struct KeepAlive {
+    stop: mpsc::Sender<()>,
+    thread: JoinHandle<()>,
+}
+
+impl Driver for Busylight {
+    const DEVICES: &'static [(u16, u16)] = &[
+        (MICROCHIP_VID, 0xf848),
+        (PLENOM_VID, 0x3bca),
+        (PLENOM_VID, 0x3bcb),
+        (PLENOM_VID, 0x3bcc),
+        (PLENOM_VID, 0x3bcd),
+        (PLENOM_VID, 0x3bce),
+        (PLENOM_VID, 0x3bcf),
+    ];
+
+    fn from_handle(handle: DeviceHandle) -> Result<Self> {
+        let handle = Arc::new(Mutex::new(handle));
+        let (stop, stopped) = mpsc::channel::<()>();
+
+        let thread = std::thread::spawn({
+            let handle = handle.clone();
+            move || {
+                let mut step = [0u8; STEP_SIZE];
+                step[0] = OP_KEEP_ALIVE | KEEP_ALIVE_TIMEOUT_SECS;
+
+                while let Err(mpsc::RecvTimeoutError::Timeout) =
+                    stopped.recv_timeout(KEEP_ALIVE_INTERVAL)
+                {
+                    let handle = handle.lock().unwrap_or_else(PoisonError::into_inner);
+                    _ = write_steps(&handle, &[step]);
+                }
+            }
+        });
+
+        Ok(Self {
+            handle,
+            keep_alive: Some(KeepAlive { stop, thread }),
+        })
+    }
+}
+
+impl Busylight {
+    pub fn set_solid(&self, color: Rgb) -> Result<()> {
+        self.write(jump_step(color, 0, 0))
+    }
+
+    /// Blinks the color, on and off times have a resolution of 100ms.
+    pub fn blink(&self, color: Rgb, on: Duration, off: Duration) -> Result<()> {
+        let tenths = |d: Duration| (d.as_millis() / 100).min(u8::MAX as u128) as u8;
+        self.write(jump_step(color, tenths(on), tenths(off)))
+    }
+
+    pub fn into_inner(mut self) -> DeviceHandle {
+        if let Some(keep_alive) = self.keep_alive.take() {
+            keep_alive.stop();
+        }
+        let handle = self.handle.clone();
+        drop(self);
+
+        match Arc::try_unwrap(handle) {
+            Ok(handle) => handle.into_inner().unwrap_or_else(PoisonError::into_inner),
+            Err(_) => unreachable!("keep-alive thread has been joined"),
+        }
+    }
+
+    fn write(&self, step: [u8; STEP_SIZE]) -> Result<()> {
+        let handle = self.handle.lock().unwrap_or_else(PoisonError::into_inner);
+        write_steps(&handle, &[step])
+    }
+}
+
+impl ColorOutput for Busylight {
+    fn set_color(&mut self, color: Rgb) -> Result<()> {
+        self.set_solid(color)
+    }
+}
+
+impl Drop for Busylight {
+    fn drop(&mut self) {
+        if let Some(keep_alive) = self.keep_alive.take() {
+            keep_alive.stop();
+        }
+    }
+}
+
+impl KeepAlive {
+    fn stop(self) {
+        drop(self.stop);
+        _ = self.thread.join();
+    }
+}
+
+fn jump_step(color: Rgb, on_time: u8, off_time: u8) -> [u8; STEP_SIZE] {
+    // Color channels are PWM duty cycles from 0 to 100
+    let pwm = |x: u8| (x as u16 * 100 / 255) as u8;
+    [
+        OP_JUMP,
+        0,
+        pwm(color.r),
+        pwm(color.g),
+        pwm(color.b),
+        on_time,
+        off_time,
+        0,
+    ]
+}
+
+fn write_steps(handle: &DeviceHandle, steps: &[[u8; STEP_SIZE]]) -> Result<()> {
+    let mut report = [0u8; REPORT_SIZE];
+    for (i, step) in steps.iter().take(7).enumerate() {
+        report[i * STEP_SIZE..(i + 1) * STEP_SIZE].copy_from_slice(step);
+    }
+    // Sensitivity, timeout and trigger followed by padding
+    report[59..62].fill(0xff);
+    let checksum: u16 = report[..62].iter().map(|x| *x as u16).sum();
+    report[62..].copy_from_slice(&checksum.to_be_bytes());
+
+    let mut buffer = [0u8; REPORT_SIZE + 1];
+    buffer[1..].copy_from_slice(&report);
+    handle.write_raw(&buffer)
+}
@@ -0,0 +1,123 @@
+use std::ops::RangeInclusive;
+
+use crate::{DeviceHandle, HidLightError, Result};
+
+use super::Driver;
+
+pub const LOGITECH_VID: u16 = 0x046d;
+pub const LITRA_GLOW_PID: u16 = 0xc900;
+pub const LITRA_BEAM_PID: u16 = 0xc901;
+
+const REPORT_SIZE: usize = 20;
+const RESPONSE_TIMEOUT_MS: i32 = 500;
+
+const CMD_GET_POWER: u8 = 0x01;
+const CMD_SET_POWER: u8 = 0x1c;
+const CMD_GET_BRIGHTNESS: u8 = 0x31;
+const CMD_SET_BRIGHTNESS: u8 = 0x4c;
+const CMD_GET_TEMPERATURE: u8 = 0x81;
+const CMD_SET_TEMPERATURE: u8 = 0x9c;
+
+pub const TEMPERATURE_KELVIN: RangeInclusive<u16> = 2700..=6500;
+
+/// Logitech Litra Glow and Litra Beam.
+pub struct Litra {
+    handle: DeviceHandle,
+    brightness_lumens: RangeInclusive<u16>,
+}
+
+impl Driver for Litra {
+    const DEVICES: &'static [(u16, u16)] = &[
+        (LOGITECH_VID, LITRA_GLOW_PID),
+        (LOGITECH_VID, LITRA_BEAM_PID),
+    ];
+
+    fn from_handle(handle: DeviceHandle) -> Result<Self> {
+        let pid = handle.hid().get_device_info()?.product_id();
+        let brightness_lumens = match pid {
+            LITRA_BEAM_PID => 30..=400,
+            _ => 20..=250,
+        };
+
+        Ok(Self {
+            handle,
+            brightness_lumens,
+        })
+    }
+}
+
+impl Litra {
+    pub fn set_power(&self, on: bool) -> Result<()> {
+        self.command(CMD_SET_POWER, &[on as u8])
+    }
+
+    pub fn power(&self) -> Result<bool> {
+        let response = self.query(CMD_GET_POWER)?;
+        Ok(response[4] == 1)
+    }
+
+    pub fn brightness_range(&self) -> RangeInclusive<u16> {
+        self.brightness_lumens.clone()
+    }
+
+    pub fn set_brightness_lumens(&self, lumens: u16) -> Result<()> {
+        if !self.brightness_lumens.contains(&lumens) {
+            return Err(HidLightError::InvalidArgument);
+        }
+        self.command(CMD_SET_BRIGHTNESS, &lumens.to_be_bytes())
+    }
+
+    pub fn brightness_lumens(&self) -> Result<u16> {
+        let response = self.query(CMD_GET_BRIGHTNESS)?;
+        Ok(u16::from_be_bytes([response[4], response[5]]))
+    }
+
+    /// Sets brightness as a fraction of the device's lumen range.
+    pub fn set_brightness(&self, value: f32) -> Result<()> {
+        let range = &self.brightness_lumens;
+        let span = (range.end() - range.start()) as f32;
+        let lumens = range.start() + (span * value.clamp(0.0, 1.0)).round() as u16;
+        self.set_brightness_lumens(lumens)
+    }
+
+    /// Sets the color temperature, rounded to the 100K steps the device supports.
+    pub fn set_temperature_kelvin(&self, kelvin: u16) -> Result<()> {
+        if !TEMPERATURE_KELVIN.contains(&kelvin) {
+            return Err(HidLightError::InvalidArgument);
+        }
+        let kelvin = (kelvin + 50) / 100 * 100;
+        self.command(CMD_SET_TEMPERATURE, &kelvin.to_be_bytes())
+    }
+
+    pub fn temperature_kelvin(&self) -> Result<u16> {
+        let response = self.query(CMD_GET_TEMPERATURE)?;
+        Ok(u16::from_be_bytes([response[4], response[5]]))
+    }
+
+    pub fn into_inner(self) -> DeviceHandle {
+        self.handle
+    }
+
+    fn command(&self, command: u8, args: &[u8]) -> Result<()> {
+        let mut buffer = [0u8; REPORT_SIZE];
+        buffer[..4].copy_from_slice(&[0x11, 0xff, 0x04, command]);
+        buffer[4..4 + args.len()].copy_from_slice(args);
+        self.handle.write_raw(&buffer)
+    }
+
+    fn query(&self, command: u8) -> Result<[u8; REPORT_SIZE]> {
+        self.command(command, &[])?;
+
+        let mut buffer = [0u8; REPORT_SIZE];
+        loop {
+            let len = self.handle.read_raw(&mut buffer, RESPONSE_TIMEOUT_MS)?;
+            if len == 0 {
+                return Err(HidLightError::NoResponse);
+            }
+            // Skip unrelated notifications, such as button presses on the Beam
+            if buffer[..4] == [0x11, 0xff, 0x04, command] {
+                return Ok(buffer);
+            }
+        }
+    }
+}
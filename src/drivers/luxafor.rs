@@ -0,0 +1,130 @@
+use crate::{
+    color::{ColorOutput, Rgb},
+    DeviceHandle, HidLightError, Result,
+};
+
+use super::Driver;
+
+pub const MICROCHIP_VID: u16 = 0x04d8;
+pub const LUXAFOR_PID: u16 = 0xf372;
+
+const MODE_STATIC: u8 = 0x01;
+const MODE_FADE: u8 = 0x02;
+const MODE_STROBE: u8 = 0x03;
+const MODE_WAVE: u8 = 0x04;
+const MODE_PATTERN: u8 = 0x06;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Led {
+    #[default]
+    All,
+    Front,
+    Back,
+    /// A single LED, numbered 1 to 6.
+    Single(u8),
+}
+
+impl Led {
+    fn code(self) -> Result<u8> {
+        match self {
+            Led::All => Ok(0xff),
+            Led::Front => Ok(0x41),
+            Led::Back => Ok(0x42),
+            Led::Single(n @ 1..=6) => Ok(n),
+            Led::Single(_) => Err(HidLightError::InvalidArgument),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wave {
+    Short = 1,
+    Long = 2,
+    OverlappingShort = 3,
+    OverlappingLong = 4,
+    Full = 5,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    TrafficLights = 1,
+    Random1 = 2,
+    Random2 = 3,
+    Random3 = 4,
+    Police = 5,
+    Random4 = 6,
+    Random5 = 7,
+    Rainbow = 8,
+}
+
+/// Luxafor Flag, Orb and Bluetooth dongle.
+pub struct Luxafor {
+    handle: DeviceHandle,
+}
+
+impl Driver for Luxafor {
+    const DEVICES: &'static [(u16, u16)] = &[(MICROCHIP_VID, LUXAFOR_PID)];
+
+    fn from_handle(handle: DeviceHandle) -> Result<Self> {
+        Ok(Self { handle })
+    }
+}
+
+impl Luxafor {
+    pub fn set_led(&self, led: Led, color: Rgb) -> Result<()> {
+        self.command([MODE_STATIC, led.code()?, color.r, color.g, color.b, 0, 0, 0])
+    }
+
+    /// Fades to the color, `speed` 0 is the fastest.
+    pub fn fade(&self, led: Led, color: Rgb, speed: u8) -> Result<()> {
+        self.command([
+            MODE_FADE,
+            led.code()?,
+            color.r,
+            color.g,
+            color.b,
+            speed,
+            0,
+            0,
+        ])
+    }
+
+    pub fn strobe(&self, led: Led, color: Rgb, speed: u8, repeat: u8) -> Result<()> {
+        let led = led.code()?;
+        self.command([
+            MODE_STROBE,
+            led,
+            color.r,
+            color.g,
+            color.b,
+            speed,
+            0,
+            repeat,
+        ])
+    }
+
+    pub fn wave(&self, wave: Wave, color: Rgb, speed: u8, repeat: u8) -> Result<()> {
+        let wave = wave as u8;
+        self.command([MODE_WAVE, wave, color.r, color.g, color.b, 0, repeat, speed])
+    }
+
+    pub fn pattern(&self, pattern: Pattern, repeat: u8) -> Result<()> {
+        self.command([MODE_PATTERN, pattern as u8, repeat, 0, 0, 0, 0, 0])
+    }
+
+    pub fn into_inner(self) -> DeviceHandle {
+        self.handle
+    }
+
+    fn command(&self, data: [u8; 8]) -> Result<()> {
+        let mut buffer = [0u8; 9];
+        buffer[1..].copy_from_slice(&data);
+        self.handle.write_raw(&buffer)
+    }
+}
+
+impl ColorOutput for Luxafor {
+    fn set_color(&mut self, color: Rgb) -> Result<()> {
+        self.set_led(Led::All, color)
+    }
+}
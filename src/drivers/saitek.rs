@@ -0,0 +1,151 @@
+use crate::{DeviceHandle, HidLightError, Result};
+
+use super::Driver;
+
+pub const SAITEK_VID: u16 = 0x06a3;
+pub const MULTI_PANEL_PID: u16 = 0x0d06;
+pub const SWITCH_PANEL_PID: u16 = 0x0d67;
+
+const DIGIT_BLANK: u8 = 0x0f;
+const DIGIT_MINUS: u8 = 0xde;
+
+/// Landing gear indicators on the Switch Panel, amber is both the red and green LED.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwitchPanelLed {
+    NoseGreen = 0,
+    LeftGreen = 1,
+    RightGreen = 2,
+    NoseRed = 3,
+    LeftRed = 4,
+    RightRed = 5,
+}
+
+pub struct SwitchPanel {
+    handle: DeviceHandle,
+    leds: u8,
+}
+
+impl Driver for SwitchPanel {
+    const DEVICES: &'static [(u16, u16)] = &[(SAITEK_VID, SWITCH_PANEL_PID)];
+
+    fn from_handle(handle: DeviceHandle) -> Result<Self> {
+        Ok(Self { handle, leds: 0 })
+    }
+}
+
+impl SwitchPanel {
+    pub fn set_led(&mut self, led: SwitchPanelLed, on: bool) -> Result<()> {
+        set_bit(&mut self.leds, led as u8, on);
+        self.handle.send_feature_raw(&[0x00, self.leds])
+    }
+
+    pub fn led(&self, led: SwitchPanelLed) -> bool {
+        self.leds & (1 << led as u8) != 0
+    }
+
+    pub fn into_inner(self) -> DeviceHandle {
+        self.handle
+    }
+}
+
+/// Autopilot button backlights on the Multi Panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultiPanelLed {
+    Ap = 0,
+    Hdg = 1,
+    Nav = 2,
+    Ias = 3,
+    Alt = 4,
+    Vs = 5,
+    Apr = 6,
+    Rev = 7,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultiPanelDisplay {
+    Top,
+    Bottom,
+}
+
+pub struct MultiPanel {
+    handle: DeviceHandle,
+    top: [u8; 5],
+    bottom: [u8; 5],
+    leds: u8,
+}
+
+impl Driver for MultiPanel {
+    const DEVICES: &'static [(u16, u16)] = &[(SAITEK_VID, MULTI_PANEL_PID)];
+
+    fn from_handle(handle: DeviceHandle) -> Result<Self> {
+        Ok(Self {
+            handle,
+            top: [DIGIT_BLANK; 5],
+            bottom: [DIGIT_BLANK; 5],
+            leds: 0,
+        })
+    }
+}
+
+impl MultiPanel {
+    pub const DISPLAY_DIGITS: usize = 5;
+
+    pub fn set_led(&mut self, led: MultiPanelLed, on: bool) -> Result<()> {
+        set_bit(&mut self.leds, led as u8, on);
+        self.update()
+    }
+
+    pub fn led(&self, led: MultiPanelLed) -> bool {
+        self.leds & (1 << led as u8) != 0
+    }
+
+    /// Shows a right aligned number, `None` blanks the display.
+    pub fn set_display(&mut self, display: MultiPanelDisplay, value: Option<i32>) -> Result<()> {
+        let digits = match value {
+            Some(value) => seven_segment_digits(value).ok_or(HidLightError::InvalidArgument)?,
+            None => [DIGIT_BLANK; 5],
+        };
+        match display {
+            MultiPanelDisplay::Top => self.top = digits,
+            MultiPanelDisplay::Bottom => self.bottom = digits,
+        }
+        self.update()
+    }
+
+    pub fn into_inner(self) -> DeviceHandle {
+        self.handle
+    }
+
+    fn update(&self) -> Result<()> {
+        let mut buffer = [0u8; 12];
+        buffer[1..6].copy_from_slice(&self.top);
+        buffer[6..11].copy_from_slice(&self.bottom);
+        buffer[11] = self.leds;
+        self.handle.send_feature_raw(&buffer)
+    }
+}
+
+fn set_bit(bits: &mut u8, bit: u8, on: bool) {
+    if on {
+        *bits |= 1 << bit;
+    } else {
+        *bits &= !(1 << bit);
+    }
+}
+
+fn seven_segment_digits(value: i32) -> Option<[u8; MultiPanel::DISPLAY_DIGITS]> {
+    let text = value.to_string();
+    if text.len() > MultiPanel::DISPLAY_DIGITS {
+        return None;
+    }
+
+    let mut digits = [DIGIT_BLANK; MultiPanel::DISPLAY_DIGITS];
+    let offset = MultiPanel::DISPLAY_DIGITS - text.len();
+    for (digit, c) in digits[offset..].iter_mut().zip(text.chars()) {
+        *digit = match c {
+            '-' => DIGIT_MINUS,
+            c => c.to_digit(10)? as u8,
+        };
+    }
+    Some(digits)
+}
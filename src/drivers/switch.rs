@@ -0,0 +1,110 @@
+use crate::{DeviceHandle, Result};
+
+use super::Driver;
+
+pub const NINTENDO_VID: u16 = 0x057e;
+pub const JOYCON_L_PID: u16 = 0x2006;
+pub const JOYCON_R_PID: u16 = 0x2007;
+pub const PRO_CONTROLLER_PID: u16 = 0x2009;
+
+const OUTPUT_REPORT_SIZE: usize = 49;
+const NEUTRAL_RUMBLE: [u8; 8] = [0x00, 0x01, 0x40, 0x40, 0x00, 0x01, 0x40, 0x40];
+
+const SUBCMD_SET_PLAYER_LIGHTS: u8 = 0x30;
+const SUBCMD_SET_HOME_LIGHT: u8 = 0x38;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PlayerLight {
+    #[default]
+    Off,
+    On,
+    Flashing,
+}
+
+/// Joy-Con or Switch Pro Controller, driven through the subcommand protocol.
+pub struct SwitchController {
+    handle: DeviceHandle,
+    packet_counter: u8,
+}
+
+impl Driver for SwitchController {
+    const DEVICES: &'static [(u16, u16)] = &[
+        (NINTENDO_VID, JOYCON_L_PID),
+        (NINTENDO_VID, JOYCON_R_PID),
+        (NINTENDO_VID, PRO_CONTROLLER_PID),
+    ];
+
+    fn from_handle(handle: DeviceHandle) -> Result<Self> {
+        let is_usb = handle
+            .hid()
+            .get_device_info()
+            .is_ok_and(|x| matches!(x.bus_type(), hidapi::BusType::Usb));
+
+        if is_usb {
+            // Wired controllers only accept subcommands after a handshake and
+            // disconnect after a while unless told otherwise
+            handle.write_raw(&[0x80, 0x02])?;
+            handle.write_raw(&[0x80, 0x04])?;
+        }
+
+        Ok(Self {
+            handle,
+            packet_counter: 0,
+        })
+    }
+}
+
+impl SwitchController {
+    pub fn set_player_lights(&mut self, lights: [PlayerLight; 4]) -> Result<()> {
+        let mut arg = 0u8;
+        for (i, light) in lights.iter().enumerate() {
+            match light {
+                PlayerLight::Off => {}
+                PlayerLight::On => arg |= 1 << i,
+                PlayerLight::Flashing => arg |= 1 << (i + 4),
+            }
+        }
+        self.subcommand(SUBCMD_SET_PLAYER_LIGHTS, &[arg])
+    }
+
+    /// Lights the pattern the console uses for players 1 to 8, anything else turns them off.
+    pub fn set_player(&mut self, player: u8) -> Result<()> {
+        use PlayerLight::{Off, On};
+        let lights = match player {
+            1 => [On, Off, Off, Off],
+            2 => [On, On, Off, Off],
+            3 => [On, On, On, Off],
+            4 => [On, On, On, On],
+            5 => [On, Off, Off, On],
+            6 => [On, Off, On, Off],
+            7 => [On, Off, On, On],
+            8 => [Off, On, On, Off],
+            _ => [Off; 4],
+        };
+        self.set_player_lights(lights)
+    }
+
+    /// Sets the home button ring brightness, ignored by the left Joy-Con.
+    pub fn set_home_light(&mut self, brightness: f32) -> Result<()> {
+        let level = (brightness.clamp(0.0, 1.0) * 15.0).round() as u8;
+        // One mini cycle holding the given level indefinitely
+        let args = [0x01, level << 4, level | (level << 4), 0x11, 0x11];
+        self.subcommand(SUBCMD_SET_HOME_LIGHT, &args)
+    }
+
+    pub fn into_inner(self) -> DeviceHandle {
+        self.handle
+    }
+
+    fn subcommand(&mut self, id: u8, args: &[u8]) -> Result<()> {
+        let mut buffer = [0u8; OUTPUT_REPORT_SIZE];
+        buffer[0] = 0x01;
+        buffer[1] = self.packet_counter;
+        buffer[2..10].copy_from_slice(&NEUTRAL_RUMBLE);
+        buffer[10] = id;
+        buffer[11..11 + args.len()].copy_from_slice(args);
+        self.packet_counter = (self.packet_counter + 1) & 0x0f;
+
+        self.handle.write_raw(&buffer)
+    }
+}
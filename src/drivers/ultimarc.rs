@@ -0,0 +1,182 @@
+use crate::{DeviceHandle, HidLightError, Result};
+
+use super::Driver;
+
+pub const ULTIMARC_VID: u16 = 0xd209;
+
+const PACLED64_LEDS: usize = 64;
+const ULTIMATE_IO_LEDS: usize = 96;
+const PACDRIVE_LEDS: usize = 16;
+
+const CMD_SET_ALL_INTENSITY: u8 = 0x80;
+
+/// Names for the lamps wired to a controller, defaulting to their port number.
+#[derive(Debug, Clone)]
+pub struct LampNames {
+    names: Vec<String>,
+}
+
+impl LampNames {
+    fn new(count: usize) -> Self {
+        Self {
+            names: (1..=count).map(|x| format!("LED {x}")).collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn set(&mut self, index: usize, name: impl Into<String>) -> Result<()> {
+        let slot = self
+            .names
+            .get_mut(index)
+            .ok_or(HidLightError::InvalidArgument)?;
+        *slot = name.into();
+        Ok(())
+    }
+
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.names.get(index).map(|x| x.as_str())
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|x| x == name)
+    }
+}
+
+/// PacDrive, 16 on/off outputs.
+pub struct PacDrive {
+    handle: DeviceHandle,
+    state: u16,
+    pub names: LampNames,
+}
+
+impl Driver for PacDrive {
+    const DEVICES: &'static [(u16, u16)] = &[
+        (ULTIMARC_VID, 0x1500),
+        (ULTIMARC_VID, 0x1501),
+        (ULTIMARC_VID, 0x1502),
+        (ULTIMARC_VID, 0x1503),
+        (ULTIMARC_VID, 0x1504),
+        (ULTIMARC_VID, 0x1505),
+        (ULTIMARC_VID, 0x1506),
+        (ULTIMARC_VID, 0x1507),
+    ];
+
+    fn from_handle(handle: DeviceHandle) -> Result<Self> {
+        Ok(Self {
+            handle,
+            state: 0,
+            names: LampNames::new(PACDRIVE_LEDS),
+        })
+    }
+}
+
+impl PacDrive {
+    pub fn set(&mut self, index: usize, on: bool) -> Result<()> {
+        if index >= PACDRIVE_LEDS {
+            return Err(HidLightError::InvalidArgument);
+        }
+        if on {
+            self.state |= 1 << index;
+        } else {
+            self.state &= !(1 << index);
+        }
+        self.update()
+    }
+
+    pub fn set_named(&mut self, name: &str, on: bool) -> Result<()> {
+        let index = self
+            .names
+            .index_of(name)
+            .ok_or(HidLightError::InvalidArgument)?;
+        self.set(index, on)
+    }
+
+    pub fn set_all(&mut self, state: u16) -> Result<()> {
+        self.state = state;
+        self.update()
+    }
+
+    pub fn state(&self) -> u16 {
+        self.state
+    }
+
+    pub fn into_inner(self) -> DeviceHandle {
+        self.handle
+    }
+
+    fn update(&self) -> Result<()> {
+        let [hi, lo] = self.state.to_be_bytes();
+        self.handle.write_raw(&[0x00, 0x00, 0x00, hi, lo])
+    }
+}
+
+/// PacLED64 and Ultimate I/O, outputs with 256 intensity levels.
+pub struct PacLed {
+    handle: DeviceHandle,
+    count: usize,
+    pub names: LampNames,
+}
+
+impl Driver for PacLed {
+    const DEVICES: &'static [(u16, u16)] = &[
+        (ULTIMARC_VID, 0x1401),
+        (ULTIMARC_VID, 0x1402),
+        (ULTIMARC_VID, 0x1403),
+        (ULTIMARC_VID, 0x1404),
+        (ULTIMARC_VID, 0x0410),
+        (ULTIMARC_VID, 0x0411),
+        (ULTIMARC_VID, 0x0412),
+        (ULTIMARC_VID, 0x0413),
+    ];
+
+    fn from_handle(handle: DeviceHandle) -> Result<Self> {
+        let pid = handle.hid().get_device_info()?.product_id();
+        let count = match pid {
+            0x0410..=0x0413 => ULTIMATE_IO_LEDS,
+            _ => PACLED64_LEDS,
+        };
+
+        Ok(Self {
+            handle,
+            count,
+            names: LampNames::new(count),
+        })
+    }
+}
+
+impl PacLed {
+    pub fn led_count(&self) -> usize {
+        self.count
+    }
+
+    pub fn set_intensity(&self, index: usize, intensity: u8) -> Result<()> {
+        if index >= self.count {
+            return Err(HidLightError::InvalidArgument);
+        }
+        self.handle.write_raw(&[0x00, index as u8, intensity])
+    }
+
+    pub fn set_named(&self, name: &str, intensity: u8) -> Result<()> {
+        let index = self
+            .names
+            .index_of(name)
+            .ok_or(HidLightError::InvalidArgument)?;
+        self.set_intensity(index, intensity)
+    }
+
+    pub fn set_all(&self, intensity: u8) -> Result<()> {
+        self.handle
+            .write_raw(&[0x00, CMD_SET_ALL_INTENSITY, intensity])
+    }
+
+    pub fn into_inner(self) -> DeviceHandle {
+        self.handle
+    }
+}
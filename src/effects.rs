@@ -0,0 +1,465 @@
+//! Time based effects that drive outputs, so every app doesn't write its own blink loop.
+
+use std::{
+    collections::BTreeMap,
+    f32::consts::TAU,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use crate::{DeviceHandle, OutputId, Result, Ticker};
+
+/// An output value over time.
+pub trait Effect: Send {
+    /// The value, 0 to 1, at `t` after the effect started on an output that was at
+    /// `initial`. `None` once the effect is over.
+    fn sample(&self, t: Duration, initial: f32) -> Option<f32>;
+
+    /// How long the effect lasts, `None` if it never ends.
+    fn duration(&self) -> Option<Duration>;
+
+    /// Stops the effect after `duration`.
+    fn limit(self, duration: Duration) -> Limit<Self>
+    where
+        Self: Sized,
+    {
+        Limit {
+            effect: self,
+            duration,
+        }
+    }
+
+    /// Plays `next` once this one is over, starting from where this one ended.
+    fn then<E: Effect>(self, next: E) -> Then<Self, E>
+    where
+        Self: Sized,
+    {
+        Then { first: self, next }
+    }
+
+    /// Scales the values into `min..=max`.
+    fn range(self, min: f32, max: f32) -> Range<Self>
+    where
+        Self: Sized,
+    {
+        Range {
+            effect: self,
+            min,
+            max,
+        }
+    }
+}
+
+/// On for `duty` of every `period`, forever.
+#[derive(Debug, Clone, Copy)]
+pub struct Blink {
+    pub period: Duration,
+    pub duty: f32,
+}
+
+impl Blink {
+    pub fn new(period: Duration) -> Self {
+        Self { period, duty: 0.5 }
+    }
+
+    pub fn duty(mut self, duty: f32) -> Self {
+        self.duty = duty.clamp(0.0, 1.0);
+        self
+    }
+}
+
+impl Effect for Blink {
+    fn sample(&self, t: Duration, _initial: f32) -> Option<f32> {
+        let phase = phase(t, self.period);
+        Some(if phase < self.duty { 1.0 } else { 0.0 })
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// A sine between off and full, starting off, forever.
+#[derive(Debug, Clone, Copy)]
+pub struct Breathe {
+    pub period: Duration,
+}
+
+impl Breathe {
+    pub fn new(period: Duration) -> Self {
+        Self { period }
+    }
+}
+
+impl Effect for Breathe {
+    fn sample(&self, t: Duration, _initial: f32) -> Option<f32> {
+        let phase = phase(t, self.period);
+        Some(0.5 - 0.5 * (phase * TAU).cos())
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// A linear fade to `to`, from `from` or wherever the output was.
+#[derive(Debug, Clone, Copy)]
+pub struct Fade {
+    pub from: Option<f32>,
+    pub to: f32,
+    pub duration: Duration,
+}
+
+impl Fade {
+    pub fn to(to: f32, duration: Duration) -> Self {
+        Self {
+            from: None,
+            to,
+            duration,
+        }
+    }
+
+    pub fn starting_at(mut self, from: f32) -> Self {
+        self.from = Some(from);
+        self
+    }
+}
+
+impl Effect for Fade {
+    fn sample(&self, t: Duration, initial: f32) -> Option<f32> {
+        if t > self.duration {
+            return None;
+        }
+        let from = self.from.unwrap_or(initial);
+        let progress = if self.duration.is_zero() {
+            1.0
+        } else {
+            t.as_secs_f32() / self.duration.as_secs_f32()
+        };
+        Some(from + (self.to - from) * progress)
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        Some(self.duration)
+    }
+}
+
+/// Holds a value forever.
+#[derive(Debug, Clone, Copy)]
+pub struct Constant(pub f32);
+
+impl Effect for Constant {
+    fn sample(&self, _t: Duration, _initial: f32) -> Option<f32> {
+        Some(self.0)
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Limit<E> {
+    effect: E,
+    duration: Duration,
+}
+
+impl<E: Effect> Effect for Limit<E> {
+    fn sample(&self, t: Duration, initial: f32) -> Option<f32> {
+        if t > self.duration {
+            None
+        } else {
+            self.effect.sample(t, initial)
+        }
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        Some(
+            self.effect
+                .duration()
+                .map_or(self.duration, |x| x.min(self.duration)),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Then<A, B> {
+    first: A,
+    next: B,
+}
+
+impl<A: Effect, B: Effect> Effect for Then<A, B> {
+    fn sample(&self, t: Duration, initial: f32) -> Option<f32> {
+        match self.first.duration() {
+            Some(end) if t > end => {
+                let last = self.first.sample(end, initial).unwrap_or(initial);
+                self.next.sample(t - end, last)
+            }
+            _ => self.first.sample(t, initial),
+        }
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        Some(self.first.duration()? + self.next.duration()?)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Range<E> {
+    effect: E,
+    min: f32,
+    max: f32,
+}
+
+impl<E: Effect> Effect for Range<E> {
+    fn sample(&self, t: Duration, initial: f32) -> Option<f32> {
+        self.effect
+            .sample(t, initial)
+            .map(|x| self.min + (self.max - self.min) * x)
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        self.effect.duration()
+    }
+}
+
+fn phase(t: Duration, period: Duration) -> f32 {
+    if period.is_zero() {
+        return 0.0;
+    }
+    (t.as_secs_f64() / period.as_secs_f64()).fract() as f32
+}
+
+/// How a layer combines with the layers below it.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Blend {
+    /// Covers everything below.
+    #[default]
+    Replace,
+    Add,
+    Max,
+    /// Mixes over what's below, 0 leaving it as is and 1 covering it.
+    Alpha(f32),
+}
+
+impl Blend {
+    fn apply(self, below: f32, value: f32) -> f32 {
+        match self {
+            Blend::Replace => value,
+            Blend::Add => below + value,
+            Blend::Max => below.max(value),
+            Blend::Alpha(a) => below + (value - below) * a.clamp(0.0, 1.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct LayerId(u64);
+
+struct Layer {
+    id: LayerId,
+    output: OutputId,
+    effect: Box<dyn Effect>,
+    initial: f32,
+    blend: Blend,
+    priority: i32,
+}
+
+/// Plays effects on outputs of one device, as layers composited every frame.
+pub struct Player {
+    layers: Vec<Layer>,
+    /// What outputs were at before their first layer, the bottom of the stack.
+    base: BTreeMap<OutputId, f32>,
+    next_layer: u64,
+    frame: Duration,
+    start: Instant,
+}
+
+impl Default for Player {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Player {
+    pub fn new() -> Self {
+        Self {
+            layers: vec![],
+            base: BTreeMap::new(),
+            next_layer: 0,
+            frame: Duration::from_millis(20),
+            start: Instant::now(),
+        }
+    }
+
+    /// Measures time from `start` instead of from when the player was created, to
+    /// line it up with other players.
+    pub fn start_at(mut self, start: Instant) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// How often outputs are updated, 50 times a second by default.
+    pub fn frame_interval(mut self, frame: Duration) -> Self {
+        self.frame = frame;
+        self
+    }
+
+    /// Plays `effect` on each of `outputs`, replacing whatever they were playing.
+    pub fn add(
+        &mut self,
+        handle: &mut DeviceHandle,
+        outputs: &[OutputId],
+        effect: impl Effect + Clone + 'static,
+    ) -> Result<LayerId> {
+        self.layers.retain(|x| !outputs.contains(&x.output));
+        self.add_layer(handle, outputs, effect, Blend::Replace, 0)
+    }
+
+    /// Plays `effect` on each of `outputs` on top of, or below, whatever they're
+    /// playing. Layers are composited from the lowest priority up, layers with the
+    /// same priority in the order they were added.
+    pub fn add_layer(
+        &mut self,
+        handle: &mut DeviceHandle,
+        outputs: &[OutputId],
+        effect: impl Effect + Clone + 'static,
+        blend: Blend,
+        priority: i32,
+    ) -> Result<LayerId> {
+        let id = LayerId(self.next_layer);
+        self.next_layer += 1;
+
+        let t = self.start.elapsed();
+        for output in outputs {
+            let initial = handle.output(*output)?.real_value;
+            self.base.entry(*output).or_insert(initial);
+            self.layers.push(Layer {
+                id,
+                output: *output,
+                effect: Box::new(Offset(effect.clone(), t)),
+                initial,
+                blend,
+                priority,
+            });
+        }
+        Ok(id)
+    }
+
+    pub fn remove_layer(&mut self, id: LayerId) {
+        self.layers.retain(|x| x.id != id);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// The composited value of every output with layers at `t` since the player was
+    /// created, dropping layers that have ended.
+    pub fn frame(&mut self, t: Duration) -> Vec<(OutputId, f32)> {
+        let mut samples = vec![];
+        self.layers
+            .retain(|layer| match layer.effect.sample(t, layer.initial) {
+                Some(value) => {
+                    samples.push((layer.output, layer.priority, layer.blend, value));
+                    true
+                }
+                None => false,
+            });
+        // Stable, so equal priorities stay in the order they were added
+        samples.sort_by_key(|x| (x.0, x.1));
+
+        let mut values: Vec<(OutputId, f32)> = vec![];
+        for (output, _, blend, value) in samples {
+            match values.last_mut() {
+                Some((last, below)) if *last == output => *below = blend.apply(*below, value),
+                _ => {
+                    let base = self.base.get(&output).copied().unwrap_or_default();
+                    values.push((output, blend.apply(base, value)));
+                }
+            }
+        }
+        for (_, value) in &mut values {
+            *value = value.clamp(0.0, 1.0);
+        }
+        values
+    }
+
+    /// Sets every output to its current composited value and writes the changes.
+    pub fn update(&mut self, handle: &mut DeviceHandle) -> Result<()> {
+        for (id, value) in self.frame(self.start.elapsed()) {
+            handle.set(id, value)?;
+        }
+        handle.flush()
+    }
+
+    /// Updates until every effect has ended, which may be never.
+    pub fn run(&mut self, handle: &mut DeviceHandle) -> Result<()> {
+        let mut ticker = self.ticker(handle);
+        while !self.is_finished() {
+            self.update(handle)?;
+            ticker.tick();
+        }
+        Ok(())
+    }
+
+    fn ticker(&self, handle: &DeviceHandle) -> Ticker {
+        Ticker::for_device(handle, 1.0 / self.frame.as_secs_f32())
+    }
+
+    /// Runs the effects on a writer thread until they end or it's stopped.
+    pub fn spawn(mut self, mut handle: DeviceHandle) -> EffectThread {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let thread = std::thread::spawn(move || {
+            let mut ticker = self.ticker(&handle);
+            while !self.is_finished() && !thread_stop.load(Ordering::Relaxed) {
+                self.update(&mut handle)?;
+                ticker.tick();
+            }
+            Ok(handle)
+        });
+        EffectThread { stop, thread }
+    }
+}
+
+/// Starts an effect partway through the player's clock.
+struct Offset<E>(E, Duration);
+
+impl<E: Effect> Effect for Offset<E> {
+    fn sample(&self, t: Duration, initial: f32) -> Option<f32> {
+        self.0.sample(t.saturating_sub(self.1), initial)
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        self.0.duration().map(|x| x + self.1)
+    }
+}
+
+pub struct EffectThread {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<Result<DeviceHandle>>,
+}
+
+impl EffectThread {
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Stops the effects where they are and hands the device back.
+    pub fn stop(self) -> Result<DeviceHandle> {
+        self.stop.store(true, Ordering::Relaxed);
+        self.join()
+    }
+
+    /// Waits for every effect to end.
+    pub fn join(self) -> Result<DeviceHandle> {
+        self.thread
+            .join()
+            .unwrap_or_else(|x| std::panic::resume_unwind(x))
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{
+    drivers, indicators::LED_PAGE, lamp_array::LIGHTING_PAGE, profiles, quirks, DeviceInfo,
+    HidLights,
+};
+
+const GENERIC_DESKTOP_PAGE: u16 = 0x01;
+/// Joystick, game pad, keyboard and keypad, which often have LEDs. Mice, pointers and
+/// system controls rarely do.
+const GENERIC_DESKTOP_LIGHTS: [u16; 4] = [0x04, 0x05, 0x06, 0x07];
+
+/// Narrows enumeration down to the devices a user is looking for. Every criterion
+/// that's set has to match.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceFilter {
+    pub vid: Option<u16>,
+    pub pid: Option<u16>,
+    pub usage_page: Option<u16>,
+    /// Case insensitive search of the product, manufacturer and serial strings, and
+    /// of `vid:pid` in hex.
+    pub text: Option<String>,
+    /// Only devices with something to light, see [`crate::Capabilities::is_controllable`].
+    pub controllable: bool,
+}
+
+impl DeviceFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn vid(mut self, vid: u16) -> Self {
+        self.vid = Some(vid);
+        self
+    }
+
+    pub fn pid(mut self, pid: u16) -> Self {
+        self.pid = Some(pid);
+        self
+    }
+
+    pub fn usage_page(mut self, page: u16) -> Self {
+        self.usage_page = Some(page);
+        self
+    }
+
+    /// Ignored when empty.
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        let text = text.into();
+        self.text = (!text.trim().is_empty()).then_some(text);
+        self
+    }
+
+    pub fn controllable(mut self) -> Self {
+        self.controllable = true;
+        self
+    }
+
+    /// Whether the device matches. Checking [`Self::controllable`] briefly opens the
+    /// device, so it's done last, once everything else matches.
+    pub fn matches(&self, info: &DeviceInfo) -> bool {
+        let eq = |want: Option<u16>, have: u16| want.is_none() || want == Some(have);
+        let ids = eq(self.vid, info.vid)
+            && eq(self.pid, info.pid)
+            && eq(self.usage_page, info.usage_page);
+        if !ids || !self.matches_text(info) {
+            return false;
+        }
+        !self.controllable || info.probe().is_ok_and(|x| x.is_controllable())
+    }
+
+    fn matches_text(&self, info: &DeviceInfo) -> bool {
+        let Some(text) = &self.text else {
+            return true;
+        };
+        let text = text.trim().to_lowercase();
+        let ids = format!("{:04x}:{:04x}", info.vid, info.pid);
+        [&info.name, &info.manufacturer, &info.serial]
+            .into_iter()
+            .flatten()
+            .chain([&ids])
+            .any(|x| x.to_lowercase().contains(&text))
+    }
+}
+
+impl DeviceInfo {
+    /// A guess at whether the device has lights, from what's known without opening
+    /// it: its top-level usage, the built-in drivers, profiles and quirks, and any
+    /// layout already cached for the interface. Can be wrong both ways,
+    /// [`Self::probe`] is definitive.
+    pub fn is_likely_light(&self) -> bool {
+        let page = self.usage_page;
+        page == LED_PAGE
+            || page == LIGHTING_PAGE
+            || (page == GENERIC_DESKTOP_PAGE && GENERIC_DESKTOP_LIGHTS.contains(&self.usage_id))
+            || drivers::is_supported(self)
+            || profiles::for_device(self).is_some()
+            || quirks::QUIRKS
+                .iter()
+                .any(|x| x.device.matches(self.vid, self.pid, self.name.as_deref()))
+            || self.cache.has_outputs(&self.path)
+    }
+}
+
+impl HidLights {
+    /// The devices that look like they have lights, see [`DeviceInfo::is_likely_light`].
+    /// Unlike [`DeviceFilter::controllable`] no device is opened, so it's cheap enough
+    /// for a default device list.
+    pub fn light_devices(&self) -> Vec<DeviceInfo> {
+        self.devices()
+            .into_iter()
+            .filter(DeviceInfo::is_likely_light)
+            .collect()
+    }
+
+    /// The devices matching `filter`, in enumeration order.
+    pub fn filtered_devices(&self, filter: &DeviceFilter) -> Vec<DeviceInfo> {
+        self.devices()
+            .into_iter()
+            .filter(|x| filter.matches(x))
+            .collect()
+    }
+}
@@ -0,0 +1,285 @@
+//! A small server taking light frames over UDP or WebSocket, so games and tools can
+//! drive lights without linking this crate.
+//!
+//! A frame sets some outputs of one device, each as a fraction of its range like
+//! [`DeviceHandle::set`], and is flushed as one update. As JSON, in a UDP datagram or
+//! a WebSocket text message, outputs are keyed by name (ignoring case) or index:
+//!
+//! ```json
+//! { "device": "keyboard", "outputs": { "Caps Lock": 1.0, "3": 0.5 } }
+//! ```
+//!
+//! The binary form, in a datagram or binary message, addresses outputs by index:
+//!
+//! ```text
+//! u8      format version, 1
+//! u8      length of the device name, then the name as UTF-8
+//! then for each output, little endian
+//! u16     output index
+//! u16     value, 0 to 65535
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::ErrorKind,
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use crate::{DeviceHandle, HidLightError, Result};
+
+const BINARY_VERSION: u8 = 1;
+
+/// How often the server threads check the stop flag.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+pub struct Frame {
+    pub device: String,
+    pub outputs: BTreeMap<String, f32>,
+}
+
+impl Frame {
+    pub fn new(device: impl Into<String>) -> Self {
+        Self {
+            device: device.into(),
+            outputs: BTreeMap::new(),
+        }
+    }
+
+    /// Sets an output, by name or index.
+    pub fn set(mut self, output: impl ToString, value: f32) -> Self {
+        self.outputs.insert(output.to_string(), value);
+        self
+    }
+
+    pub fn from_json(data: &[u8]) -> Result<Self> {
+        serde_json::from_slice(data).map_err(|x| frame_error(&x))
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Decodes the binary form, see the module docs.
+    pub fn from_binary(data: &[u8]) -> Result<Self> {
+        let [version, len, rest @ ..] = data else {
+            return Err(frame_error(&"frame too short"));
+        };
+        if *version != BINARY_VERSION {
+            return Err(frame_error(&format_args!("unknown frame format {version}")));
+        }
+        if rest.len() < *len as usize {
+            return Err(frame_error(&"frame too short"));
+        }
+        let (name, values) = rest.split_at(*len as usize);
+        if values.len() % 4 != 0 {
+            return Err(frame_error(&"frame ends mid output"));
+        }
+
+        let device = std::str::from_utf8(name).map_err(|x| frame_error(&x))?;
+        let outputs = values
+            .chunks_exact(4)
+            .map(|x| {
+                let index = u16::from_le_bytes([x[0], x[1]]);
+                let value = u16::from_le_bytes([x[2], x[3]]);
+                (index.to_string(), value as f32 / u16::MAX as f32)
+            })
+            .collect();
+        Ok(Self {
+            device: device.into(),
+            outputs,
+        })
+    }
+
+    /// Encodes the binary form. Fails if an output is keyed by name or the device
+    /// name is over 255 bytes.
+    pub fn to_binary(&self) -> Result<Vec<u8>> {
+        let len = u8::try_from(self.device.len()).map_err(|_| HidLightError::InvalidArgument)?;
+        let mut data = vec![BINARY_VERSION, len];
+        data.extend_from_slice(self.device.as_bytes());
+        for (output, value) in &self.outputs {
+            let index: u16 = output.parse().map_err(|_| HidLightError::InvalidArgument)?;
+            let value = (value.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16;
+            data.extend_from_slice(&index.to_le_bytes());
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        Ok(data)
+    }
+}
+
+/// The devices frames can address, shared by any number of listeners.
+#[derive(Clone, Default)]
+pub struct FrameServer {
+    devices: Arc<Mutex<HashMap<String, DeviceHandle>>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl FrameServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes a device addressable as `name`.
+    pub fn add_device(&self, name: impl Into<String>, handle: DeviceHandle) {
+        self.devices().insert(name.into(), handle);
+    }
+
+    pub fn remove_device(&self, name: &str) -> Option<DeviceHandle> {
+        self.devices().remove(name)
+    }
+
+    /// Set to stop the listeners.
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    /// Sets and flushes the frame's outputs. Outputs the device doesn't have are
+    /// an error, and nothing is sent.
+    pub fn apply(&self, frame: &Frame) -> Result<()> {
+        let mut devices = self.devices();
+        let handle = devices
+            .get_mut(&frame.device)
+            .ok_or_else(|| frame_error(&format_args!("no device named {}", frame.device)))?;
+
+        let mut values = vec![];
+        for (output, value) in &frame.outputs {
+            let id = handle.find_output(output)?.ok_or_else(|| {
+                frame_error(&format_args!("{} has no output {output}", frame.device))
+            })?;
+            values.push((id, *value));
+        }
+        for (id, value) in values {
+            handle.set(id, value)?;
+        }
+        handle.flush()
+    }
+
+    /// Decodes and applies a UDP datagram, JSON if it starts with `{`.
+    pub fn apply_datagram(&self, data: &[u8]) -> Result<()> {
+        let frame = match data.first() {
+            Some(b'{') => Frame::from_json(data)?,
+            _ => Frame::from_binary(data)?,
+        };
+        self.apply(&frame)
+    }
+
+    /// Listens for frames on a UDP socket, on a new thread. The thread ends when the
+    /// stop flag is set or on a device error, bad frames are skipped.
+    pub fn serve_udp(
+        &self,
+        addr: impl ToSocketAddrs,
+    ) -> Result<(SocketAddr, JoinHandle<Result<()>>)> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_read_timeout(Some(POLL_INTERVAL))?;
+        let local = socket.local_addr()?;
+
+        let server = self.clone();
+        let thread = std::thread::spawn(move || {
+            let mut buffer = vec![0; 65536];
+            while !server.stop.load(Ordering::Relaxed) {
+                let len = match socket.recv(&mut buffer) {
+                    Ok(len) => len,
+                    Err(err) if is_timeout(&err) => continue,
+                    Err(err) => return Err(err.into()),
+                };
+                skip_bad_frames(server.apply_datagram(&buffer[..len]))?;
+            }
+            Ok(())
+        });
+        Ok((local, thread))
+    }
+
+    /// Accepts WebSocket connections, each handled on its own thread. Text messages
+    /// are JSON frames and binary messages binary ones.
+    pub fn serve_websocket(
+        &self,
+        addr: impl ToSocketAddrs,
+    ) -> Result<(SocketAddr, JoinHandle<Result<()>>)> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let local = listener.local_addr()?;
+
+        let server = self.clone();
+        let thread = std::thread::spawn(move || {
+            while !server.stop.load(Ordering::Relaxed) {
+                let stream = match listener.accept() {
+                    Ok((stream, _)) => stream,
+                    Err(err) if is_timeout(&err) => {
+                        std::thread::sleep(POLL_INTERVAL);
+                        continue;
+                    }
+                    Err(err) => return Err(err.into()),
+                };
+                let server = server.clone();
+                std::thread::spawn(move || {
+                    if let Err(_err) = server.handle_websocket(stream) {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!(%_err, "WebSocket connection closed");
+                    }
+                });
+            }
+            Ok(())
+        });
+        Ok((local, thread))
+    }
+
+    fn handle_websocket(&self, stream: TcpStream) -> Result<()> {
+        stream.set_nonblocking(false)?;
+        let mut socket = tungstenite::accept(stream).map_err(|x| frame_error(&x))?;
+        socket.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+
+        while !self.stop.load(Ordering::Relaxed) {
+            let result = match socket.read() {
+                Ok(tungstenite::Message::Text(text)) => {
+                    Frame::from_json(text.as_bytes()).and_then(|x| self.apply(&x))
+                }
+                Ok(tungstenite::Message::Binary(data)) => {
+                    Frame::from_binary(&data).and_then(|x| self.apply(&x))
+                }
+                Ok(tungstenite::Message::Close(_)) => break,
+                Ok(_) => Ok(()),
+                Err(tungstenite::Error::Io(err)) if is_timeout(&err) => Ok(()),
+                Err(tungstenite::Error::ConnectionClosed) => break,
+                Err(err) => return Err(frame_error(&err)),
+            };
+            skip_bad_frames(result)?;
+        }
+        Ok(())
+    }
+
+    /// Stops the listeners and gives back the devices once they've let go of them.
+    pub fn into_devices(self) -> HashMap<String, DeviceHandle> {
+        self.stop.store(true, Ordering::Relaxed);
+        std::mem::take(&mut *self.devices())
+    }
+
+    fn devices(&self) -> std::sync::MutexGuard<'_, HashMap<String, DeviceHandle>> {
+        self.devices.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn is_timeout(err: &std::io::Error) -> bool {
+    matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
+
+/// Bad frames are the sender's problem, only device errors stop a listener.
+fn skip_bad_frames(result: Result<()>) -> Result<()> {
+    match result {
+        Err(HidLightError::Frame(_err)) => {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(err = %_err, "skipped light frame");
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+fn frame_error(err: &dyn std::fmt::Display) -> HidLightError {
+    HidLightError::Frame(err.to_string())
+}
@@ -0,0 +1,100 @@
+use std::collections::VecDeque;
+
+use crate::{DeviceHandle, Report, Result};
+
+/// States written by [`DeviceHandle::flush`], for stepping back and forth.
+#[derive(Debug, Clone)]
+pub(crate) struct History {
+    limit: usize,
+    undo: VecDeque<Vec<Report>>,
+    redo: Vec<Vec<Report>>,
+    /// What the device was last set to, the baseline for the next entry.
+    written: Vec<Report>,
+}
+
+impl History {
+    /// Adds the state just written, forgetting anything undone.
+    pub fn record(&mut self, state: &[Report]) {
+        let previous = std::mem::replace(&mut self.written, state.to_vec());
+        self.undo.push_back(previous);
+        if self.undo.len() > self.limit {
+            self.undo.pop_front();
+        }
+        self.redo.clear();
+    }
+
+    fn undo(&mut self) -> Option<Vec<Report>> {
+        let previous = self.undo.pop_back()?;
+        let current = std::mem::replace(&mut self.written, previous.clone());
+        self.redo.push(current);
+        Some(previous)
+    }
+
+    fn redo(&mut self) -> Option<Vec<Report>> {
+        let next = self.redo.pop()?;
+        let current = std::mem::replace(&mut self.written, next.clone());
+        self.undo.push_back(current);
+        Some(next)
+    }
+}
+
+impl DeviceHandle {
+    /// Keeps the last `limit` states written by [`Self::flush`] for [`Self::undo`],
+    /// starting from the current one. 0 turns history off and forgets it.
+    pub fn set_history_limit(&mut self, limit: usize) -> Result<()> {
+        if limit == 0 {
+            self.history = None;
+            return Ok(());
+        }
+        match &mut self.history {
+            Some(history) => {
+                history.limit = limit;
+                while history.undo.len() > limit {
+                    history.undo.pop_front();
+                }
+            }
+            None => {
+                self.history = Some(History {
+                    limit,
+                    undo: VecDeque::new(),
+                    redo: vec![],
+                    written: self.state()?.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the state before the last flush again. Values set since that flush
+    /// are dropped. Returns false when there's nothing to undo.
+    pub fn undo(&mut self) -> Result<bool> {
+        match self.history.as_mut().and_then(History::undo) {
+            Some(state) => self.write_state(state).map(|()| true),
+            None => Ok(false),
+        }
+    }
+
+    /// Writes the state undone last again. Returns false when there's nothing to redo.
+    pub fn redo(&mut self) -> Result<bool> {
+        match self.history.as_mut().and_then(History::redo) {
+            Some(state) => self.write_state(state).map(|()| true),
+            None => Ok(false),
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history.as_ref().is_some_and(|x| !x.undo.is_empty())
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history.as_ref().is_some_and(|x| !x.redo.is_empty())
+    }
+
+    fn write_state(&mut self, mut state: Vec<Report>) -> Result<()> {
+        for report in &mut state {
+            report.dirty = true;
+        }
+        self.state = Some(state);
+        self.write_dirty()
+    }
+}
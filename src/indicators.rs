@@ -0,0 +1,222 @@
+use crate::{lamp_array::LampArray, DeviceHandle, DeviceOutput, HidLightError, Result};
+
+pub(crate) const LED_PAGE: u16 = 0x08;
+
+const GENERIC_INDICATOR: u16 = 0x4b;
+const PLAYER_INDICATOR: u16 = 0x60;
+const PLAYER_1: u16 = 0x61;
+const PLAYER_8: u16 = 0x68;
+
+/// Well known indicators on the LED usage page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LedKind {
+    NumLock,
+    CapsLock,
+    ScrollLock,
+    Compose,
+    Kana,
+    Power,
+    Shift,
+    DoNotDisturb,
+    Mute,
+    OffHook,
+    Ring,
+    MessageWaiting,
+    /// External Power Connected, the nearest the LED page has to a charging light.
+    ExternalPower,
+    BatteryLow,
+    Speaker,
+    Headset,
+    Hold,
+    Microphone,
+    MicrophoneMute,
+    CameraOn,
+    Busy,
+    Ready,
+    Error,
+    /// Player 1 to 8 indicators.
+    Player(u8),
+}
+
+impl LedKind {
+    pub fn usage_id(self) -> Option<u16> {
+        Some(match self {
+            LedKind::NumLock => 0x01,
+            LedKind::CapsLock => 0x02,
+            LedKind::ScrollLock => 0x03,
+            LedKind::Compose => 0x04,
+            LedKind::Kana => 0x05,
+            LedKind::Power => 0x06,
+            LedKind::Shift => 0x07,
+            LedKind::DoNotDisturb => 0x08,
+            LedKind::Mute => 0x09,
+            LedKind::OffHook => 0x17,
+            LedKind::Ring => 0x18,
+            LedKind::MessageWaiting => 0x19,
+            LedKind::BatteryLow => 0x1d,
+            LedKind::Speaker => 0x1e,
+            LedKind::Headset => 0x1f,
+            LedKind::Hold => 0x20,
+            LedKind::Microphone => 0x21,
+            LedKind::CameraOn => 0x28,
+            LedKind::Busy => 0x2c,
+            LedKind::Ready => 0x2d,
+            LedKind::Error => 0x39,
+            LedKind::ExternalPower => 0x4d,
+            LedKind::MicrophoneMute => 0x57,
+            LedKind::Player(n @ 1..=8) => PLAYER_INDICATOR + n as u16,
+            LedKind::Player(_) => return None,
+        })
+    }
+}
+
+/// The host's lock keys, for mirroring onto other devices with [`DeviceHandle::set_led`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct HostLocks {
+    pub num_lock: bool,
+    pub caps_lock: bool,
+    pub scroll_lock: bool,
+}
+
+impl HostLocks {
+    /// Reads the lock state from the kernel's keyboard LEDs, a lock counting as on
+    /// when any keyboard shows it. Only Linux exposes it without a window to receive
+    /// key events, elsewhere this fails with [`HidLightError::UnsupportedDevice`].
+    pub fn read() -> Result<Self> {
+        #[cfg(target_os = "linux")]
+        {
+            let mut locks = Self::default();
+            for entry in std::fs::read_dir("/sys/class/leds")? {
+                let path = entry?.path();
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                let lock = match name.rsplit_once("::") {
+                    Some((_, "numlock")) => &mut locks.num_lock,
+                    Some((_, "capslock")) => &mut locks.caps_lock,
+                    Some((_, "scrolllock")) => &mut locks.scroll_lock,
+                    _ => continue,
+                };
+                let brightness = std::fs::read_to_string(path.join("brightness"))?;
+                *lock |= brightness.trim() != "0";
+            }
+            Ok(locks)
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            Err(HidLightError::UnsupportedDevice)
+        }
+    }
+
+    /// The indicator for each lock and whether it's on.
+    pub fn leds(&self) -> [(LedKind, bool); 3] {
+        [
+            (LedKind::NumLock, self.num_lock),
+            (LedKind::CapsLock, self.caps_lock),
+            (LedKind::ScrollLock, self.scroll_lock),
+        ]
+    }
+}
+
+impl DeviceHandle {
+    /// Turns a well known indicator on or off, wherever it is in the device's reports.
+    ///
+    /// Like [`Self::set`] followed by [`Self::flush`], so outputs sharing the report
+    /// keep their values.
+    pub fn set_led(&mut self, led: LedKind, on: bool) -> Result<()> {
+        let usage_id = led.usage_id().ok_or(HidLightError::InvalidArgument)?;
+        let report = self
+            .state()?
+            .iter_mut()
+            .find(|x| x.output_by_usage(LED_PAGE, usage_id).is_some())
+            .ok_or(HidLightError::UnsupportedDevice)?;
+        if let Some(output) = report.output_by_usage_mut(LED_PAGE, usage_id) {
+            output.set_usage_active(LED_PAGE, usage_id, on)?;
+        }
+        report.dirty = true;
+        self.flush()
+    }
+
+    /// Shows the player number using whichever indicators the device has.
+    ///
+    /// Devices with a multi-bit Player Indicator get the number written directly,
+    /// discrete Player 1-8 LEDs light only the matching one and, failing both, four
+    /// Generic Indicators are treated as player LEDs. Game controllers with none of
+    /// these but a LampArray get a quarter of its ring lit, see
+    /// [`LampArray::set_player_ring`]. Player 0 turns them all off.
+    ///
+    /// Indicators are set like [`Self::set`] followed by [`Self::flush`], so other
+    /// outputs keep their values.
+    pub fn set_player_indicator(&mut self, player: u8) -> Result<()> {
+        let reports = self.state()?;
+        let player = player as u16;
+
+        let has_player_usages = reports.iter().flat_map(|x| &x.outputs).any(|x| {
+            x.usage_page() == LED_PAGE
+                && (PLAYER_INDICATOR..=PLAYER_8).contains(&x.usage_id())
+                && !(x.usage_id() == PLAYER_INDICATOR && x.is_toggle())
+                || (x.is_selector() && has_player_option(x))
+        });
+
+        let generic_count = reports
+            .iter()
+            .flat_map(|x| &x.outputs)
+            .filter(|x| x.usage_page() == LED_PAGE && x.usage_id() == GENERIC_INDICATOR)
+            .count();
+        let use_generic = !has_player_usages && generic_count == 4;
+
+        if !has_player_usages && !use_generic {
+            return LampArray::new(self.clone())?.set_player_ring(player as u8);
+        }
+
+        let mut generic_index = 0;
+        for report in reports.iter_mut() {
+            let mut changed = false;
+            for out in &mut report.outputs {
+                if out.is_selector() {
+                    if has_player_usages && has_player_option(out) {
+                        // Player 0 or one the device lacks clears the selection
+                        if out
+                            .select_usage(LED_PAGE, PLAYER_INDICATOR + player)
+                            .is_err()
+                        {
+                            out.deselect();
+                        }
+                        changed = true;
+                    }
+                    continue;
+                }
+                if out.usage_page() != LED_PAGE {
+                    continue;
+                }
+                match out.usage_id() {
+                    PLAYER_INDICATOR if has_player_usages && !out.is_toggle() => {
+                        out.set_logical(player as i32);
+                        changed = true;
+                    }
+                    id @ PLAYER_1..=PLAYER_8 if has_player_usages => {
+                        out.real_value = if id - PLAYER_INDICATOR == player {
+                            1.0
+                        } else {
+                            0.0
+                        };
+                        changed = true;
+                    }
+                    GENERIC_INDICATOR if use_generic => {
+                        generic_index += 1;
+                        out.real_value = if generic_index == player { 1.0 } else { 0.0 };
+                        changed = true;
+                    }
+                    _ => {}
+                }
+            }
+
+            report.dirty |= changed;
+        }
+
+        self.flush()
+    }
+}
+
+fn has_player_option(output: &DeviceOutput) -> bool {
+    (PLAYER_1..=PLAYER_8).any(|id| output.has_usage(LED_PAGE, id))
+}
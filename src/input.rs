@@ -0,0 +1,246 @@
+//! Decoding input reports, so lights can follow buttons.
+
+use std::ops::{Range, RangeInclusive};
+
+use crate::{
+    descriptor::{self, MainKind},
+    descriptor_error, field_elements, is_vendor_field_usage, BitOrder, DeviceHandle, Quirks,
+    Result, Usage,
+};
+
+/// A value in the device's input reports.
+#[derive(Debug, Clone)]
+pub struct InputField {
+    report_id: u32,
+    bits: Range<u32>,
+    usage: Usage,
+    logical: RangeInclusive<i32>,
+    /// For one usage of an array field, the value the array holds while it's active.
+    array_index: Option<i32>,
+    /// Slots of the array `bits` covers, any of which can hold the usage.
+    slots: u32,
+    pub name: String,
+}
+
+impl InputField {
+    pub fn report_id(&self) -> u32 {
+        self.report_id
+    }
+
+    pub fn bits(&self) -> &Range<u32> {
+        &self.bits
+    }
+
+    pub fn usage(&self) -> Usage {
+        self.usage
+    }
+
+    pub fn logical_range(&self) -> RangeInclusive<i32> {
+        self.logical.clone()
+    }
+
+    /// Whether this is one of the usages of an array field, which is 1 while any
+    /// slot of the array holds it and 0 otherwise.
+    pub fn is_array_usage(&self) -> bool {
+        self.array_index.is_some()
+    }
+
+    /// The field's value in a report's data, `None` if the data is for another
+    /// report. `data` starts with the report ID when the device numbers its reports.
+    ///
+    /// Fields are read least significant bit first, as the HID spec lays them out,
+    /// unless `quirks` gives an input bit order.
+    pub fn decode(&self, data: &[u8], numbered: bool, quirks: &Quirks) -> Option<i32> {
+        let data = if numbered {
+            let (id, rest) = data.split_first()?;
+            if *id as u32 != self.report_id {
+                return None;
+            }
+            rest
+        } else {
+            data
+        };
+        if (self.bits.end as usize).div_ceil(8) > data.len() {
+            return None;
+        }
+
+        let packing = Quirks {
+            bit_order: quirks.input_bit_order.unwrap_or(BitOrder::LsbFirst),
+            ..*quirks
+        };
+        Some(match self.array_index {
+            Some(index) => {
+                let width = self.bits.len() as u32 / self.slots;
+                (0..self.slots).any(|slot| {
+                    let start = self.bits.start + slot * width;
+                    packing.unpack(data, start..start + width) as i32 == index
+                }) as i32
+            }
+            None => {
+                let raw = packing.unpack(data, self.bits.clone());
+                // Sign extend when the logical range goes negative
+                let width = self.bits.len() as u32;
+                if *self.logical.start() < 0
+                    && (1..32).contains(&width)
+                    && (raw >> (width - 1)) & 1 != 0
+                {
+                    (raw | (!0 << width)) as i32
+                } else {
+                    raw as i32
+                }
+            }
+        })
+    }
+
+    /// Whether the field counts as pressed, i.e. is above the bottom of its range.
+    pub fn is_active(&self, value: i32) -> bool {
+        value > (*self.logical.start()).max(0)
+    }
+}
+
+impl DeviceHandle {
+    /// Every field in the device's input reports, named from the usage tables. Array
+    /// fields, as keyboards use for keys, give one field per usage.
+    pub fn inputs(&self) -> Result<Vec<InputField>> {
+        let bytes = self.descriptor_bytes()?;
+        let ranges = descriptor::usage_ranges(&bytes);
+        let descriptor = hidparser::parse_report_descriptor(&bytes).map_err(descriptor_error)?;
+        let mut inputs = vec![];
+
+        for report in descriptor.input_reports {
+            let report_id: u32 = report.report_id.map(|x| x.into()).unwrap_or_default();
+            let ranges: Vec<_> = ranges
+                .iter()
+                .filter(|x| x.kind == MainKind::Input && x.report_id == report_id)
+                .collect();
+            let elements = field_elements(&report.fields, &ranges);
+            // The last array's usages, logical minimum and slot width, where it ends
+            // and its first field in `inputs`
+            let mut last_array = None;
+
+            for (i, field) in report.fields.into_iter().enumerate() {
+                match field {
+                    hidparser::ReportField::Variable(x) => {
+                        if x.attributes.constant || is_vendor_field_usage(x.usage) {
+                            continue;
+                        }
+                        let element = elements[i];
+                        let usage = Usage {
+                            page: x.usage.page(),
+                            id: element.range_id.unwrap_or(x.usage.id()),
+                        };
+                        let mut name = usage.name().unwrap_or_else(|| "Unk".into());
+                        if element.range_id.is_none() && element.count > 1 {
+                            name = format!("{name} [{}]", element.index);
+                        }
+                        inputs.push(InputField {
+                            report_id,
+                            bits: x.bits,
+                            usage,
+                            logical: x.logical_minimum.into()..=x.logical_maximum.into(),
+                            array_index: None,
+                            slots: 1,
+                            name,
+                        });
+                    }
+                    hidparser::ReportField::Array(x) => {
+                        let minimum: i32 = x.logical_minimum.into();
+                        let ids: Vec<_> = x
+                            .usage_list
+                            .iter()
+                            .flat_map(|x| x.start()..=x.end())
+                            .collect();
+
+                        // Each slot of a report count comes as its own field, the
+                        // usages of the array cover all of them as outputs do
+                        let key = (ids, minimum, x.bits.len());
+                        if let Some((previous, end, start)) = &mut last_array {
+                            if *previous == key && *end == x.bits.start {
+                                *end = x.bits.end;
+                                for input in &mut inputs[*start..] {
+                                    input.bits.end = x.bits.end;
+                                    input.slots += 1;
+                                }
+                                continue;
+                            }
+                        }
+                        last_array = Some((key.clone(), x.bits.end, inputs.len()));
+
+                        let usages = key
+                            .0
+                            .into_iter()
+                            .map(hidparser::report_data_types::Usage::from);
+                        for (index, usage) in usages.enumerate() {
+                            if is_vendor_field_usage(usage) {
+                                continue;
+                            }
+                            let usage = Usage {
+                                page: usage.page(),
+                                id: usage.id(),
+                            };
+                            inputs.push(InputField {
+                                report_id,
+                                bits: x.bits.clone(),
+                                usage,
+                                logical: 0..=1,
+                                array_index: Some(minimum + index as i32),
+                                slots: 1,
+                                name: usage.name().unwrap_or_else(|| "Unk".into()),
+                            });
+                        }
+                    }
+                    hidparser::ReportField::Padding(_) => {}
+                }
+            }
+        }
+
+        Ok(inputs)
+    }
+
+    /// Waits up to `timeout_ms` for an input report, -1 waiting forever. Returns
+    /// `None` on timeout, otherwise the report, starting with its ID if the device
+    /// numbers its reports.
+    pub fn read_input(&self, timeout_ms: i32) -> Result<Option<Vec<u8>>> {
+        let mut buffer = vec![0; 1024];
+        let len = self.read_raw(&mut buffer, timeout_ms)?;
+        if len == 0 {
+            return Ok(None);
+        }
+        buffer.truncate(len);
+        Ok(Some(buffer))
+    }
+
+    /// Whether input reports start with a report ID.
+    pub fn numbered_inputs(&self) -> Result<bool> {
+        Ok(self
+            .report_descriptor()?
+            .input_reports
+            .iter()
+            .any(|x| x.report_id.is_some()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn array_usage_is_found_in_any_slot() {
+        let key = InputField {
+            report_id: 0,
+            bits: 0..16,
+            usage: Usage {
+                page: 0x07,
+                id: 0x04,
+            },
+            logical: 0..=1,
+            array_index: Some(4),
+            slots: 2,
+            name: "Keyboard A".into(),
+        };
+        let quirks = Quirks::default();
+        assert_eq!(key.decode(&[0x04, 0x00], false, &quirks), Some(1));
+        assert_eq!(key.decode(&[0x05, 0x04], false, &quirks), Some(1));
+        assert_eq!(key.decode(&[0x05, 0x06], false, &quirks), Some(0));
+    }
+}
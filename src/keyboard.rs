@@ -0,0 +1,322 @@
+//! Finding the lamp under a key on per-key RGB keyboards.
+
+use std::collections::HashMap;
+
+use crate::{
+    color::Rgb,
+    lamp_array::{LampArray, LampAttributes},
+    HidLightError, Result,
+};
+
+/// A key by its usage on the HID Keyboard/Keypad page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Key(pub u16);
+
+impl Key {
+    pub const A: Key = Key(0x04);
+    pub const B: Key = Key(0x05);
+    pub const C: Key = Key(0x06);
+    pub const D: Key = Key(0x07);
+    pub const E: Key = Key(0x08);
+    pub const F: Key = Key(0x09);
+    pub const G: Key = Key(0x0a);
+    pub const H: Key = Key(0x0b);
+    pub const I: Key = Key(0x0c);
+    pub const J: Key = Key(0x0d);
+    pub const K: Key = Key(0x0e);
+    pub const L: Key = Key(0x0f);
+    pub const M: Key = Key(0x10);
+    pub const N: Key = Key(0x11);
+    pub const O: Key = Key(0x12);
+    pub const P: Key = Key(0x13);
+    pub const Q: Key = Key(0x14);
+    pub const R: Key = Key(0x15);
+    pub const S: Key = Key(0x16);
+    pub const T: Key = Key(0x17);
+    pub const U: Key = Key(0x18);
+    pub const V: Key = Key(0x19);
+    pub const W: Key = Key(0x1a);
+    pub const X: Key = Key(0x1b);
+    pub const Y: Key = Key(0x1c);
+    pub const Z: Key = Key(0x1d);
+    pub const DIGIT_1: Key = Key(0x1e);
+    pub const DIGIT_2: Key = Key(0x1f);
+    pub const DIGIT_3: Key = Key(0x20);
+    pub const DIGIT_4: Key = Key(0x21);
+    pub const DIGIT_5: Key = Key(0x22);
+    pub const DIGIT_6: Key = Key(0x23);
+    pub const DIGIT_7: Key = Key(0x24);
+    pub const DIGIT_8: Key = Key(0x25);
+    pub const DIGIT_9: Key = Key(0x26);
+    pub const DIGIT_0: Key = Key(0x27);
+    pub const ENTER: Key = Key(0x28);
+    pub const ESCAPE: Key = Key(0x29);
+    pub const BACKSPACE: Key = Key(0x2a);
+    pub const TAB: Key = Key(0x2b);
+    pub const SPACE: Key = Key(0x2c);
+    pub const MINUS: Key = Key(0x2d);
+    pub const EQUAL: Key = Key(0x2e);
+    pub const LEFT_BRACKET: Key = Key(0x2f);
+    pub const RIGHT_BRACKET: Key = Key(0x30);
+    pub const BACKSLASH: Key = Key(0x31);
+    pub const NON_US_HASH: Key = Key(0x32);
+    pub const SEMICOLON: Key = Key(0x33);
+    pub const APOSTROPHE: Key = Key(0x34);
+    pub const GRAVE: Key = Key(0x35);
+    pub const COMMA: Key = Key(0x36);
+    pub const PERIOD: Key = Key(0x37);
+    pub const SLASH: Key = Key(0x38);
+    pub const CAPS_LOCK: Key = Key(0x39);
+    pub const F1: Key = Key(0x3a);
+    pub const F2: Key = Key(0x3b);
+    pub const F3: Key = Key(0x3c);
+    pub const F4: Key = Key(0x3d);
+    pub const F5: Key = Key(0x3e);
+    pub const F6: Key = Key(0x3f);
+    pub const F7: Key = Key(0x40);
+    pub const F8: Key = Key(0x41);
+    pub const F9: Key = Key(0x42);
+    pub const F10: Key = Key(0x43);
+    pub const F11: Key = Key(0x44);
+    pub const F12: Key = Key(0x45);
+    pub const PRINT_SCREEN: Key = Key(0x46);
+    pub const SCROLL_LOCK: Key = Key(0x47);
+    pub const PAUSE: Key = Key(0x48);
+    pub const INSERT: Key = Key(0x49);
+    pub const HOME: Key = Key(0x4a);
+    pub const PAGE_UP: Key = Key(0x4b);
+    pub const DELETE: Key = Key(0x4c);
+    pub const END: Key = Key(0x4d);
+    pub const PAGE_DOWN: Key = Key(0x4e);
+    pub const RIGHT: Key = Key(0x4f);
+    pub const LEFT: Key = Key(0x50);
+    pub const DOWN: Key = Key(0x51);
+    pub const UP: Key = Key(0x52);
+    pub const NUM_LOCK: Key = Key(0x53);
+    pub const KEYPAD_DIVIDE: Key = Key(0x54);
+    pub const KEYPAD_MULTIPLY: Key = Key(0x55);
+    pub const KEYPAD_MINUS: Key = Key(0x56);
+    pub const KEYPAD_PLUS: Key = Key(0x57);
+    pub const KEYPAD_ENTER: Key = Key(0x58);
+    pub const KEYPAD_1: Key = Key(0x59);
+    pub const KEYPAD_2: Key = Key(0x5a);
+    pub const KEYPAD_3: Key = Key(0x5b);
+    pub const KEYPAD_4: Key = Key(0x5c);
+    pub const KEYPAD_5: Key = Key(0x5d);
+    pub const KEYPAD_6: Key = Key(0x5e);
+    pub const KEYPAD_7: Key = Key(0x5f);
+    pub const KEYPAD_8: Key = Key(0x60);
+    pub const KEYPAD_9: Key = Key(0x61);
+    pub const KEYPAD_0: Key = Key(0x62);
+    pub const KEYPAD_PERIOD: Key = Key(0x63);
+    pub const NON_US_BACKSLASH: Key = Key(0x64);
+    pub const APPLICATION: Key = Key(0x65);
+    pub const LEFT_CONTROL: Key = Key(0xe0);
+    pub const LEFT_SHIFT: Key = Key(0xe1);
+    pub const LEFT_ALT: Key = Key(0xe2);
+    pub const LEFT_GUI: Key = Key(0xe3);
+    pub const RIGHT_CONTROL: Key = Key(0xe4);
+    pub const RIGHT_SHIFT: Key = Key(0xe5);
+    pub const RIGHT_ALT: Key = Key(0xe6);
+    pub const RIGHT_GUI: Key = Key(0xe7);
+
+    pub fn usage_id(self) -> u16 {
+        self.0
+    }
+
+    /// A PC set 1 scancode, extended keys with their 0xe0 prefix in the high byte,
+    /// as Windows reports them.
+    pub fn from_scancode(scancode: u16) -> Option<Key> {
+        SCANCODES.iter().find(|x| x.0 == scancode).map(|x| Key(x.1))
+    }
+
+    pub fn scancode(self) -> Option<u16> {
+        SCANCODES.iter().find(|x| x.1 == self.0).map(|x| x.0)
+    }
+
+    pub fn name(self) -> Option<String> {
+        hut::Usage::new_from_page_and_id(0x07, self.0)
+            .ok()
+            .map(|x| x.name())
+    }
+}
+
+/// Set 1 scancodes and the keyboard usage they map to.
+const SCANCODES: &[(u16, u16)] = &[
+    (0x01, 0x29),
+    (0x02, 0x1e),
+    (0x03, 0x1f),
+    (0x04, 0x20),
+    (0x05, 0x21),
+    (0x06, 0x22),
+    (0x07, 0x23),
+    (0x08, 0x24),
+    (0x09, 0x25),
+    (0x0a, 0x26),
+    (0x0b, 0x27),
+    (0x0c, 0x2d),
+    (0x0d, 0x2e),
+    (0x0e, 0x2a),
+    (0x0f, 0x2b),
+    (0x10, 0x14),
+    (0x11, 0x1a),
+    (0x12, 0x08),
+    (0x13, 0x15),
+    (0x14, 0x17),
+    (0x15, 0x1c),
+    (0x16, 0x18),
+    (0x17, 0x0c),
+    (0x18, 0x12),
+    (0x19, 0x13),
+    (0x1a, 0x2f),
+    (0x1b, 0x30),
+    (0x1c, 0x28),
+    (0x1d, 0xe0),
+    (0x1e, 0x04),
+    (0x1f, 0x16),
+    (0x20, 0x07),
+    (0x21, 0x09),
+    (0x22, 0x0a),
+    (0x23, 0x0b),
+    (0x24, 0x0d),
+    (0x25, 0x0e),
+    (0x26, 0x0f),
+    (0x27, 0x33),
+    (0x28, 0x34),
+    (0x29, 0x35),
+    (0x2a, 0xe1),
+    (0x2b, 0x31),
+    (0x2c, 0x1d),
+    (0x2d, 0x1b),
+    (0x2e, 0x06),
+    (0x2f, 0x19),
+    (0x30, 0x05),
+    (0x31, 0x11),
+    (0x32, 0x10),
+    (0x33, 0x36),
+    (0x34, 0x37),
+    (0x35, 0x38),
+    (0x36, 0xe5),
+    (0x37, 0x55),
+    (0x38, 0xe2),
+    (0x39, 0x2c),
+    (0x3a, 0x39),
+    (0x3b, 0x3a),
+    (0x3c, 0x3b),
+    (0x3d, 0x3c),
+    (0x3e, 0x3d),
+    (0x3f, 0x3e),
+    (0x40, 0x3f),
+    (0x41, 0x40),
+    (0x42, 0x41),
+    (0x43, 0x42),
+    (0x44, 0x43),
+    (0x45, 0x53),
+    (0x46, 0x47),
+    (0x47, 0x5f),
+    (0x48, 0x60),
+    (0x49, 0x61),
+    (0x4a, 0x56),
+    (0x4b, 0x5c),
+    (0x4c, 0x5d),
+    (0x4d, 0x5e),
+    (0x4e, 0x57),
+    (0x4f, 0x59),
+    (0x50, 0x5a),
+    (0x51, 0x5b),
+    (0x52, 0x62),
+    (0x53, 0x63),
+    (0x56, 0x64),
+    (0x57, 0x44),
+    (0x58, 0x45),
+    (0xe01c, 0x58),
+    (0xe01d, 0xe4),
+    (0xe035, 0x54),
+    (0xe037, 0x46),
+    (0xe038, 0xe6),
+    (0xe047, 0x4a),
+    (0xe048, 0x52),
+    (0xe049, 0x4b),
+    (0xe04b, 0x50),
+    (0xe04d, 0x4f),
+    (0xe04f, 0x4d),
+    (0xe050, 0x51),
+    (0xe051, 0x4e),
+    (0xe052, 0x49),
+    (0xe053, 0x4c),
+    (0xe05b, 0xe3),
+    (0xe05c, 0xe7),
+    (0xe05d, 0x65),
+];
+
+/// Which lamps sit under which keys. A key can have several lamps, e.g. a wide
+/// space bar.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyMap {
+    lamps: HashMap<Key, Vec<u16>>,
+}
+
+impl KeyMap {
+    /// From the input bindings the lamps report.
+    pub fn from_lamps(lamps: &[LampAttributes]) -> Self {
+        Self::from_pairs(
+            lamps
+                .iter()
+                .filter_map(|x| x.input_binding.map(|key| (key, x.id))),
+        )
+    }
+
+    /// From `(keyboard usage, lamp ID)` pairs, as in [`crate::Quirks::key_map`].
+    pub fn from_pairs(pairs: impl IntoIterator<Item = (u16, u16)>) -> Self {
+        let mut lamps: HashMap<Key, Vec<u16>> = HashMap::new();
+        for (key, lamp) in pairs {
+            lamps.entry(Key(key)).or_default().push(lamp);
+        }
+        Self { lamps }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lamps.is_empty()
+    }
+
+    pub fn lamps(&self, key: Key) -> &[u16] {
+        self.lamps.get(&key).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn key(&self, lamp: u16) -> Option<Key> {
+        self.lamps
+            .iter()
+            .find(|x| x.1.contains(&lamp))
+            .map(|x| *x.0)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = Key> + '_ {
+        self.lamps.keys().copied()
+    }
+}
+
+impl LampArray {
+    /// The device's key map, from the quirks table or else from its lamps' input
+    /// bindings.
+    pub fn key_map(&self) -> &KeyMap {
+        &self.keys
+    }
+
+    /// Sets every lamp under `key` right away.
+    pub fn set_key_color(&self, key: Key, color: Rgb) -> Result<()> {
+        self.set_keys(&[(key, color)])
+    }
+
+    /// Sets the lamps under several keys in one update.
+    pub fn set_keys(&self, colors: &[(Key, Rgb)]) -> Result<()> {
+        let mut lamps = vec![];
+        for (key, color) in colors {
+            let ids = self.keys.lamps(*key);
+            if ids.is_empty() {
+                return Err(HidLightError::UnknownKey(*key));
+            }
+            lamps.extend(ids.iter().map(|x| (*x, *color)));
+        }
+        self.set_lamps(&lamps)
+    }
+}
@@ -0,0 +1,463 @@
+//! HID LampArrays, the per-lamp RGB interface on the Lighting and Illumination page
+//! used by keyboards, mice and light strips that work with Windows Dynamic Lighting.
+//!
+//! Everything goes through feature reports: the array's attributes, one request and
+//! response per lamp for its attributes, then batched updates of lamp colors.
+
+use std::{f64::consts::TAU, ops::Range};
+
+use hidparser::ReportField;
+
+use crate::{
+    color::Rgb,
+    keyboard::KeyMap,
+    quirks::{BitOrder, Quirks},
+    DeviceHandle, HidLightError, Result,
+};
+
+pub(crate) const LIGHTING_PAGE: u16 = 0x59;
+
+const LAMP_COUNT: u16 = 0x03;
+const BOUNDING_BOX_WIDTH: u16 = 0x04;
+const BOUNDING_BOX_HEIGHT: u16 = 0x05;
+const BOUNDING_BOX_DEPTH: u16 = 0x06;
+const LAMP_ARRAY_KIND: u16 = 0x07;
+const MIN_UPDATE_INTERVAL: u16 = 0x08;
+const LAMP_ID: u16 = 0x21;
+const POSITION_X: u16 = 0x23;
+const POSITION_Y: u16 = 0x24;
+const POSITION_Z: u16 = 0x25;
+const LAMP_PURPOSES: u16 = 0x26;
+const UPDATE_LATENCY: u16 = 0x27;
+const RED_LEVEL_COUNT: u16 = 0x28;
+const GREEN_LEVEL_COUNT: u16 = 0x29;
+const BLUE_LEVEL_COUNT: u16 = 0x2a;
+const INTENSITY_LEVEL_COUNT: u16 = 0x2b;
+const IS_PROGRAMMABLE: u16 = 0x2c;
+const INPUT_BINDING: u16 = 0x2d;
+const RED_CHANNEL: u16 = 0x51;
+const GREEN_CHANNEL: u16 = 0x52;
+const BLUE_CHANNEL: u16 = 0x53;
+const INTENSITY_CHANNEL: u16 = 0x54;
+const UPDATE_FLAGS: u16 = 0x55;
+const LAMP_ID_START: u16 = 0x61;
+const LAMP_ID_END: u16 = 0x62;
+const AUTONOMOUS_MODE: u16 = 0x71;
+
+/// Set on the last update of a frame, the device shows nothing until it sees it.
+const UPDATE_COMPLETE: u32 = 0x01;
+
+/// LampArray reports are always laid out the way the HID spec says, whatever quirks
+/// the device's output reports need.
+const PACKING: Quirks = Quirks {
+    bit_order: BitOrder::LsbFirst,
+    input_bit_order: None,
+    swap_bytes: false,
+    max_write_size: None,
+    max_report_rate: None,
+    key_map: None,
+    write_path: None,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LampArrayKind {
+    Keyboard,
+    Mouse,
+    GameController,
+    Peripheral,
+    Scene,
+    Notification,
+    Chassis,
+    Wearable,
+    Furniture,
+    Art,
+    Other(u32),
+}
+
+impl From<u32> for LampArrayKind {
+    fn from(x: u32) -> Self {
+        match x {
+            1 => LampArrayKind::Keyboard,
+            2 => LampArrayKind::Mouse,
+            3 => LampArrayKind::GameController,
+            4 => LampArrayKind::Peripheral,
+            5 => LampArrayKind::Scene,
+            6 => LampArrayKind::Notification,
+            7 => LampArrayKind::Chassis,
+            8 => LampArrayKind::Wearable,
+            9 => LampArrayKind::Furniture,
+            10 => LampArrayKind::Art,
+            x => LampArrayKind::Other(x),
+        }
+    }
+}
+
+/// A point or extent in micrometers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LampArrayAttributes {
+    pub lamp_count: u16,
+    pub bounding_box: Position,
+    pub kind: LampArrayKind,
+    pub min_update_interval_us: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LampAttributes {
+    pub id: u16,
+    pub position: Position,
+    /// Bit flags: control, accent, branding, status, illumination, presentation.
+    pub purposes: u32,
+    pub update_latency_us: u32,
+    pub red_levels: u8,
+    pub green_levels: u8,
+    pub blue_levels: u8,
+    pub intensity_levels: u8,
+    /// Lamps that aren't programmable only take intensity.
+    pub is_programmable: bool,
+    /// Keyboard usage of the key the lamp sits under, if any.
+    pub input_binding: Option<u16>,
+}
+
+impl LampAttributes {
+    /// Scales an 8 bit channel to the lamp's number of levels.
+    fn scale(value: u8, levels: u8) -> u32 {
+        match levels {
+            0 => value as u32,
+            levels => (value as u32 * (levels as u32 - 1) + 127) / 255,
+        }
+    }
+
+    fn channels(&self, color: Rgb) -> [u32; 4] {
+        let intensity = color.r.max(color.g).max(color.b);
+        [
+            Self::scale(color.r, self.red_levels),
+            Self::scale(color.g, self.green_levels),
+            Self::scale(color.b, self.blue_levels),
+            Self::scale(intensity, self.intensity_levels),
+        ]
+    }
+}
+
+/// A feature report and where each Lighting and Illumination usage sits in it.
+#[derive(Debug, Clone)]
+struct FeatureReport {
+    id: u8,
+    len: usize,
+    fields: Vec<(u16, Range<u32>)>,
+}
+
+impl FeatureReport {
+    fn has(&self, usage: u16) -> bool {
+        self.fields.iter().any(|x| x.0 == usage)
+    }
+
+    fn bits(&self, usage: u16) -> impl Iterator<Item = Range<u32>> + '_ {
+        self.fields
+            .iter()
+            .filter(move |x| x.0 == usage)
+            .map(|x| x.1.clone())
+    }
+
+    fn buffer(&self) -> Vec<u8> {
+        let mut buffer = vec![0; self.len + 1];
+        buffer[0] = self.id;
+        buffer
+    }
+
+    fn set(&self, buffer: &mut [u8], usage: u16, value: u32) {
+        if let Some(bits) = self.bits(usage).next() {
+            PACKING.pack(&mut buffer[1..], bits, value);
+        }
+    }
+
+    fn get(&self, buffer: &[u8], usage: u16) -> u32 {
+        self.bits(usage)
+            .next()
+            .map(|bits| PACKING.unpack(&buffer[1..], bits))
+            .unwrap_or_default()
+    }
+}
+
+/// The LampArray feature reports of a device.
+#[derive(Debug, Clone)]
+struct Layout {
+    attributes: FeatureReport,
+    attributes_request: FeatureReport,
+    attributes_response: FeatureReport,
+    multi_update: FeatureReport,
+    range_update: Option<FeatureReport>,
+    control: Option<FeatureReport>,
+}
+
+impl Layout {
+    fn parse(descriptor: &hidparser::ReportDescriptor) -> Option<Self> {
+        let reports: Vec<FeatureReport> = descriptor
+            .features
+            .iter()
+            .map(|report| FeatureReport {
+                id: report.report_id.map(u32::from).unwrap_or_default() as u8,
+                len: report.size_in_bits.div_ceil(8),
+                fields: report
+                    .fields
+                    .iter()
+                    .filter_map(|field| match field {
+                        ReportField::Variable(x) if x.usage.page() == LIGHTING_PAGE => {
+                            Some((x.usage.id(), x.bits.clone()))
+                        }
+                        _ => None,
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let find = |usage: u16, without: &[u16]| {
+            reports
+                .iter()
+                .find(|x| x.has(usage) && without.iter().all(|u| !x.has(*u)))
+                .cloned()
+        };
+
+        Some(Self {
+            attributes: find(BOUNDING_BOX_WIDTH, &[])?,
+            attributes_request: find(LAMP_ID, &[POSITION_X, RED_CHANNEL, LAMP_COUNT])?,
+            attributes_response: find(POSITION_X, &[])?,
+            multi_update: find(RED_CHANNEL, &[LAMP_ID_START])?,
+            range_update: find(LAMP_ID_START, &[]),
+            control: find(AUTONOMOUS_MODE, &[]),
+        })
+    }
+
+    /// Lamps one multi update report can carry.
+    fn batch_size(&self) -> usize {
+        self.multi_update.bits(LAMP_ID).count()
+    }
+}
+
+/// A device implementing the HID LampArray interface.
+pub struct LampArray {
+    handle: DeviceHandle,
+    layout: Layout,
+    attributes: LampArrayAttributes,
+    lamps: Vec<LampAttributes>,
+    /// Colors set through [`crate::surface::LightSurface`] and whether they've changed
+    /// since the last present.
+    pub(crate) frame: Vec<(Rgb, bool)>,
+    pub(crate) keys: KeyMap,
+}
+
+impl LampArray {
+    /// Reads the array's attributes and those of every lamp. Fails with
+    /// [`HidLightError::UnsupportedDevice`] if the device has no LampArray.
+    pub fn new(handle: DeviceHandle) -> Result<Self> {
+        let layout = Layout::parse(&handle.report_descriptor()?)
+            .filter(|x| x.batch_size() > 0)
+            .ok_or(HidLightError::UnsupportedDevice)?;
+
+        let report = &layout.attributes;
+        let mut buffer = report.buffer();
+        handle.get_feature_raw(&mut buffer)?;
+        let attributes = LampArrayAttributes {
+            lamp_count: report.get(&buffer, LAMP_COUNT) as u16,
+            bounding_box: Position {
+                x: report.get(&buffer, BOUNDING_BOX_WIDTH),
+                y: report.get(&buffer, BOUNDING_BOX_HEIGHT),
+                z: report.get(&buffer, BOUNDING_BOX_DEPTH),
+            },
+            kind: report.get(&buffer, LAMP_ARRAY_KIND).into(),
+            min_update_interval_us: report.get(&buffer, MIN_UPDATE_INTERVAL),
+        };
+
+        let mut array = Self {
+            handle,
+            layout,
+            attributes,
+            lamps: vec![],
+            frame: vec![(Rgb::BLACK, true); attributes.lamp_count as usize],
+            keys: KeyMap::default(),
+        };
+        array.lamps = (0..attributes.lamp_count)
+            .map(|id| array.read_lamp(id))
+            .collect::<Result<_>>()?;
+        array.keys = match array.handle.quirks().key_map {
+            Some(pairs) => KeyMap::from_pairs(pairs.iter().copied()),
+            None => KeyMap::from_lamps(&array.lamps),
+        };
+        Ok(array)
+    }
+
+    fn read_lamp(&self, id: u16) -> Result<LampAttributes> {
+        let request = &self.layout.attributes_request;
+        let mut buffer = request.buffer();
+        request.set(&mut buffer, LAMP_ID, id as u32);
+        self.handle.send_feature_raw(&buffer)?;
+
+        let report = &self.layout.attributes_response;
+        let mut buffer = report.buffer();
+        self.handle.get_feature_raw(&mut buffer)?;
+        if report.get(&buffer, LAMP_ID) != id as u32 {
+            return Err(HidLightError::NoResponse);
+        }
+
+        Ok(LampAttributes {
+            id,
+            position: Position {
+                x: report.get(&buffer, POSITION_X),
+                y: report.get(&buffer, POSITION_Y),
+                z: report.get(&buffer, POSITION_Z),
+            },
+            purposes: report.get(&buffer, LAMP_PURPOSES),
+            update_latency_us: report.get(&buffer, UPDATE_LATENCY),
+            red_levels: report.get(&buffer, RED_LEVEL_COUNT) as u8,
+            green_levels: report.get(&buffer, GREEN_LEVEL_COUNT) as u8,
+            blue_levels: report.get(&buffer, BLUE_LEVEL_COUNT) as u8,
+            intensity_levels: report.get(&buffer, INTENSITY_LEVEL_COUNT) as u8,
+            is_programmable: report.get(&buffer, IS_PROGRAMMABLE) != 0,
+            input_binding: Some(report.get(&buffer, INPUT_BINDING) as u16).filter(|x| *x != 0),
+        })
+    }
+
+    pub fn attributes(&self) -> &LampArrayAttributes {
+        &self.attributes
+    }
+
+    pub fn lamps(&self) -> &[LampAttributes] {
+        &self.lamps
+    }
+
+    /// With autonomous mode on, the device runs its own effects and ignores updates.
+    /// It has to be turned off before colors can be set.
+    pub fn set_autonomous_mode(&self, on: bool) -> Result<()> {
+        let Some(report) = &self.layout.control else {
+            return Err(HidLightError::UnsupportedDevice);
+        };
+        let mut buffer = report.buffer();
+        report.set(&mut buffer, AUTONOMOUS_MODE, on as u32);
+        self.handle.send_feature_raw(&buffer)
+    }
+
+    /// Sets the given lamps, as many per report as the device takes. The device shows
+    /// the new colors once the last report is in.
+    pub fn set_lamps(&self, colors: &[(u16, Rgb)]) -> Result<()> {
+        let report = &self.layout.multi_update;
+        let ids: Vec<_> = report.bits(LAMP_ID).collect();
+        let channels: Vec<Vec<_>> = [RED_CHANNEL, GREEN_CHANNEL, BLUE_CHANNEL, INTENSITY_CHANNEL]
+            .iter()
+            .map(|x| report.bits(*x).collect())
+            .collect();
+
+        // The update only completes with the last batch, keep others from cutting in
+        let _lock = self.handle.lock_writes();
+        let batches = colors.chunks(ids.len().max(1));
+        let count = batches.len();
+        for (i, batch) in batches.enumerate() {
+            let mut buffer = report.buffer();
+            report.set(&mut buffer, LAMP_COUNT, batch.len() as u32);
+            if i + 1 == count {
+                report.set(&mut buffer, UPDATE_FLAGS, UPDATE_COMPLETE);
+            }
+            for (slot, (id, color)) in batch.iter().enumerate() {
+                let lamp = self
+                    .lamps
+                    .get(*id as usize)
+                    .ok_or(HidLightError::InvalidArgument)?;
+                PACKING.pack(&mut buffer[1..], ids[slot].clone(), *id as u32);
+                for (bits, value) in channels.iter().zip(lamp.channels(*color)) {
+                    if let Some(bits) = bits.get(slot) {
+                        PACKING.pack(&mut buffer[1..], bits.clone(), value);
+                    }
+                }
+            }
+            self.handle.send_feature_raw(&buffer)?;
+        }
+        Ok(())
+    }
+
+    /// Sets every lamp from `start` to `end` inclusive to one color, in a single
+    /// report where the device supports it.
+    pub fn set_range(&self, start: u16, end: u16, color: Rgb) -> Result<()> {
+        if start > end || end >= self.attributes.lamp_count {
+            return Err(HidLightError::InvalidArgument);
+        }
+
+        let Some(report) = &self.layout.range_update else {
+            let colors: Vec<_> = (start..=end).map(|x| (x, color)).collect();
+            return self.set_lamps(&colors);
+        };
+
+        // The range report only has one set of channels, scaled for the first lamp
+        let channels = self.lamps[start as usize].channels(color);
+        let mut buffer = report.buffer();
+        report.set(&mut buffer, UPDATE_FLAGS, UPDATE_COMPLETE);
+        report.set(&mut buffer, LAMP_ID_START, start as u32);
+        report.set(&mut buffer, LAMP_ID_END, end as u32);
+        for (usage, value) in [RED_CHANNEL, GREEN_CHANNEL, BLUE_CHANNEL, INTENSITY_CHANNEL]
+            .into_iter()
+            .zip(channels)
+        {
+            report.set(&mut buffer, usage, value);
+        }
+        self.handle.send_feature_raw(&buffer)
+    }
+
+    pub fn fill(&self, color: Rgb) -> Result<()> {
+        match self.attributes.lamp_count {
+            0 => Ok(()),
+            count => self.set_range(0, count - 1, color),
+        }
+    }
+
+    /// Lights one quarter of a game controller's ring of lamps per player, 1 to 4,
+    /// clockwise from the top. Player 0 turns the ring off.
+    pub fn set_player_ring(&self, player: u8) -> Result<()> {
+        if self.attributes.kind != LampArrayKind::GameController || self.lamps.is_empty() {
+            return Err(HidLightError::UnsupportedDevice);
+        }
+        if player > 4 {
+            return Err(HidLightError::InvalidArgument);
+        }
+        match self.set_autonomous_mode(false) {
+            Ok(()) | Err(HidLightError::UnsupportedDevice) => {}
+            Err(err) => return Err(err),
+        }
+
+        let center_x = self.attributes.bounding_box.x as f64 / 2.0;
+        let center_y = self.attributes.bounding_box.y as f64 / 2.0;
+        let mut ring: Vec<_> = self
+            .lamps
+            .iter()
+            .map(|lamp| {
+                // Y grows downwards, so this is the clockwise angle from the top
+                let angle =
+                    (lamp.position.x as f64 - center_x).atan2(center_y - lamp.position.y as f64);
+                (angle.rem_euclid(TAU), lamp.id)
+            })
+            .collect();
+        ring.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let count = ring.len();
+        let colors: Vec<_> = ring
+            .iter()
+            .enumerate()
+            .map(|(i, (_, id))| {
+                let on = player > 0 && i * 4 / count == player as usize - 1;
+                (*id, if on { Rgb::WHITE } else { Rgb::BLACK })
+            })
+            .collect();
+        self.set_lamps(&colors)
+    }
+
+    pub fn handle(&self) -> &DeviceHandle {
+        &self.handle
+    }
+
+    pub fn into_inner(self) -> DeviceHandle {
+        self.handle
+    }
+}
@@ -1,10 +1,13 @@
 use std::{
+    collections::HashMap,
     ffi::{CStr, CString},
     ops::{Range, RangeInclusive},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError, RwLock,
+    },
 };
 
-use bitvec::{order::Msb0, view::BitView};
 use extfn::extfn;
 use hidapi::{HidApi, HidDevice};
 use hidparser::report_data_types::StringIndex;
@@ -13,32 +16,170 @@ use thiserror::Error;
 pub use hidapi;
 pub use hut;
 
+#[cfg(feature = "ambient")]
+pub mod ambient;
+#[cfg(feature = "audio")]
+pub mod audio;
+pub mod bindings;
+mod cache;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod color;
+pub mod descriptor;
+mod device_id;
+#[cfg(feature = "dmx")]
+pub mod dmx;
+mod dmx_map;
+pub mod drivers;
+pub mod effects;
+mod filter;
+#[cfg(feature = "frames")]
+pub mod frames;
+mod history;
+mod indicators;
+pub mod input;
+pub mod keyboard;
+pub mod lamp_array;
+pub mod metrics;
+#[cfg(feature = "midi")]
+pub mod midi;
+mod observers;
+mod open_options;
+#[cfg(feature = "openrgb")]
+pub mod openrgb;
+#[cfg(feature = "osc")]
+pub mod osc;
+mod output_key;
+pub mod patterns;
+mod physical;
+pub mod plugin;
+mod probe;
+pub mod profiles;
+pub mod quirks;
+pub mod recording;
+pub mod scene;
+#[cfg(feature = "rhai")]
+pub mod script;
+mod shared;
+mod supervisor;
+pub mod surface;
+pub mod sync;
+pub mod telephony;
+mod ticker;
+pub mod timeline;
+mod transition;
+mod units;
+mod value;
+mod write;
+
+pub use device_id::{DeviceId, DeviceInstance};
+pub use dmx_map::{DmxChannel, DmxMap, ResolvedDmxMap};
+pub use filter::DeviceFilter;
+pub use indicators::{HostLocks, LedKind};
+pub use observers::ObserverId;
+pub use open_options::{OpenFuture, OpenOptions};
+pub use output_key::OutputKey;
+pub use physical::PhysicalDevice;
+pub use probe::Capabilities;
+pub use quirks::{BitOrder, Quirks};
+pub use shared::SharedReport;
+pub use supervisor::Supervisor;
+pub use ticker::Ticker;
+pub use transition::{DeviceState, Easing, Transition};
+pub use units::{PhysicalRange, Unit, UnitSystem};
+pub use value::Value;
+pub use write::{RetryPolicy, WriteGuard, WritePath};
+
 type Result<T> = std::result::Result<T, HidLightError>;
 
+const MAX_DESCRIPTOR_SIZE: usize = 64 * 1024;
+
 #[derive(Debug, Error)]
 pub enum HidLightError {
     #[error("HIDAPI Failure")]
     HidApi(#[from] hidapi::HidError),
-    #[error("Descriptor parse error")]
-    DescriptorError,
+    #[error("Descriptor parse error: {detail}")]
+    DescriptorError { detail: String },
+    #[error("Report descriptor is larger than {0} bytes")]
+    DescriptorTruncated(usize),
+    #[error("Device not supported by this driver")]
+    UnsupportedDevice,
+    #[error("Invalid argument")]
+    InvalidArgument,
+    #[error("No response from device")]
+    NoResponse,
+    #[error("No output at bit {} of report {}", .0.bit_offset, .0.report_id)]
+    UnknownOutput(OutputId),
+    #[error("No output matches {0}")]
+    UnknownOutputKey(OutputKey),
+    #[error("Value {value} is outside {range:?} for the output at bit {} of report {}", output.bit_offset, output.report_id)]
+    OutOfRange {
+        output: OutputId,
+        value: i32,
+        range: RangeInclusive<i32>,
+    },
+    #[error("The output at bit {} of report {} has no usage {page:#04x}:{id:#04x}", output.bit_offset, output.report_id)]
+    UnknownUsage {
+        output: OutputId,
+        page: u16,
+        id: u16,
+    },
+    #[error("Invalid output key")]
+    InvalidOutputKey,
+    #[error("Device not found")]
+    DeviceNotFound,
+    #[error("Devices can't be re-enumerated through a shared HidApi")]
+    SharedApi,
+    #[error("Timed out")]
+    Timeout,
+    #[error("IO error")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid hex dump")]
+    InvalidHex,
+    #[error("Permission denied opening {path}, {hint}")]
+    PermissionDenied { path: String, hint: String },
+    #[error("Device disconnected")]
+    Disconnected,
+    #[error("Failed to open {path}")]
+    Open {
+        path: String,
+        source: hidapi::HidError,
+    },
+    #[error("Short write of report {report_id}, {written} of {expected} bytes")]
+    ShortWrite {
+        report_id: u32,
+        expected: usize,
+        written: usize,
+    },
+    #[error("No lamp under key {0:?}")]
+    UnknownKey(keyboard::Key),
+    #[error("No scene named {0:?}")]
+    UnknownScene(String),
+    #[error("Script error: {0}")]
+    Script(String),
+    #[error("Plugin error: {0}")]
+    Plugin(String),
+    #[error("MIDI error: {0}")]
+    Midi(String),
+    #[error("OSC error: {0}")]
+    Osc(String),
+    #[error("Bad light frame: {0}")]
+    Frame(String),
+    #[error("OpenRGB protocol error: {0}")]
+    OpenRgb(String),
+    #[error("Screen capture failed: {0}")]
+    Capture(String),
+    #[error("Audio error: {0}")]
+    Audio(String),
+    #[cfg(feature = "serde")]
+    #[error("Invalid JSON")]
+    Json(#[from] serde_json::Error),
 }
 
 #[extfn]
-fn indexed_name(
-    self: Option<StringIndex>,
-    dev: &HidDevice,
-    usage: hidparser::report_data_types::Usage,
-) -> Option<String> {
-    self.and_then(|i| unsafe {
-        // Unsafe to transmute string index as crate doesnt expose the inner value
-        let i = std::mem::transmute::<_, u32>(i) as i32;
-        dev.get_indexed_string(i).ok().flatten()
-    })
-    .or_else(|| {
-        hut::Usage::new_from_page_and_id(usage.page(), usage.id())
-            .map(|x| x.to_string())
-            .ok()
-    })
+fn string_number(self: StringIndex) -> i32 {
+    // Unsafe to transmute string index as crate doesnt expose the inner value
+    unsafe { std::mem::transmute::<_, u32>(self) as i32 }
 }
 
 #[extfn]
@@ -49,14 +190,21 @@ fn usage_name(self: hidparser::report_data_types::Usage) -> Option<String> {
 }
 
 #[extfn]
-fn parent_name(self: &Vec<hidparser::ReportCollection>, dev: &HidDevice) -> Option<String> {
-    let rc = self.first()?;
+fn collection_path(self: &Vec<hidparser::ReportCollection>) -> Vec<String> {
+    self.iter()
+        .map(|rc| {
+            rc.usage
+                .usage_name()
+                .unwrap_or_else(|| format!("0x{:02X}:0x{:02X}", rc.usage.page(), rc.usage.id()))
+        })
+        .collect()
+}
 
-    rc.string
-        .map(|s| unsafe { std::mem::transmute::<_, u32>(s) })
-        .and_then(|i| dev.get_indexed_string(i as _).ok())
-        .flatten()
-        .or_else(|| rc.usage.usage_name())
+#[extfn]
+fn collection_strings(self: &Vec<hidparser::ReportCollection>) -> Vec<Option<i32>> {
+    self.iter()
+        .map(|rc| rc.string.map(|x| x.string_number()))
+        .collect()
 }
 
 #[extfn]
@@ -108,64 +256,269 @@ fn into_hut(self: hidparser::report_data_types::Usage) -> Option<hut::Usage> {
     hut::Usage::new_from_page_and_id(self.page(), self.id()).ok()
 }
 
+pub(crate) fn is_vendor_field_usage(usage: hidparser::report_data_types::Usage) -> bool {
+    usage.into_hut().is_some_and(|x| x.is_vendor_usage())
+}
+
 pub struct HidLights {
-    hidapi: Arc<hidapi::HidApi>,
+    hidapi: Api,
+    cache: Arc<cache::DescriptorCache>,
+}
+
+/// The hidapi context, in whichever form the app shares it.
+#[derive(Clone)]
+pub(crate) enum Api {
+    Locked(Arc<RwLock<HidApi>>),
+    Shared(Arc<HidApi>),
 }
 
+impl Api {
+    /// Runs `f` with the context, holding off [`HidLights::refresh`] meanwhile.
+    pub fn with<T>(&self, f: impl FnOnce(&HidApi) -> T) -> T {
+        match self {
+            Api::Locked(x) => f(&x.read().unwrap_or_else(PoisonError::into_inner)),
+            Api::Shared(x) => f(x),
+        }
+    }
+
+    /// Like [`Self::with`], also holding off everyone else using the context through
+    /// this crate.
+    #[cfg(target_os = "macos")]
+    pub fn with_exclusive<T>(&self, f: impl FnOnce(&HidApi) -> T) -> T {
+        static SHARED: Mutex<()> = Mutex::new(());
+        match self {
+            Api::Locked(x) => f(&x.write().unwrap_or_else(PoisonError::into_inner)),
+            Api::Shared(x) => {
+                let _lock = SHARED.lock().unwrap_or_else(PoisonError::into_inner);
+                f(x)
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct DeviceInfo {
     pub vid: u16,
     pub pid: u16,
     pub name: Option<String>,
     pub manufacturer: Option<String>,
     pub usage: Option<hut::Usage>,
+    pub usage_page: u16,
+    pub usage_id: u16,
     pub serial: Option<String>,
+    pub interface_number: i32,
+    pub bus_type: hidapi::BusType,
+    pub release_number: u16,
     path: CString,
-    api: Arc<HidApi>,
+    id: DeviceId,
+    api: Api,
+    cache: Arc<cache::DescriptorCache>,
 }
 
+/// An open device. Clones share the device, what was last written to it and its write
+/// lock, but keep their own settings and values set through [`Self::set`].
 pub struct DeviceHandle {
-    device: HidDevice,
+    /// hidapi devices can't be used from several threads at once, see [`Self::hid`].
+    device: Arc<Mutex<HidDevice>>,
+    vid: u16,
+    pid: u16,
+    /// The interface's path, which its layout is cached under.
+    path: CString,
+    cache: Arc<cache::DescriptorCache>,
+    profile: Option<&'static profiles::Profile>,
+    state: Option<Vec<Report>>,
+    /// Last bytes written per report ID, so bits no output covers are written back as
+    /// they were. Seeded from GET_REPORT for feature reports, see [`Self::write_report`].
+    shadow: Arc<Mutex<HashMap<u32, Vec<u8>>>>,
+    quirks: Quirks,
+    write_path: WritePath,
+    retry_policy: RetryPolicy,
+    /// Set once [`WritePath::InterruptThenFeature`] has fallen back.
+    use_feature: AtomicBool,
+    disconnected: Arc<AtomicBool>,
+    metrics: Option<Arc<dyn metrics::WriteMetrics>>,
+    recorder: Option<Arc<recording::Recorder>>,
+    /// Shared with every other handle on the same path.
+    write_lock: Arc<write::DeviceLock>,
+    observers: Arc<Mutex<observers::Observers>>,
+    history: Option<history::History>,
+    /// String descriptors read so far by index, each is a request to the device.
+    strings: Arc<Mutex<HashMap<i32, Option<String>>>>,
+}
+
+impl Clone for DeviceHandle {
+    fn clone(&self) -> Self {
+        Self {
+            device: self.device.clone(),
+            vid: self.vid,
+            pid: self.pid,
+            path: self.path.clone(),
+            cache: self.cache.clone(),
+            profile: self.profile,
+            state: self.state.clone(),
+            shadow: self.shadow.clone(),
+            quirks: self.quirks,
+            write_path: self.write_path,
+            retry_policy: self.retry_policy,
+            use_feature: AtomicBool::new(self.use_feature.load(Ordering::Relaxed)),
+            disconnected: self.disconnected.clone(),
+            metrics: self.metrics.clone(),
+            recorder: self.recorder.clone(),
+            write_lock: self.write_lock.clone(),
+            observers: self.observers.clone(),
+            history: self.history.clone(),
+            strings: self.strings.clone(),
+        }
+    }
 }
 
 impl HidLights {
     pub fn new() -> Result<Self> {
         Ok(Self {
-            hidapi: Arc::new(hidapi::HidApi::new()?),
+            hidapi: Api::Locked(Arc::new(RwLock::new(hidapi::HidApi::new()?))),
+            cache: Default::default(),
         })
     }
 
+    /// Shares a context with other users of hidapi. It's behind a lock as
+    /// [`Self::refresh`] needs mutable access to the device list.
+    pub fn from_api(hidapi: Arc<RwLock<HidApi>>) -> Self {
+        Self {
+            hidapi: Api::Locked(hidapi),
+            cache: Default::default(),
+        }
+    }
+
+    /// Shares a context the app keeps as a plain `Arc`. Without a lock to get
+    /// mutable access, the device list is whatever the app last enumerated and
+    /// [`Self::refresh`] fails with [`HidLightError::SharedApi`].
+    pub fn from_shared_api(hidapi: Arc<HidApi>) -> Self {
+        Self {
+            hidapi: Api::Shared(hidapi),
+            cache: Default::default(),
+        }
+    }
+
+    /// The context, unless it was given with [`Self::from_shared_api`].
+    pub fn hidapi(&self) -> Option<Arc<RwLock<HidApi>>> {
+        match &self.hidapi {
+            Api::Locked(x) => Some(x.clone()),
+            Api::Shared(_) => None,
+        }
+    }
+
+    /// The context given with [`Self::from_shared_api`].
+    pub fn shared_hidapi(&self) -> Option<Arc<HidApi>> {
+        match &self.hidapi {
+            Api::Locked(_) => None,
+            Api::Shared(x) => Some(x.clone()),
+        }
+    }
+
+    /// Re-enumerates devices, later calls to [`Self::devices`] reflect the new list.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub fn refresh(&self) -> Result<()> {
+        let Api::Locked(hidapi) = &self.hidapi else {
+            return Err(HidLightError::SharedApi);
+        };
+        hidapi
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .refresh_devices()?;
+        Ok(())
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
     pub fn devices(&self) -> Vec<DeviceInfo> {
-        self.hidapi
-            .device_list()
-            .map(|x| DeviceInfo {
-                name: x
-                    .product_string()
-                    .map(|x| x.to_string())
-                    .filter(|x| !x.is_empty()),
-                manufacturer: x
-                    .manufacturer_string()
-                    .filter(|x| !x.is_empty())
-                    .map(|x| x.to_string()),
-                usage: hut::Usage::new_from_page_and_id(x.usage_page(), x.usage()).ok(),
-                serial: x
-                    .serial_number()
-                    .filter(|x| !x.is_empty())
-                    .map(|x| x.to_string()),
-
-                pid: x.product_id(),
-                vid: x.vendor_id(),
-                path: x.path().to_owned(),
-                api: self.hidapi.clone(),
-            })
-            .filter(|x| !x.is_vendor_usage())
-            .collect()
+        let devices: Vec<_> = self.hidapi.with(|api| {
+            api.device_list()
+                .map(|x| DeviceInfo {
+                    name: x
+                        .product_string()
+                        .map(|x| x.to_string())
+                        .filter(|x| !x.is_empty()),
+                    manufacturer: x
+                        .manufacturer_string()
+                        .filter(|x| !x.is_empty())
+                        .map(|x| x.to_string()),
+                    usage: hut::Usage::new_from_page_and_id(x.usage_page(), x.usage()).ok(),
+                    usage_page: x.usage_page(),
+                    usage_id: x.usage(),
+                    serial: x
+                        .serial_number()
+                        .filter(|x| !x.is_empty())
+                        .map(|x| x.to_string()),
+                    interface_number: x.interface_number(),
+                    bus_type: x.bus_type(),
+                    release_number: x.release_number(),
+
+                    pid: x.product_id(),
+                    vid: x.vendor_id(),
+                    path: x.path().to_owned(),
+                    id: DeviceId::for_device(x),
+                    api: self.hidapi.clone(),
+                    cache: self.cache.clone(),
+                })
+                .filter(|x| !x.is_vendor_usage() || drivers::is_supported(x))
+                .collect()
+        });
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(count = devices.len(), "enumerated devices");
+        devices
+    }
+
+    /// Forgets the report layouts of previously opened devices.
+    pub fn clear_descriptor_cache(&self) {
+        self.cache.clear();
+    }
+
+    pub fn find(&self, predicate: impl FnMut(&DeviceInfo) -> bool) -> Option<DeviceInfo> {
+        self.devices().into_iter().find(predicate)
+    }
+
+    /// Opens the first interface with the given IDs.
+    pub fn open(&self, vid: u16, pid: u16) -> Result<DeviceHandle> {
+        self.find(|x| x.vid == vid && x.pid == pid)
+            .ok_or(HidLightError::DeviceNotFound)?
+            .open()
+    }
+
+    pub fn open_with_serial(&self, vid: u16, pid: u16, serial: &str) -> Result<DeviceHandle> {
+        self.find(|x| x.vid == vid && x.pid == pid && x.serial.as_deref() == Some(serial))
+            .ok_or(HidLightError::DeviceNotFound)?
+            .open()
     }
 }
 
 impl DeviceInfo {
     pub fn open(&self) -> Result<DeviceHandle> {
-        let dev = self.api.open_path(&self.path)?;
-        Ok(DeviceHandle { device: dev })
+        self.open_with(&OpenOptions::default())
+    }
+
+    fn handle(&self, device: HidDevice) -> DeviceHandle {
+        let quirks = quirks::for_device(self);
+        DeviceHandle {
+            device: Arc::new(Mutex::new(device)),
+            vid: self.vid,
+            pid: self.pid,
+            path: self.path.clone(),
+            cache: self.cache.clone(),
+            profile: profiles::for_device(self),
+            state: None,
+            shadow: Arc::default(),
+            quirks,
+            write_path: quirks.write_path.unwrap_or_default(),
+            retry_policy: RetryPolicy::default(),
+            use_feature: AtomicBool::new(false),
+            disconnected: Arc::default(),
+            metrics: None,
+            recorder: None,
+            write_lock: write::DeviceLock::for_path(&self.path),
+            observers: Arc::default(),
+            history: None,
+            strings: Arc::default(),
+        }
     }
 
     pub fn is_vendor_usage(&self) -> bool {
@@ -177,185 +530,1410 @@ impl DeviceInfo {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum DeviceOutputValue {
     Toggle,
     Signed(RangeInclusive<i32>),
     Unsigned(RangeInclusive<i32>),
+    /// An array field, with up to `slots` of `options` active at a time. Option `n`
+    /// is written as the logical minimum plus `n`, one per slot, empty slots as 0.
+    Selector {
+        options: Vec<Usage>,
+        logical_minimum: i32,
+        slots: usize,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Usage {
+    pub page: u16,
+    pub id: u16,
+}
+
+impl Usage {
+    pub fn name(&self) -> Option<String> {
+        self.to_hut().map(|x| x.name())
+    }
+
+    pub fn to_hut(&self) -> Option<hut::Usage> {
+        hut::Usage::new_from_page_and_id(self.page, self.id).ok()
+    }
+}
+
+/// Identifies an output by its report and the first bit it occupies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct OutputId {
+    pub report_id: u32,
+    pub bit_offset: u32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DeviceOutput {
     kind: DeviceOutputValue,
+    report_id: u32,
     pub real_value: f32,
     bits: Range<u32>,
+    usage_page: u16,
+    usage_id: u16,
+    collections: Vec<String>,
+    designator: Option<u32>,
+    physical: Option<PhysicalRange>,
+    /// Indices into the selector's options, in slot order.
+    selected: Vec<usize>,
+    attributes: OutputAttributes,
+    strings: NameStrings,
     pub name: Option<String>,
 }
 
-#[derive(Debug)]
+/// String descriptors naming an output, read from the device by
+/// [`DeviceOutput::resolve_name`] rather than while parsing.
+#[derive(Debug, Clone, Default)]
+struct NameStrings {
+    /// The output's own string, and its slot when it's one of several identical ones.
+    name: Option<(i32, Option<u32>)>,
+    /// One per entry in [`DeviceOutput::collections`].
+    collections: Vec<Option<i32>>,
+}
+
+/// The flags a field's Output item was declared with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OutputAttributes {
+    /// Read only, usually padding or a fixed value.
+    pub constant: bool,
+    /// Values are changes from the current state rather than the state itself.
+    pub relative: bool,
+    /// Values roll over from the maximum to the minimum and back.
+    pub wrap: bool,
+    pub non_linear: bool,
+    /// The control doesn't return to a rest position on its own.
+    pub no_preferred_state: bool,
+    /// Values outside the logical range mean "no value", see [`Value::Null`].
+    pub null_state: bool,
+    /// The device may change the value on its own, so it shouldn't be assumed to
+    /// hold what was last written.
+    pub volatile: bool,
+    pub buffered_bytes: bool,
+}
+
+#[derive(Debug, Clone)]
 pub struct Report {
     id: u32,
+    /// Whether the descriptor declares a report ID, only then is it sent on the wire.
+    numbered: bool,
     pub outputs: Vec<DeviceOutput>,
     size_in_bits: usize,
+    dirty: bool,
 }
 
 impl DeviceHandle {
+    /// The raw report descriptor, see [`descriptor`] for saving it.
+    pub fn descriptor_bytes(&self) -> Result<Vec<u8>> {
+        let mut report_buffer = vec![0u8; 4096];
+
+        // Backends silently truncate to the buffer size, so a full buffer may mean there's more
+        loop {
+            let descriptor_len = self.hid().get_report_descriptor(&mut report_buffer)?;
+            if descriptor_len < report_buffer.len() {
+                report_buffer.truncate(descriptor_len);
+                return Ok(report_buffer);
+            }
+            if report_buffer.len() >= MAX_DESCRIPTOR_SIZE {
+                return Err(HidLightError::DescriptorTruncated(report_buffer.len()));
+            }
+            report_buffer.resize(report_buffer.len() * 2, 0);
+        }
+    }
+
+    fn report_descriptor(&self) -> Result<hidparser::ReportDescriptor> {
+        hidparser::parse_report_descriptor(&self.descriptor_bytes()?).map_err(descriptor_error)
+    }
+
+    /// The device's output reports, each with its outputs ordered by bit offset.
     pub fn reports(&self) -> Result<Vec<Report>> {
-        {
-            let dev = &self.device;
-            let mut report_buffer = [0u8; 4096];
-
-            let descriptor_len = dev.get_report_descriptor(&mut report_buffer)?;
-
-            let descriptor = hidparser::parse_report_descriptor(&report_buffer[0..descriptor_len])
-                .map_err(|_| HidLightError::DescriptorError)?;
-
-            let mut result = vec![];
-
-            for rep in descriptor.output_reports {
-                let report_id: u32 = rep.report_id.map(|x| x.into()).unwrap_or_default();
-                let mut report = Report {
-                    id: report_id,
-                    size_in_bits: rep.size_in_bits,
-                    outputs: vec![],
-                };
-
-                for rep_field in rep.fields {
-                    match rep_field {
-                        hidparser::ReportField::Variable(variable_field) => {
-                            if variable_field
-                                .usage
-                                .into_hut()
-                                .is_some_and(|x| x.is_vendor_usage())
-                                || !variable_field.attributes.variable
-                            {
-                                continue;
-                            }
+        let descriptor = self.descriptor_bytes()?;
+        let hash = device_id::fnv1a(&descriptor);
 
-                            let name = variable_field
-                                .string_index
-                                .indexed_name(&dev, variable_field.usage)
-                                .unwrap_or_else(|| "Unk".into());
-
-                            report.outputs.push(DeviceOutput {
-                                kind: if variable_field.bits.len() == 1 {
-                                    DeviceOutputValue::Toggle
-                                } else {
-                                    DeviceOutputValue::Unsigned(
-                                        variable_field.logical_minimum.into()
-                                            ..=variable_field.logical_maximum.into(),
-                                    )
-                                },
-                                real_value: 0.0,
-                                bits: variable_field.bits,
-                                name: Some(name),
-                            });
-                        }
-                        hidparser::ReportField::Array(array_field) => {
-                            let designators = array_field.designator_list.iter();
-                            let usages = array_field.usage_list.iter();
-                            let strings = array_field.string_list.iter();
-                            let size = array_field.bits.end - array_field.bits.start;
-                            let size = size / array_field.usage_list.len() as u32;
-                            for (i, ((_designator, usage), string)) in
-                                designators.zip(usages).zip(strings).enumerate()
-                            {
-                                let usage =
-                                    hidparser::report_data_types::Usage::from(usage.start());
-                                if usage.into_hut().is_some_and(|x| x.is_vendor_usage()) {
-                                    continue;
-                                }
-                                let mut name = string
-                                    .range()
-                                    .next()
-                                    .map(|x| StringIndex::from(x))
-                                    .indexed_name(&dev, usage)
-                                    .unwrap_or_else(|| "Unk".into());
-                                name.push(' ');
-                                name.push(char::from_digit(i as _, 10).unwrap());
-                                let start_bit = array_field.bits.start + i as u32 * size;
-                                let bits = start_bit..(start_bit + size);
-                                report.outputs.push(DeviceOutput {
-                                    kind: if bits.len() == 1 {
-                                        DeviceOutputValue::Toggle
-                                    } else {
-                                        DeviceOutputValue::Unsigned(
-                                            array_field.logical_minimum.into()
-                                                ..=array_field.logical_maximum.into(),
-                                        )
-                                    },
-                                    real_value: 0.0,
-                                    bits,
-                                    name: Some(name),
-                                });
-                            }
-                        }
-                        hidparser::ReportField::Padding(_) => {}
-                    }
-                }
+        let mut result = match self.cache.get(&self.path, self.vid, self.pid, hash) {
+            Some(reports) => {
+                #[cfg(feature = "tracing")]
+                tracing::debug!(vid = self.vid, pid = self.pid, "using cached reports");
+                reports
+            }
+            None => {
+                let reports = build_reports(&descriptor)?;
+                self.cache
+                    .insert(&self.path, self.vid, self.pid, hash, reports.clone());
+                reports
+            }
+        };
 
-                if !report.outputs.is_empty() {
-                    result.push(report);
-                }
+        if let Some(profile) = self.profile {
+            profile.apply(&mut result);
+        }
+
+        Ok(result)
+    }
+
+    pub fn profile(&self) -> Option<&'static profiles::Profile> {
+        self.profile
+    }
+
+    pub fn set_profile(&mut self, profile: Option<&'static profiles::Profile>) {
+        self.profile = profile;
+    }
+
+    /// Every output on the device, backed by state kept on the handle for [`Self::set`].
+    pub fn outputs(&mut self) -> Result<impl Iterator<Item = &DeviceOutput>> {
+        Ok(self.state()?.iter().flat_map(|x| x.outputs.iter()))
+    }
+
+    pub fn output(&mut self, id: OutputId) -> Result<&DeviceOutput> {
+        self.state()?
+            .iter()
+            .filter(|x| x.id == id.report_id)
+            .flat_map(|x| x.outputs.iter())
+            .find(|x| x.bits.start == id.bit_offset)
+            .ok_or(HidLightError::UnknownOutput(id))
+    }
+
+    pub fn output_by_key(&mut self, key: &OutputKey) -> Result<&DeviceOutput> {
+        self.state()?
+            .iter()
+            .flat_map(|x| x.outputs.iter())
+            .find(|x| key.matches(x))
+            .ok_or_else(|| HidLightError::UnknownOutputKey(key.clone()))
+    }
+
+    /// Updates an output's value, it's sent to the device on the next [`Self::flush`].
+    pub fn set(&mut self, id: OutputId, value: f32) -> Result<()> {
+        let report = self
+            .state()?
+            .iter_mut()
+            .filter(|x| x.id == id.report_id)
+            .find(|x| x.outputs.iter().any(|x| x.bits.start == id.bit_offset))
+            .ok_or(HidLightError::UnknownOutput(id))?;
+
+        for out in report.outputs.iter_mut() {
+            if out.bits.start == id.bit_offset {
+                out.real_value = value;
             }
+        }
+        report.dirty = true;
+        Ok(())
+    }
+
+    /// Like [`Self::set`], taking any [`Value`] and checking it fits the output.
+    pub fn set_value(&mut self, id: OutputId, value: Value) -> Result<()> {
+        let report = self
+            .state()?
+            .iter_mut()
+            .filter(|x| x.id == id.report_id)
+            .find(|x| x.outputs.iter().any(|x| x.bits.start == id.bit_offset))
+            .ok_or(HidLightError::UnknownOutput(id))?;
+
+        report.output_mut(id)?.set_value(value)?;
+        report.dirty = true;
+        Ok(())
+    }
+
+    /// Writes every report changed through [`Self::set`] since the last flush.
+    pub fn flush(&mut self) -> Result<()> {
+        let changed = self.state.iter().flatten().any(|x| x.dirty);
+        self.write_dirty()?;
+        if let (true, Some(history), Some(state)) = (changed, &mut self.history, &self.state) {
+            history.record(state);
+        }
+        Ok(())
+    }
+
+    fn write_dirty(&mut self) -> Result<()> {
+        let Some(mut state) = self.state.take() else {
+            return Ok(());
+        };
+
+        let _lock = self.lock_writes();
+        let result = state.iter_mut().filter(|x| x.dirty).try_for_each(|report| {
+            self.write_report(report)?;
+            report.dirty = false;
+            Ok(())
+        });
+
+        self.state = Some(state);
+        result
+    }
+
+    fn state(&mut self) -> Result<&mut Vec<Report>> {
+        if self.state.is_none() {
+            self.state = Some(self.reports()?);
+        }
+        Ok(self.state.get_or_insert_with(Vec::new))
+    }
+
+    /// Resolves the names of every output kept on the handle, see
+    /// [`DeviceOutput::resolve_name`].
+    pub fn resolve_names(&mut self) -> Result<()> {
+        let mut state = std::mem::take(self.state()?);
+        for report in &mut state {
+            report.resolve_names(self);
+        }
+        self.state = Some(state);
+        Ok(())
+    }
 
-            Ok(result)
+    /// A string descriptor, or None when the device doesn't have it. Only the first
+    /// lookup of an index reaches the device, failures included, as devices without
+    /// the string tend to stall on every request for it. The cache goes with the
+    /// handle, a reconnected device is asked again.
+    pub(crate) fn indexed_string(&self, index: i32) -> Option<String> {
+        let mut strings = self.strings.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(string) = strings.get(&index) {
+            return string.clone();
         }
+        let string = self.hid().get_indexed_string(index).ok().flatten();
+        strings.insert(index, string.clone());
+        string
     }
 
+    /// Writes `report`, keeping bits no output covers as they were last written.
+    ///
+    /// Before the first write, a report sent as a feature report starts from what the
+    /// device returns for it. hidapi can't read output reports back, so those start
+    /// from zero, or from [`Self::set_report_baseline`], until written once.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(report_id = report.id))
+    )]
     pub fn write_report(&self, report: &Report) -> Result<()> {
-        let mut buffer = vec![0u8; report.size_in_bits.div_ceil(8) + 1];
+        let _lock = self.lock_writes();
+        let mut shadow = self.shadow.lock().unwrap_or_else(PoisonError::into_inner);
+        if !shadow.contains_key(&report.id) {
+            if let Some(current) = self.current_feature(report) {
+                shadow.insert(report.id, current);
+            }
+        }
+        let buffer = self.pack(report, &shadow);
+        self.write_raw(&buffer)?;
+        shadow.insert(report.id, buffer[1..].to_vec());
+        drop(shadow);
+
+        self.notify(report);
+        Ok(())
+    }
+
+    /// Exactly what [`Self::write_report`] would send for `report`, report ID first.
+    pub fn packed_report(&self, report: &Report) -> Vec<u8> {
+        let shadow = self.shadow.lock().unwrap_or_else(PoisonError::into_inner);
+        self.pack(report, &shadow)
+    }
+
+    /// Writes `data` as is, report ID first, for debugging devices whose descriptor
+    /// doesn't match what they expect. Later writes of the report keep bits no output
+    /// covers as they're written here.
+    pub fn write_raw_report(&self, data: &[u8]) -> Result<()> {
+        let (id, rest) = data.split_first().ok_or(HidLightError::InvalidArgument)?;
+        self.write_raw(data)?;
+        self.shadow
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(*id as u32, rest.to_vec());
+
+        // Read back what the bytes set, for observers
+        if !self.observers().is_empty() {
+            let report = self.reports()?.into_iter().find(|x| x.id == *id as u32);
+            if let Some(mut report) = report {
+                report.read_from(rest, &self.quirks);
+                self.notify(&report);
+            }
+        }
+        Ok(())
+    }
+
+    /// What the device holds for `report`, without the report ID, when it's written
+    /// as a feature report and the device answers GET_REPORT for it.
+    fn current_feature(&self, report: &Report) -> Option<Vec<u8>> {
+        let features = match self.write_path {
+            WritePath::Interrupt => false,
+            WritePath::Feature => true,
+            WritePath::InterruptThenFeature => self.use_feature.load(Ordering::Relaxed),
+        };
+        if !features {
+            return None;
+        }
+        let mut buffer = vec![0; report.data_len() + 1];
         buffer[0] = report.id as u8;
-        let bits = buffer[1..].view_bits_mut::<Msb0>();
-        //TODO: Shouldn't have to set each bit individually, could set it using far fewer operations
-        for out in &report.outputs {
-            let real_value = out.real_value.clamp(0.0, 1.0);
-            match &out.kind {
-                DeviceOutputValue::Toggle => {
-                    let enabled = real_value > f32::EPSILON;
-                    for bit in out.bits.clone() {
-                        bits.set(bit as _, enabled);
+        // The length counts the report ID, which hidapi leaves in the first byte
+        let len = self.get_feature_raw(&mut buffer).ok()?;
+        Some(buffer.get(1..len)?.to_vec())
+    }
+
+    fn pack(&self, report: &Report, shadow: &HashMap<u32, Vec<u8>>) -> Vec<u8> {
+        let mut buffer = vec![0; report.data_len() + 1];
+        if let Some(previous) = shadow.get(&report.id) {
+            let len = previous.len().min(report.data_len());
+            buffer[1..=len].copy_from_slice(&previous[..len]);
+        }
+        report.pack_into(&mut buffer, &self.quirks);
+        buffer
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    /// Overrides how fields are packed, for devices the quirks database doesn't know.
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+        if let Some(path) = quirks.write_path {
+            self.set_write_path(path);
+        }
+    }
+
+    /// Splits writes longer than `size` bytes, see [`Quirks::max_write_size`].
+    pub fn set_max_write_size(&mut self, size: Option<usize>) {
+        self.quirks.max_write_size = size;
+    }
+
+    /// Sets the bytes, without the report ID, that bits not covered by any output
+    /// are written with, for devices that expect vendor or constant fields to hold a
+    /// particular value, and whose output reports can't be read back to find it.
+    /// Later writes keep whatever they last wrote.
+    pub fn set_report_baseline(&self, report_id: u32, data: &[u8]) {
+        self.shadow
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(report_id, data.to_vec());
+    }
+
+    pub(crate) fn write_raw(&self, data: &[u8]) -> Result<()> {
+        let _lock = self.lock_writes();
+        let Some(max) = self
+            .quirks
+            .max_write_size
+            .filter(|x| *x > 1 && data.len() > *x)
+        else {
+            return self.send(data);
+        };
+
+        // Every chunk goes out as its own report with the same ID
+        let mut chunk = Vec::with_capacity(max);
+        for part in data[1..].chunks(max - 1) {
+            chunk.clear();
+            chunk.push(data[0]);
+            chunk.extend_from_slice(part);
+            self.send(&chunk)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn read_raw(&self, buffer: &mut [u8], timeout_ms: i32) -> Result<usize> {
+        self.checked(self.hid().read_timeout(buffer, timeout_ms))
+    }
+
+    /// The device, held until the guard is dropped. Blocking reads hold it for their
+    /// whole timeout, so writes from other threads wait that long at most.
+    pub(crate) fn hid(&self) -> MutexGuard<'_, HidDevice> {
+        self.device.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub(crate) fn send_feature_raw(&self, data: &[u8]) -> Result<()> {
+        let _lock = self.lock_writes();
+        self.checked(self.hid().send_feature_report(data))
+    }
+
+    pub(crate) fn get_feature_raw(&self, buffer: &mut [u8]) -> Result<usize> {
+        self.checked(self.hid().get_feature_report(buffer))
+    }
+}
+
+/// hidparser's errors carry the offset and item that failed, keep them for bug reports.
+fn descriptor_error(err: impl std::fmt::Debug) -> HidLightError {
+    HidLightError::DescriptorError {
+        detail: format!("{err:?}"),
+    }
+}
+
+/// [`OutputAttributes`] from the attributes of any of hidparser's field types.
+macro_rules! output_attributes {
+    ($attributes:expr) => {{
+        let x = &$attributes;
+        OutputAttributes {
+            constant: x.constant,
+            relative: x.relative,
+            wrap: x.wrap,
+            non_linear: x.nonlinear,
+            no_preferred_state: x.no_preferred,
+            null_state: x.null_state,
+            volatile: x.volatile,
+            buffered_bytes: x.buffered_bytes,
+        }
+    }};
+}
+
+/// Builds reports from a raw descriptor, e.g. a dump or a device opened elsewhere.
+///
+/// Names come from the usage tables only, until resolved with
+/// [`DeviceOutput::resolve_name`] against a device with the same descriptor.
+pub fn parse_reports(descriptor: &[u8]) -> Result<Vec<Report>> {
+    build_reports(descriptor)
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(len = descriptor.len()))
+)]
+fn build_reports(descriptor: &[u8]) -> Result<Vec<Report>> {
+    let ranges = descriptor::usage_ranges(descriptor);
+    let descriptor = hidparser::parse_report_descriptor(descriptor).map_err(descriptor_error)?;
+
+    let mut result = vec![];
+
+    for rep in descriptor.output_reports {
+        let report_id: u32 = rep.report_id.map(|x| x.into()).unwrap_or_default();
+        let mut report = Report {
+            id: report_id,
+            numbered: rep.report_id.is_some(),
+            size_in_bits: rep.size_in_bits,
+            outputs: vec![],
+            dirty: false,
+        };
+
+        let ranges: Vec<_> = ranges
+            .iter()
+            .filter(|x| x.kind == descriptor::MainKind::Output && x.report_id == report_id)
+            .collect();
+        let elements = field_elements(&rep.fields, &ranges);
+
+        for (field_index, rep_field) in rep.fields.into_iter().enumerate() {
+            match rep_field {
+                hidparser::ReportField::Variable(mut variable_field) => {
+                    if variable_field
+                        .usage
+                        .into_hut()
+                        .is_some_and(|x| x.is_vendor_usage())
+                        || !variable_field.attributes.variable
+                    {
+                        continue;
                     }
-                }
-                DeviceOutputValue::Signed(x) => {
-                    // Unused for now
-                    let value = x.start() + ((x.end() - x.start()) as f32 * real_value) as i32;
-                    let value = value as i32;
 
-                    for (src_bit, dst_bit) in out.bits.clone().enumerate() {
-                        bits.set(dst_bit as _, (value & (1 << src_bit)) != 0);
+                    let element = elements[field_index];
+                    let in_range = element.range_id.is_some();
+                    if let Some(id) = element.range_id {
+                        variable_field.usage = hidparser::report_data_types::Usage::from(
+                            u32::from(variable_field.usage.page()) << 16 | u32::from(id),
+                        );
                     }
+
+                    // A string index on a usage range is shared by every instance
+                    let string_index = if in_range {
+                        None
+                    } else {
+                        variable_field.string_index
+                    };
+                    let mut name = variable_field
+                        .usage
+                        .into_hut()
+                        .map(|x| x.to_string())
+                        .unwrap_or_else(|| "Unk".into());
+                    // Identical slots from a report count only differ by position
+                    let slot = (!in_range && element.count > 1).then_some(element.index);
+                    if let Some(slot) = slot {
+                        name = format!("{name} [{slot}]");
+                    }
+
+                    report.outputs.push(DeviceOutput {
+                        kind: if variable_field.bits.len() == 1 {
+                            DeviceOutputValue::Toggle
+                        } else {
+                            DeviceOutputValue::Unsigned(
+                                variable_field.logical_minimum.into()
+                                    ..=variable_field.logical_maximum.into(),
+                            )
+                        },
+                        report_id,
+                        real_value: 0.0,
+                        bits: variable_field.bits,
+                        usage_page: variable_field.usage.page(),
+                        usage_id: variable_field.usage.id(),
+                        collections: variable_field.member_of.collection_path(),
+                        designator: variable_field.designator_index.map(|x| unsafe {
+                            // Same as string indexes, the inner value isn't exposed
+                            std::mem::transmute::<_, u32>(x)
+                        }),
+                        physical: physical_range(&variable_field),
+                        selected: vec![],
+                        attributes: output_attributes!(variable_field.attributes),
+                        strings: NameStrings {
+                            name: string_index.map(|x| (x.string_number(), slot)),
+                            collections: variable_field.member_of.collection_strings(),
+                        },
+                        name: Some(name),
+                    });
                 }
-                DeviceOutputValue::Unsigned(x) => {
-                    let value = x.start() + ((x.end() - x.start()) as f32 * real_value) as i32;
-                    let value = value as u32;
-                    if value > 0 {
-                        for (src_bit, dst_bit) in out.bits.clone().rev().enumerate() {
-                            let set = (value & (1 << src_bit)) != 0;
-                            bits.set(dst_bit as _, set);
+                hidparser::ReportField::Array(array_field) => {
+                    let options: Vec<Usage> = array_field
+                        .usage_list
+                        .iter()
+                        .flat_map(|x| x.start()..=x.end())
+                        .map(|x| {
+                            let usage = hidparser::report_data_types::Usage::from(x);
+                            Usage {
+                                page: usage.page(),
+                                id: usage.id(),
+                            }
+                        })
+                        .collect();
+                    let Some(first) = options.first().copied() else {
+                        continue;
+                    };
+                    if options
+                        .iter()
+                        .all(|x| x.to_hut().is_some_and(|x| x.is_vendor_usage()))
+                    {
+                        continue;
+                    }
+
+                    let logical_minimum: i32 = array_field.logical_minimum.into();
+
+                    // Each slot of a report count comes as its own field, put back
+                    // together so one selection can't be repeated across them
+                    if let Some(DeviceOutput {
+                        kind:
+                            DeviceOutputValue::Selector {
+                                options: previous,
+                                logical_minimum: previous_minimum,
+                                slots,
+                            },
+                        bits,
+                        ..
+                    }) = report.outputs.last_mut()
+                    {
+                        if *previous == options
+                            && *previous_minimum == logical_minimum
+                            && bits.end == array_field.bits.start
+                            && bits.len() / *slots == array_field.bits.len()
+                        {
+                            bits.end = array_field.bits.end;
+                            *slots += 1;
+                            continue;
                         }
                     }
+
+                    let name = array_field
+                        .member_of
+                        .last()
+                        .and_then(|x| x.usage.usage_name())
+                        .unwrap_or_else(|| "Selector".into());
+                    let string_index = array_field
+                        .string_list
+                        .first()
+                        .and_then(|x| x.range().next())
+                        .map(|x| StringIndex::from(x).string_number());
+
+                    report.outputs.push(DeviceOutput {
+                        kind: DeviceOutputValue::Selector {
+                            options,
+                            logical_minimum,
+                            slots: 1,
+                        },
+                        report_id,
+                        real_value: 0.0,
+                        bits: array_field.bits,
+                        usage_page: first.page,
+                        usage_id: first.id,
+                        collections: array_field.member_of.collection_path(),
+                        designator: array_field
+                            .designator_list
+                            .first()
+                            .and_then(|x| x.range().next()),
+                        physical: None,
+                        selected: vec![],
+                        attributes: output_attributes!(array_field.attributes),
+                        strings: NameStrings {
+                            name: string_index.map(|x| (x, None)),
+                            collections: array_field.member_of.collection_strings(),
+                        },
+                        name: Some(name),
+                    });
                 }
+                hidparser::ReportField::Padding(_) => {}
             }
         }
 
-        self.device.write(&buffer)?;
-        Ok(())
+        // In the order they're laid out on the wire, whatever order the fields came in
+        report.outputs.sort_by_key(|x| x.bits.start);
+        if !report.outputs.is_empty() {
+            result.push(report);
+        }
     }
+
+    #[cfg(feature = "tracing")]
+    tracing::debug!(
+        reports = result.len(),
+        outputs = result.iter().map(|x| x.outputs.len()).sum::<usize>(),
+        "parsed descriptor"
+    );
+    Ok(result)
+}
+
+/// The field's physical extent and unit, `None` when it declares neither.
+fn physical_range(field: &hidparser::VariableField) -> Option<PhysicalRange> {
+    let unit: u32 = field.unit.map(|x| x.into()).unwrap_or_default();
+    let exponent: u32 = field.unit_exponent.map(|x| x.into()).unwrap_or_default();
+    let (min, max): (i32, i32) = match (field.physical_minimum, field.physical_maximum) {
+        (Some(min), Some(max)) => (min.into(), max.into()),
+        _ => (field.logical_minimum.into(), field.logical_maximum.into()),
+    };
+
+    // Both zero means undefined, in which case the logical range applies
+    let (min, max) = if min == 0 && max == 0 {
+        (field.logical_minimum.into(), field.logical_maximum.into())
+    } else {
+        (min, max)
+    };
+
+    if unit == 0 && exponent == 0 && field.physical_minimum.is_none() {
+        return None;
+    }
+
+    Some(PhysicalRange {
+        min,
+        max,
+        unit: Unit(unit),
+        exponent: units::nibble_to_signed(exponent).into(),
+    })
+}
+
+/// Position of a variable field among the elements of its report count.
+#[derive(Debug, Clone, Copy, Default)]
+struct FieldElement {
+    index: u32,
+    count: u32,
+    /// The element's usage ID, when the descriptor declared a Usage Minimum/Maximum
+    /// range for it.
+    range_id: Option<u16>,
+}
+
+/// hidparser emits one field per element of a report count, giving every element the
+/// last declared usage when there are fewer usages than elements. Groups those runs
+/// back together so they can be told apart.
+///
+/// Elements of a main item declared with a usage range, from `ranges`, step through
+/// it instead, see [`descriptor::usage_ranges`].
+fn field_elements(
+    fields: &[hidparser::ReportField],
+    ranges: &[&descriptor::UsageRange],
+) -> Vec<FieldElement> {
+    let mut elements: Vec<FieldElement> = Vec::with_capacity(fields.len());
+    let mut previous: Option<&hidparser::VariableField> = None;
+
+    for field in fields {
+        let hidparser::ReportField::Variable(field) = field else {
+            previous = None;
+            elements.push(FieldElement::default());
+            continue;
+        };
+
+        let continues = previous.is_some_and(|x| {
+            (x.usage.page(), x.usage.id()) == (field.usage.page(), field.usage.id())
+                && x.bits.end == field.bits.start
+                && x.bits.len() == field.bits.len()
+        });
+
+        let index = match elements.last() {
+            Some(last) if continues => last.index + 1,
+            _ => 0,
+        };
+        elements.push(FieldElement {
+            index,
+            count: 1,
+            range_id: ranges
+                .iter()
+                .find(|x| x.bits.contains(&field.bits.start))
+                .map(|x| x.element_id(field.bits.start, field.bits.len() as u32)),
+        });
+        previous = Some(field);
+    }
+
+    // Fill in the run lengths, walking back from the end of each run
+    let mut i = elements.len();
+    while i > 0 {
+        let count = elements[i - 1].index + 1;
+        for element in &mut elements[i - count as usize..i] {
+            element.count = count;
+        }
+        i -= count as usize;
+    }
+
+    elements
 }
 
 impl DeviceOutput {
+    pub fn id(&self) -> OutputId {
+        OutputId {
+            report_id: self.report_id,
+            bit_offset: self.bits.start,
+        }
+    }
+
     pub fn is_toggle(&self) -> bool {
         matches!(self.kind, DeviceOutputValue::Toggle)
     }
 
+    pub fn is_selector(&self) -> bool {
+        matches!(self.kind, DeviceOutputValue::Selector { .. })
+    }
+
+    pub fn kind(&self) -> &DeviceOutputValue {
+        &self.kind
+    }
+
+    /// The usages a selector can choose between, empty for other outputs.
+    pub fn options(&self) -> &[Usage] {
+        match &self.kind {
+            DeviceOutputValue::Selector { options, .. } => options,
+            _ => &[],
+        }
+    }
+
+    /// Index into [`Self::options`] of the active usage, the first one when several
+    /// are.
+    pub fn selected(&self) -> Option<usize> {
+        self.selected.first().copied()
+    }
+
+    /// Indices into [`Self::options`] of every active usage, in slot order.
+    pub fn selection(&self) -> &[usize] {
+        &self.selected
+    }
+
+    /// How many options a selector can have active at once, 0 for other outputs.
+    pub fn slots(&self) -> usize {
+        match &self.kind {
+            DeviceOutputValue::Selector { slots, .. } => *slots,
+            _ => 0,
+        }
+    }
+
+    /// Makes option `index` the only active one.
+    pub fn select(&mut self, index: usize) -> Result<()> {
+        self.check_option(index)?;
+        self.selected = vec![index];
+        Ok(())
+    }
+
+    /// Makes option `index` active alongside those already active. Fails with
+    /// [`HidLightError::InvalidArgument`] when every slot is taken.
+    pub fn add_selection(&mut self, index: usize) -> Result<()> {
+        self.check_option(index)?;
+        if self.selected.contains(&index) {
+            return Ok(());
+        }
+        if self.selected.len() >= self.slots() {
+            return Err(HidLightError::InvalidArgument);
+        }
+        self.selected.push(index);
+        Ok(())
+    }
+
+    /// Makes option `index` inactive, moving later selections up a slot.
+    pub fn remove_selection(&mut self, index: usize) {
+        self.selected.retain(|x| *x != index);
+    }
+
+    fn check_option(&self, index: usize) -> Result<()> {
+        if index >= self.options().len() {
+            return Err(HidLightError::OutOfRange {
+                output: self.id(),
+                value: index as i32,
+                range: 0..=self.options().len() as i32 - 1,
+            });
+        }
+        Ok(())
+    }
+
+    /// Selects the option with the given usage.
+    pub fn select_usage(&mut self, page: u16, id: u16) -> Result<()> {
+        let index = self.option_index(page, id)?;
+        self.select(index)
+    }
+
+    fn option_index(&self, page: u16, id: u16) -> Result<usize> {
+        self.options()
+            .iter()
+            .position(|x| x.page == page && x.id == id)
+            .ok_or(HidLightError::UnknownUsage {
+                output: self.id(),
+                page,
+                id,
+            })
+    }
+
+    pub fn deselect(&mut self) {
+        self.selected.clear();
+    }
+
+    /// Whether the output is, or on a selector can choose, the given usage.
+    pub fn has_usage(&self, page: u16, id: u16) -> bool {
+        (self.usage_page == page && self.usage_id == id)
+            || self.options().iter().any(|x| x.page == page && x.id == id)
+    }
+
+    /// Turns the given usage on or off. Selectors with several slots keep the other
+    /// active usages, a single slot selector switches to the usage.
+    pub fn set_usage_active(&mut self, page: u16, id: u16, on: bool) -> Result<()> {
+        if !self.is_selector() {
+            self.real_value = if on { 1.0 } else { 0.0 };
+            return Ok(());
+        }
+        let index = self.option_index(page, id)?;
+        match on {
+            true if self.slots() == 1 => self.select(index)?,
+            true => self.add_selection(index)?,
+            false => self.remove_selection(index),
+        }
+        Ok(())
+    }
+
     pub fn bits(&self) -> &Range<u32> {
         &self.bits
     }
+
+    /// Where the output starts in its report's data, outputs are ordered by it.
+    pub fn bit_offset(&self) -> u32 {
+        self.bits.start
+    }
+
+    pub fn logical_range(&self) -> RangeInclusive<i32> {
+        match &self.kind {
+            DeviceOutputValue::Toggle => 0..=1,
+            DeviceOutputValue::Signed(x) | DeviceOutputValue::Unsigned(x) => x.clone(),
+            DeviceOutputValue::Selector {
+                options,
+                logical_minimum,
+                ..
+            } => *logical_minimum..=(logical_minimum + options.len() as i32 - 1),
+        }
+    }
+
+    /// The value that gets written to the device, for selectors the one in the first
+    /// slot.
+    pub fn logical_value(&self) -> i32 {
+        if let Some(null) = self.null_value().filter(|_| self.is_null()) {
+            return null;
+        }
+        let real_value = self.real_value.clamp(0.0, 1.0);
+        match &self.kind {
+            // Every bit of a toggle is set, whatever its width
+            DeviceOutputValue::Toggle => {
+                if real_value > f32::EPSILON {
+                    -1
+                } else {
+                    0
+                }
+            }
+            DeviceOutputValue::Signed(x) | DeviceOutputValue::Unsigned(x) => {
+                x.start() + ((x.end() - x.start()) as f32 * real_value).round() as i32
+            }
+            // Zero is outside the logical range of any sane array field, so nothing
+            // is selected
+            DeviceOutputValue::Selector {
+                logical_minimum, ..
+            } => self
+                .selected()
+                .map(|x| logical_minimum + x as i32)
+                .unwrap_or(0),
+        }
+    }
+
+    /// What gets written where, one entry per slot of a selector.
+    pub(crate) fn fields(&self) -> Vec<(Range<u32>, i32)> {
+        let DeviceOutputValue::Selector {
+            logical_minimum,
+            slots,
+            ..
+        } = &self.kind
+        else {
+            return vec![(self.bits.clone(), self.logical_value())];
+        };
+        let width = self.bits.len() as u32 / *slots as u32;
+        (0..*slots)
+            .map(|slot| {
+                let start = self.bits.start + slot as u32 * width;
+                let value = self
+                    .selected
+                    .get(slot)
+                    .map_or(0, |x| logical_minimum + *x as i32);
+                (start..start + width, value)
+            })
+            .collect()
+    }
+
+    /// Sets `real_value` so that the given logical value is written.
+    pub fn set_logical(&mut self, value: i32) {
+        let range = self.logical_range();
+        let span = (range.end() - range.start()) as f32;
+        self.real_value = if span > 0.0 {
+            (value - range.start()) as f32 / span
+        } else {
+            0.0
+        }
+        .clamp(0.0, 1.0);
+    }
+
+    /// The physical extent and unit declared by the descriptor, or the logical range
+    /// without a unit when it doesn't declare one.
+    pub fn physical_range(&self) -> PhysicalRange {
+        self.physical.unwrap_or_else(|| {
+            let logical = self.logical_range();
+            PhysicalRange {
+                min: *logical.start(),
+                max: *logical.end(),
+                unit: Unit::default(),
+                exponent: 0,
+            }
+        })
+    }
+
+    pub fn unit(&self) -> Unit {
+        self.physical_range().unit
+    }
+
+    pub fn unit_exponent(&self) -> i32 {
+        self.physical_range().exponent
+    }
+
+    /// Sets the output from a value in its physical unit, e.g. candela or seconds,
+    /// clamped to the physical range.
+    pub fn set_physical(&mut self, value: f64) {
+        self.real_value = self.physical_range().fraction(value) as f32;
+    }
+
+    /// The value currently set, in its physical unit.
+    pub fn physical_value(&self) -> f64 {
+        let range = self.physical_range();
+        range.scaled_min() + (range.scaled_max() - range.scaled_min()) * self.real_value as f64
+    }
+
+    /// Names of the collections containing this output, outermost first.
+    pub fn collections(&self) -> &[String] {
+        &self.collections
+    }
+
+    /// The collection path joined for display, e.g. "Keyboard / LED Indicators".
+    pub fn collection_path(&self) -> String {
+        self.collections.join(" / ")
+    }
+
+    /// Index into the device's physical descriptor, describing where the output is mounted.
+    pub fn designator(&self) -> Option<u32> {
+        self.designator
+    }
+
+    pub fn attributes(&self) -> OutputAttributes {
+        self.attributes
+    }
+
+    /// The output's name, first replacing the usage table names of it and its
+    /// collections with any the device gives as strings. The strings are only read
+    /// once, as that's a request to the device each.
+    pub fn resolve_name(&mut self, handle: &DeviceHandle) -> Option<&str> {
+        let strings = std::mem::take(&mut self.strings);
+        if let Some((index, slot)) = strings.name {
+            if let Some(name) = handle.indexed_string(index) {
+                self.name = Some(match slot {
+                    Some(slot) => format!("{name} [{slot}]"),
+                    None => name,
+                });
+            }
+        }
+        for (collection, index) in self.collections.iter_mut().zip(strings.collections) {
+            if let Some(name) = index.and_then(|x| handle.indexed_string(x)) {
+                *collection = name;
+            }
+        }
+        self.name.as_deref()
+    }
+
+    /// Whether the output is set to [`Value::Null`].
+    pub fn is_null(&self) -> bool {
+        !self.is_selector() && self.real_value.is_nan()
+    }
+
+    /// The value written for [`Value::Null`], just outside the logical range. `None`
+    /// unless the field has the null state flag and a value to spare in its bits.
+    pub fn null_value(&self) -> Option<i32> {
+        let width = self.bits.len() as u32;
+        if !self.attributes.null_state || self.is_selector() || width == 0 || width > 32 {
+            return None;
+        }
+        let (min, max) = (
+            *self.logical_range().start() as i64,
+            *self.logical_range().end() as i64,
+        );
+        // Negative ranges are read as signed
+        let bits = if min < 0 {
+            -(1i64 << (width - 1))..=(1i64 << (width - 1)) - 1
+        } else {
+            0..=(1i64 << width) - 1
+        };
+        [min - 1, max + 1]
+            .into_iter()
+            .find(|x| bits.contains(x))
+            .map(|x| x as i32)
+    }
+
+    pub fn usage_page(&self) -> u16 {
+        self.usage_page
+    }
+
+    pub fn usage_id(&self) -> u16 {
+        self.usage_id
+    }
+
+    pub fn usage(&self) -> Option<hut::Usage> {
+        hut::Usage::new_from_page_and_id(self.usage_page, self.usage_id).ok()
+    }
 }
 
 impl Report {
     pub fn id(&self) -> u32 {
         self.id
     }
+
+    /// Resolves every output's name, see [`DeviceOutput::resolve_name`].
+    pub fn resolve_names(&mut self, handle: &DeviceHandle) {
+        for out in &mut self.outputs {
+            out.resolve_name(handle);
+        }
+    }
+
+    pub fn is_numbered(&self) -> bool {
+        self.numbered
+    }
+
+    /// Length of the report data on the wire, excluding the report ID.
+    pub fn data_len(&self) -> usize {
+        self.size_in_bits.div_ceil(8)
+    }
+
+    /// The report as [`DeviceHandle::write_report`] would send it to a device without
+    /// quirks that hasn't been written to yet, report ID first.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = vec![0; self.data_len() + 1];
+        self.pack_into(&mut buffer, &Quirks::default());
+        buffer
+    }
+
+    /// Packs the report into `buffer`, report ID first, leaving bits no output covers
+    /// as they are. Returns the number of bytes used.
+    pub fn write_into(&self, buffer: &mut [u8]) -> Result<usize> {
+        let len = self.data_len() + 1;
+        let buffer = buffer
+            .get_mut(..len)
+            .ok_or(HidLightError::InvalidArgument)?;
+        self.pack_into(buffer, &Quirks::default());
+        Ok(len)
+    }
+
+    /// Sets every output to what `data`, without the report ID, holds, the reverse of
+    /// packing.
+    pub(crate) fn read_from(&mut self, data: &[u8], quirks: &Quirks) {
+        for out in &mut self.outputs {
+            let fields = out.fields();
+            match &out.kind {
+                DeviceOutputValue::Selector {
+                    options,
+                    logical_minimum,
+                    ..
+                } => {
+                    let count = options.len();
+                    out.selected = fields
+                        .into_iter()
+                        .map(|(bits, _)| quirks.unpack(data, bits) as i32 - logical_minimum)
+                        .filter_map(|x| usize::try_from(x).ok())
+                        .filter(|x| *x < count)
+                        .collect();
+                }
+                DeviceOutputValue::Signed(_) => {
+                    let width = out.bits.len() as u32;
+                    let raw = quirks.unpack(data, out.bits.clone());
+                    let shift = 32 - width.clamp(1, 32);
+                    out.set_logical(((raw << shift) as i32) >> shift);
+                }
+                _ => out.set_logical(quirks.unpack(data, out.bits.clone()) as i32),
+            }
+        }
+    }
+
+    fn pack_into(&self, buffer: &mut [u8], quirks: &Quirks) {
+        // Field offsets start after the report ID when there is one. Without one, hidapi
+        // still expects a leading zero that isn't sent to the device.
+        buffer[0] = if self.numbered { self.id as u8 } else { 0 };
+        for (bits, value) in self.outputs.iter().flat_map(|x| x.fields()) {
+            quirks.pack(&mut buffer[1..], bits, value as u32);
+        }
+    }
+
+    /// Outputs ordered by their designator index, i.e. the order they're physically
+    /// mounted in. Outputs without one follow in report order.
+    pub fn outputs_by_position(&self) -> Vec<&DeviceOutput> {
+        let mut outputs: Vec<_> = self.outputs.iter().collect();
+        outputs.sort_by_key(|x| x.designator.unwrap_or(u32::MAX));
+        outputs
+    }
+
+    /// Names outputs that have a designator by their position among the outputs of the
+    /// same usage, e.g. "LED 0" to "LED N" along a strip.
+    pub fn label_by_position(&mut self) {
+        let mut order: Vec<usize> = (0..self.outputs.len())
+            .filter(|x| self.outputs[*x].designator.is_some())
+            .collect();
+        order.sort_by_key(|x| self.outputs[*x].designator);
+
+        let mut counts: Vec<((u16, u16), usize)> = vec![];
+        for index in order {
+            let out = &mut self.outputs[index];
+            let usage = (out.usage_page, out.usage_id);
+            let position = match counts.iter_mut().find(|(x, _)| *x == usage) {
+                Some((_, count)) => {
+                    *count += 1;
+                    *count
+                }
+                None => {
+                    counts.push((usage, 0));
+                    0
+                }
+            };
+            let base = out
+                .usage()
+                .map(|x| x.name())
+                .unwrap_or_else(|| "Output".into());
+            out.name = Some(format!("{base} {position}"));
+            out.strings.name = None;
+        }
+    }
+
+    pub fn output_by_key(&self, key: &OutputKey) -> Option<&DeviceOutput> {
+        self.outputs.iter().find(|x| key.matches(x))
+    }
+
+    /// The output with the given usage, or the selector that can choose it.
+    pub fn output_by_usage(&self, page: u16, id: u16) -> Option<&DeviceOutput> {
+        self.outputs.iter().find(|x| x.has_usage(page, id))
+    }
+
+    pub fn output_by_usage_mut(&mut self, page: u16, id: u16) -> Option<&mut DeviceOutput> {
+        self.outputs.iter_mut().find(|x| x.has_usage(page, id))
+    }
+
+    /// Outputs whose name contains `pattern`, ignoring case. Names the device gives
+    /// only match once resolved, see [`Self::resolve_names`].
+    pub fn outputs_matching<'a>(
+        &'a self,
+        pattern: &str,
+    ) -> impl Iterator<Item = &'a DeviceOutput> + 'a {
+        let pattern = pattern.to_lowercase();
+        self.outputs.iter().filter(move |x| {
+            x.name
+                .as_ref()
+                .is_some_and(|x| x.to_lowercase().contains(&pattern))
+        })
+    }
+
+    pub fn outputs_matching_mut<'a>(
+        &'a mut self,
+        pattern: &str,
+    ) -> impl Iterator<Item = &'a mut DeviceOutput> + 'a {
+        let pattern = pattern.to_lowercase();
+        self.outputs.iter_mut().filter(move |x| {
+            x.name
+                .as_ref()
+                .is_some_and(|x| x.to_lowercase().contains(&pattern))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 32 Generic Indicators from one usage, then a button array of 32 usages.
+    const INDICATORS: &[u8] = &[
+        0x05, 0x08, // Usage Page (LEDs)
+        0x09, 0x4b, // Usage (Generic Indicator)
+        0xa1, 0x01, // Collection (Application)
+        0x09, 0x4b, //   Usage (Generic Indicator)
+        0x15, 0x00, //   Logical Minimum (0)
+        0x25, 0x01, //   Logical Maximum (1)
+        0x75, 0x01, //   Report Size (1)
+        0x95, 0x20, //   Report Count (32)
+        0x91, 0x02, //   Output (Data, Variable, Absolute)
+        0x05, 0x09, //   Usage Page (Button)
+        0x19, 0x01, //   Usage Minimum (1)
+        0x29, 0x20, //   Usage Maximum (32)
+        0x15, 0x01, //   Logical Minimum (1)
+        0x25, 0x20, //   Logical Maximum (32)
+        0x75, 0x08, //   Report Size (8)
+        0x95, 0x01, //   Report Count (1)
+        0x91, 0x00, //   Output (Data, Array, Absolute)
+        0xc0, // End Collection
+    ];
+
+    #[test]
+    fn names_every_element_of_a_large_report_count() {
+        let reports = parse_reports(INDICATORS).unwrap();
+        assert_eq!(reports.len(), 1);
+        let outputs = &reports[0].outputs;
+        assert_eq!(outputs.len(), 33);
+
+        for (i, out) in outputs[..32].iter().enumerate() {
+            let i = i as u32;
+            assert_eq!(
+                out.name.as_deref(),
+                Some(format!("Generic Indicator [{i}]").as_str())
+            );
+            assert_eq!(out.bits(), &(i..i + 1));
+            assert!(out.is_toggle());
+        }
+    }
+
+    #[test]
+    fn keeps_a_large_array_as_one_selector() {
+        let reports = parse_reports(INDICATORS).unwrap();
+        let selector = &reports[0].outputs[32];
+        assert!(selector.is_selector());
+        assert_eq!(selector.bits(), &(32..40));
+        assert_eq!(selector.options().len(), 32);
+        assert_eq!(selector.options()[31], Usage { page: 0x09, id: 32 });
+        assert_eq!(selector.name.as_deref(), Some("Generic Indicator"));
+    }
+
+    #[test]
+    fn repeats_a_single_declared_usage() {
+        let descriptor = [
+            0x05, 0x08, // Usage Page (LEDs)
+            0x09, 0x4b, // Usage (Generic Indicator)
+            0xa1, 0x01, // Collection (Application)
+            0x09, 0x56, //   Usage (LED Intensity)
+            0x15, 0x00, //   Logical Minimum (0)
+            0x26, 0xff, 0x00, //   Logical Maximum (255)
+            0x75, 0x08, //   Report Size (8)
+            0x95, 0x03, //   Report Count (3)
+            0x91, 0x02, //   Output (Data, Variable, Absolute)
+            0xc0, // End Collection
+        ];
+        let reports = parse_reports(&descriptor).unwrap();
+        let outputs = &reports[0].outputs;
+        assert_eq!(outputs.len(), 3);
+        for (i, out) in outputs.iter().enumerate() {
+            assert_eq!(out.usage_id(), 0x56);
+            assert!(out.name.as_deref().unwrap().ends_with(&format!(" [{i}]")));
+        }
+    }
+
+    #[test]
+    fn steps_through_a_usage_range() {
+        let descriptor = [
+            0x05, 0x08, // Usage Page (LEDs)
+            0x09, 0x4b, // Usage (Generic Indicator)
+            0xa1, 0x01, // Collection (Application)
+            0x19, 0x01, //   Usage Minimum (Num Lock)
+            0x29, 0x02, //   Usage Maximum (Caps Lock)
+            0x15, 0x00, //   Logical Minimum (0)
+            0x25, 0x01, //   Logical Maximum (1)
+            0x75, 0x01, //   Report Size (1)
+            0x95, 0x03, //   Report Count (3)
+            0x91, 0x02, //   Output (Data, Variable, Absolute)
+            0xc0, // End Collection
+        ];
+        let reports = parse_reports(&descriptor).unwrap();
+        let ids: Vec<_> = reports[0].outputs.iter().map(|x| x.usage_id()).collect();
+        // Elements past the range repeat its last usage
+        assert_eq!(ids, [0x01, 0x02, 0x02]);
+    }
+
+    #[test]
+    fn fills_array_slots_from_the_selection() {
+        let descriptor = [
+            0x05, 0x09, // Usage Page (Button)
+            0x09, 0x01, // Usage (Button 1)
+            0xa1, 0x01, // Collection (Application)
+            0x19, 0x01, //   Usage Minimum (1)
+            0x29, 0x04, //   Usage Maximum (4)
+            0x15, 0x01, //   Logical Minimum (1)
+            0x25, 0x04, //   Logical Maximum (4)
+            0x75, 0x08, //   Report Size (8)
+            0x95, 0x02, //   Report Count (2)
+            0x91, 0x00, //   Output (Data, Array, Absolute)
+            0xc0, // End Collection
+        ];
+        let mut reports = parse_reports(&descriptor).unwrap();
+        let report = &mut reports[0];
+        assert_eq!(report.outputs.len(), 1);
+        let selector = &mut report.outputs[0];
+        assert_eq!(selector.bits(), &(0..16));
+        assert_eq!(selector.slots(), 2);
+
+        selector.add_selection(0).unwrap();
+        selector.add_selection(2).unwrap();
+        assert!(selector.add_selection(3).is_err());
+        assert_eq!(report.to_bytes(), [0x00, 0x01, 0x03]);
+
+        report.outputs[0].remove_selection(0);
+        assert_eq!(report.to_bytes(), [0x00, 0x03, 0x00]);
+    }
+
+    /// One byte wide output, with report ID 5 when `numbered`.
+    fn byte_report(numbered: bool) -> Report {
+        let mut descriptor = vec![
+            0x05, 0x08, // Usage Page (LEDs)
+            0x09, 0x4b, // Usage (Generic Indicator)
+            0xa1, 0x01, // Collection (Application)
+        ];
+        if numbered {
+            descriptor.extend([0x85, 0x05]); // Report ID (5)
+        }
+        descriptor.extend([
+            0x09, 0x4b, //   Usage (Generic Indicator)
+            0x15, 0x00, //   Logical Minimum (0)
+            0x26, 0xff, 0x00, //   Logical Maximum (255)
+            0x75, 0x08, //   Report Size (8)
+            0x95, 0x01, //   Report Count (1)
+            0x91, 0x02, //   Output (Data, Variable, Absolute)
+            0xc0, // End Collection
+        ]);
+
+        let mut reports = parse_reports(&descriptor).unwrap();
+        assert_eq!(reports.len(), 1);
+        let mut report = reports.remove(0);
+        report.outputs[0].set_logical(0x2a);
+        report
+    }
+
+    #[test]
+    fn numbered_report_starts_with_its_id() {
+        let report = byte_report(true);
+        assert!(report.is_numbered());
+        assert_eq!(report.id(), 5);
+        assert_eq!(report.outputs[0].bits(), &(0..8));
+        assert_eq!(report.to_bytes(), [0x05, 0x2a]);
+    }
+
+    #[test]
+    fn reads_back_what_it_packs() {
+        let written = byte_report(true);
+        let mut read = byte_report(true);
+        read.outputs[0].set_logical(0);
+        read.read_from(&written.to_bytes()[1..], &Quirks::default());
+        assert_eq!(read.outputs[0].logical_value(), 0x2a);
+    }
+
+    #[test]
+    fn unnumbered_report_has_no_id_byte() {
+        let report = byte_report(false);
+        assert!(!report.is_numbered());
+        // Only the zero hidapi expects, which isn't sent, and the field isn't shifted
+        assert_eq!(report.outputs[0].bits(), &(0..8));
+        assert_eq!(report.to_bytes(), [0x00, 0x2a]);
+    }
 }
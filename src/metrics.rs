@@ -0,0 +1,92 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use crate::HidLightError;
+
+/// A report write as seen by [`WriteMetrics`], after any retries.
+#[derive(Debug)]
+pub struct WriteEvent<'a> {
+    pub vid: u16,
+    pub pid: u16,
+    pub report_id: u8,
+    /// Bytes written, including the report ID.
+    pub len: usize,
+    pub latency: Duration,
+    pub attempts: u32,
+    pub error: Option<&'a HidLightError>,
+}
+
+/// Observes writes, e.g. to export device health from a daemon. Called on the
+/// writing thread, so it should be quick.
+pub trait WriteMetrics: Send + Sync {
+    fn record_write(&self, event: &WriteEvent);
+}
+
+/// Running totals, one per device is usually what's wanted.
+#[derive(Debug, Default)]
+pub struct WriteStats {
+    writes: AtomicU64,
+    failures: AtomicU64,
+    retries: AtomicU64,
+    bytes: AtomicU64,
+    latency_total_us: AtomicU64,
+    latency_max_us: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WriteStatsSnapshot {
+    pub writes: u64,
+    pub failures: u64,
+    pub retries: u64,
+    pub bytes: u64,
+    pub latency_total: Duration,
+    pub latency_max: Duration,
+}
+
+impl WriteStatsSnapshot {
+    pub fn error_rate(&self) -> f64 {
+        if self.writes == 0 {
+            0.0
+        } else {
+            self.failures as f64 / self.writes as f64
+        }
+    }
+
+    pub fn mean_latency(&self) -> Duration {
+        if self.writes == 0 {
+            Duration::ZERO
+        } else {
+            self.latency_total / self.writes as u32
+        }
+    }
+}
+
+impl WriteStats {
+    pub fn snapshot(&self) -> WriteStatsSnapshot {
+        WriteStatsSnapshot {
+            writes: self.writes.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            latency_total: Duration::from_micros(self.latency_total_us.load(Ordering::Relaxed)),
+            latency_max: Duration::from_micros(self.latency_max_us.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl WriteMetrics for WriteStats {
+    fn record_write(&self, event: &WriteEvent) {
+        let latency = event.latency.as_micros() as u64;
+        self.writes.fetch_add(1, Ordering::Relaxed);
+        self.retries
+            .fetch_add(event.attempts.saturating_sub(1) as u64, Ordering::Relaxed);
+        self.latency_total_us.fetch_add(latency, Ordering::Relaxed);
+        self.latency_max_us.fetch_max(latency, Ordering::Relaxed);
+        match event.error {
+            Some(_) => _ = self.failures.fetch_add(1, Ordering::Relaxed),
+            None => _ = self.bytes.fetch_add(event.len as u64, Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! Driving outputs from MIDI notes and controllers, e.g. from a DAW or a rhythm game.
+//!
+//! A note sets its output to its velocity while held, a controller sets its output to
+//! its value, both scaled from 0-127 to the output's range.
+
+use std::sync::{Arc, Mutex, PoisonError};
+
+use midir::{MidiInput, MidiInputConnection};
+
+use crate::{bindings::OutputRef, DeviceHandle, HidLightError, OutputId, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MidiSource {
+    Note(u8),
+    ControlChange(u8),
+}
+
+/// Sends a note or controller to an output, on `channel` (0-15) or any channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MidiMapping {
+    pub channel: Option<u8>,
+    pub source: MidiSource,
+    pub output: OutputRef,
+}
+
+impl MidiMapping {
+    pub fn note(note: u8, output: impl Into<OutputRef>) -> Self {
+        Self {
+            channel: None,
+            source: MidiSource::Note(note),
+            output: output.into(),
+        }
+    }
+
+    pub fn control_change(controller: u8, output: impl Into<OutputRef>) -> Self {
+        Self {
+            channel: None,
+            source: MidiSource::ControlChange(controller),
+            output: output.into(),
+        }
+    }
+
+    pub fn channel(mut self, channel: u8) -> Self {
+        self.channel = Some(channel);
+        self
+    }
+}
+
+/// Mappings resolved against a device, applying raw MIDI messages to it.
+pub struct MidiRouter {
+    handle: DeviceHandle,
+    mappings: Vec<(MidiMapping, OutputId)>,
+}
+
+impl MidiRouter {
+    /// Fails if a mapping names an output the device doesn't have.
+    pub fn new(mut handle: DeviceHandle, mappings: &[MidiMapping]) -> Result<Self> {
+        let mappings = mappings
+            .iter()
+            .map(|x| Ok((x.clone(), x.output.resolve(&mut handle)?)))
+            .collect::<Result<_>>()?;
+        Ok(Self { handle, mappings })
+    }
+
+    /// Applies one MIDI message and flushes the device. Returns whether any mapping
+    /// matched.
+    pub fn handle_message(&mut self, message: &[u8]) -> Result<bool> {
+        let [status, data1, data2, ..] = *message else {
+            return Ok(false);
+        };
+        let channel = status & 0x0f;
+        let (source, value) = match status & 0xf0 {
+            // Note on with velocity 0 is the usual running status note off
+            0x90 => (MidiSource::Note(data1), data2),
+            0x80 => (MidiSource::Note(data1), 0),
+            0xb0 => (MidiSource::ControlChange(data1), data2),
+            _ => return Ok(false),
+        };
+
+        let mut matched = false;
+        for (mapping, id) in &self.mappings {
+            if mapping.source == source
+                && (mapping.channel.is_none() || mapping.channel == Some(channel))
+            {
+                self.handle.set(*id, value.min(127) as f32 / 127.0)?;
+                matched = true;
+            }
+        }
+        if matched {
+            self.handle.flush()?;
+        }
+        Ok(matched)
+    }
+
+    pub fn into_inner(self) -> DeviceHandle {
+        self.handle
+    }
+}
+
+/// Names of the MIDI inputs on the system.
+pub fn input_ports() -> Result<Vec<String>> {
+    let input = MidiInput::new("hidlights").map_err(midi_error)?;
+    Ok(input
+        .ports()
+        .iter()
+        .filter_map(|x| input.port_name(x).ok())
+        .collect())
+}
+
+/// A MIDI input connected to a [`MidiRouter`]. Messages are applied on midir's
+/// thread as they arrive.
+pub struct MidiBridge {
+    connection: MidiInputConnection<()>,
+    router: Arc<Mutex<MidiRouter>>,
+    last_error: Arc<Mutex<Option<HidLightError>>>,
+}
+
+impl MidiBridge {
+    /// Connects the first input whose name contains `port`.
+    pub fn connect(port: &str, router: MidiRouter) -> Result<Self> {
+        let input = MidiInput::new("hidlights").map_err(midi_error)?;
+        let found = input
+            .ports()
+            .into_iter()
+            .find(|x| input.port_name(x).is_ok_and(|x| x.contains(port)))
+            .ok_or(HidLightError::DeviceNotFound)?;
+
+        let router = Arc::new(Mutex::new(router));
+        let last_error = Arc::new(Mutex::new(None));
+        let connection = {
+            let router = router.clone();
+            let last_error = last_error.clone();
+            input
+                .connect(
+                    &found,
+                    "hidlights",
+                    move |_, message, _| {
+                        let result = router
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .handle_message(message);
+                        if let Err(err) = result {
+                            #[cfg(feature = "tracing")]
+                            tracing::warn!(%err, "applying MIDI message failed");
+                            *last_error.lock().unwrap_or_else(PoisonError::into_inner) = Some(err);
+                        }
+                    },
+                    (),
+                )
+                .map_err(midi_error)?
+        };
+
+        Ok(Self {
+            connection,
+            router,
+            last_error,
+        })
+    }
+
+    /// The last error applying a message, cleared by reading it.
+    pub fn take_error(&self) -> Option<HidLightError> {
+        self.last_error
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+    }
+
+    /// Disconnects, giving back the router.
+    pub fn close(self) -> MidiRouter {
+        self.connection.close();
+        match Arc::try_unwrap(self.router) {
+            Ok(router) => router.into_inner().unwrap_or_else(PoisonError::into_inner),
+            Err(_) => unreachable!("the connection held the only other reference"),
+        }
+    }
+}
+
+fn midi_error(err: impl std::fmt::Display) -> HidLightError {
+    HidLightError::Midi(err.to_string())
+}
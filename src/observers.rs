@@ -0,0 +1,100 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use crate::{DeviceHandle, DeviceOutput, OutputId, Report};
+
+/// Identifies a callback added with [`DeviceHandle::on_change`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObserverId(u64);
+
+type Callback = Arc<Mutex<dyn FnMut(&DeviceOutput) + Send>>;
+
+thread_local! {
+    /// Callbacks running on this thread, which changes they make don't call again.
+    static RUNNING: RefCell<Vec<ObserverId>> = const { RefCell::new(vec![]) };
+}
+
+/// Callbacks per output, and what each output was last written as.
+#[derive(Default)]
+pub(crate) struct Observers {
+    next_id: u64,
+    callbacks: Vec<(ObserverId, OutputId, Callback)>,
+    written: HashMap<OutputId, Vec<i32>>,
+}
+
+impl Observers {
+    pub fn is_empty(&self) -> bool {
+        self.callbacks.is_empty()
+    }
+
+    /// The callbacks `report` should call, with the outputs it changed.
+    fn changed(&mut self, report: &Report) -> Vec<(ObserverId, Callback, DeviceOutput)> {
+        let mut calls = vec![];
+        for out in &report.outputs {
+            let id = out.id();
+            if !self.callbacks.iter().any(|x| x.1 == id) {
+                continue;
+            }
+            // Compared as written, so changes too small to reach the device don't count
+            let value: Vec<_> = out.fields().into_iter().map(|x| x.1).collect();
+            if self.written.get(&id) == Some(&value) {
+                continue;
+            }
+            self.written.insert(id, value);
+            for (observer, _, callback) in self.callbacks.iter().filter(|x| x.1 == id) {
+                calls.push((*observer, callback.clone(), out.clone()));
+            }
+        }
+        calls
+    }
+}
+
+impl DeviceHandle {
+    /// Calls `callback` whenever `output` changes through this handle or a clone of
+    /// it: reports written by [`Self::flush`], [`Self::write_report`] or
+    /// [`Self::write_raw_report`], and values read back from the device. The first
+    /// write of the output always calls back.
+    ///
+    /// Callbacks run on the thread making the change, after it's made and with no
+    /// locks held, so they can write through the handle. Changes a callback makes
+    /// to its own output don't call it again.
+    pub fn on_change(
+        &self,
+        output: OutputId,
+        callback: impl FnMut(&DeviceOutput) + Send + 'static,
+    ) -> ObserverId {
+        let mut observers = self.observers();
+        let id = ObserverId(observers.next_id);
+        observers.next_id += 1;
+        observers
+            .callbacks
+            .push((id, output, Arc::new(Mutex::new(callback))));
+        id
+    }
+
+    pub fn remove_observer(&self, id: ObserverId) {
+        self.observers().callbacks.retain(|x| x.0 != id);
+    }
+
+    /// Calls back for every observed output `report` changes.
+    pub(crate) fn notify(&self, report: &Report) {
+        let calls = self.observers().changed(report);
+        for (id, callback, out) in calls {
+            if RUNNING.with_borrow(|x| x.contains(&id)) {
+                continue;
+            }
+            RUNNING.with_borrow_mut(|x| x.push(id));
+            (callback.lock().unwrap_or_else(PoisonError::into_inner))(&out);
+            RUNNING.with_borrow_mut(|x| x.retain(|x| *x != id));
+        }
+    }
+
+    pub(crate) fn observers(&self) -> MutexGuard<'_, Observers> {
+        self.observers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
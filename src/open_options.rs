@@ -0,0 +1,212 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{mpsc, Arc, Mutex, PoisonError},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use crate::{DeviceHandle, DeviceInfo, HidLightError, Result};
+
+/// How [`DeviceInfo::open_with`] opens a device.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpenOptions {
+    exclusive: Option<bool>,
+    non_blocking: bool,
+    read_descriptor: bool,
+    resolve_names: bool,
+}
+
+impl OpenOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Seizes the device so the OS and other apps stop seeing it, some devices
+    /// ignore output reports otherwise. Only supported on macOS, where hidapi
+    /// opens exclusively unless told not to; elsewhere it's ignored.
+    pub fn exclusive(mut self, exclusive: bool) -> Self {
+        self.exclusive = Some(exclusive);
+        self
+    }
+
+    /// Makes reads return immediately when there's no report waiting.
+    pub fn non_blocking(mut self, non_blocking: bool) -> Self {
+        self.non_blocking = non_blocking;
+        self
+    }
+
+    /// Reads and parses the descriptor while opening, so a device with a broken
+    /// one fails here rather than on first use.
+    pub fn read_descriptor(mut self, read_descriptor: bool) -> Self {
+        self.read_descriptor = read_descriptor;
+        self
+    }
+
+    /// Reads the descriptor and every string naming an output while opening, rather
+    /// than when [`DeviceOutput::resolve_name`] asks for them.
+    ///
+    /// [`DeviceOutput::resolve_name`]: crate::DeviceOutput::resolve_name
+    pub fn resolve_names(mut self, resolve_names: bool) -> Self {
+        self.resolve_names = resolve_names;
+        self
+    }
+}
+
+impl DeviceInfo {
+    pub fn open_with(&self, options: &OpenOptions) -> Result<DeviceHandle> {
+        let mut handle = match options.exclusive {
+            #[cfg(target_os = "macos")]
+            Some(exclusive) => {
+                // The mode is global to hidapi, keep everyone else from opening with it
+                let device = self.api.with_exclusive(|api| {
+                    let previous = api.get_open_exclusive();
+                    api.set_open_exclusive(exclusive);
+                    let device = api.open_path(&self.path);
+                    api.set_open_exclusive(previous);
+                    device
+                });
+                self.handle(device.map_err(|x| self.open_error(x))?)
+            }
+            _ => self.handle(
+                self.api
+                    .with(|api| api.open_path(&self.path))
+                    .map_err(|x| self.open_error(x))?,
+            ),
+        };
+
+        if options.non_blocking {
+            handle.hid().set_blocking_mode(false)?;
+        }
+        if options.read_descriptor {
+            handle.state = Some(handle.reports()?);
+        }
+        if options.resolve_names {
+            handle.resolve_names()?;
+        }
+        Ok(handle)
+    }
+
+    /// A udev rule that gives the logged in user access to this device on Linux.
+    pub fn udev_rule(&self) -> String {
+        format!(
+            "KERNEL==\"hidraw*\", ATTRS{{idVendor}}==\"{:04x}\", ATTRS{{idProduct}}==\"{:04x}\", MODE=\"0660\", TAG+=\"uaccess\"",
+            self.vid, self.pid
+        )
+    }
+
+    fn open_error(&self, err: hidapi::HidError) -> HidLightError {
+        if !is_permission_error(&err) {
+            return HidLightError::Open {
+                path: self.path.to_string_lossy().into_owned(),
+                source: err,
+            };
+        }
+
+        let hint = if cfg!(target_os = "linux") {
+            format!(
+                "add a udev rule such as `{}` to /etc/udev/rules.d/70-hidlights.rules and replug the device",
+                self.udev_rule()
+            )
+        } else if cfg!(windows) {
+            "another program or driver has the device open exclusively, Windows also never \
+             shares keyboards and mice"
+                .into()
+        } else if cfg!(target_os = "macos") {
+            "allow Input Monitoring for this app in System Settings, or close apps that seize the device"
+                .into()
+        } else {
+            "check the device node's permissions".into()
+        };
+
+        HidLightError::PermissionDenied {
+            path: self.path.to_string_lossy().into_owned(),
+            hint,
+        }
+    }
+
+    /// Opens on a separate thread, giving up with [`HidLightError::Timeout`] after
+    /// `timeout`. An open that eventually succeeds after that closes the device again.
+    pub fn open_timeout(&self, options: &OpenOptions, timeout: Duration) -> Result<DeviceHandle> {
+        let (tx, rx) = mpsc::channel();
+        let info = self.clone();
+        let options = *options;
+        std::thread::spawn(move || {
+            _ = tx.send(info.open_with(&options));
+        });
+        rx.recv_timeout(timeout)
+            .unwrap_or(Err(HidLightError::Timeout))
+    }
+
+    /// Opens on a separate thread, resolving once it's open. Dropping the future
+    /// cancels the open, the device is closed as soon as the thread gets it.
+    pub fn open_async(&self, options: &OpenOptions) -> OpenFuture {
+        let shared = Arc::new(Mutex::new(OpenState::default()));
+        let info = self.clone();
+        let options = *options;
+        let state = shared.clone();
+        std::thread::spawn(move || {
+            let result = info.open_with(&options);
+            let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+            if !state.cancelled {
+                state.result = Some(result);
+                if let Some(waker) = state.waker.take() {
+                    waker.wake();
+                }
+            }
+        });
+        OpenFuture { state: shared }
+    }
+}
+
+fn is_permission_error(err: &hidapi::HidError) -> bool {
+    match err {
+        hidapi::HidError::IoError { error } => error.kind() == std::io::ErrorKind::PermissionDenied,
+        hidapi::HidError::HidApiError { message } => {
+            let message = message.to_lowercase();
+            ["permission denied", "access denied", "access is denied"]
+                .iter()
+                .any(|x| message.contains(x))
+        }
+        _ => false,
+    }
+}
+
+#[derive(Default)]
+struct OpenState {
+    result: Option<Result<DeviceHandle>>,
+    waker: Option<Waker>,
+    cancelled: bool,
+}
+
+/// A device being opened in the background, see [`DeviceInfo::open_async`].
+pub struct OpenFuture {
+    state: Arc<Mutex<OpenState>>,
+}
+
+impl OpenFuture {
+    pub fn cancel(self) {}
+}
+
+impl Future for OpenFuture {
+    type Output = Result<DeviceHandle>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for OpenFuture {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.cancelled = true;
+        state.result = None;
+    }
+}
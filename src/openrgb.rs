@@ -0,0 +1,152 @@
+//! The [OpenRGB](https://openrgb.org) network SDK protocol, both serving our devices
+//! to OpenRGB aware software and mirroring what OpenRGB shows onto them.
+//!
+//! Every packet starts with a 16 byte header: `ORGB`, then the device index, packet ID
+//! and length of the data that follows, each a little endian u32. Colors are sent as
+//! `r, g, b, 0` bytes.
+
+use std::io::{Read, Write};
+
+use crate::{color::Rgb, HidLightError, Result};
+
+mod client;
+mod server;
+
+pub use client::{mirror, run_mirror, Controller, OpenRgbClient};
+pub use server::{DeviceType, OpenRgbServer};
+
+/// The port OpenRGB's SDK server listens on by default.
+pub const DEFAULT_PORT: u16 = 6742;
+
+/// The newest protocol version we speak, connections use the lower of ours and the
+/// other side's.
+pub const PROTOCOL_VERSION: u32 = 3;
+
+const MAGIC: &[u8; 4] = b"ORGB";
+
+pub(crate) mod packet {
+    pub const REQUEST_CONTROLLER_COUNT: u32 = 0;
+    pub const REQUEST_CONTROLLER_DATA: u32 = 1;
+    pub const REQUEST_PROTOCOL_VERSION: u32 = 40;
+    pub const SET_CLIENT_NAME: u32 = 50;
+    pub const RESIZE_ZONE: u32 = 1000;
+    pub const UPDATE_LEDS: u32 = 1050;
+    pub const UPDATE_ZONE_LEDS: u32 = 1051;
+    pub const UPDATE_SINGLE_LED: u32 = 1052;
+    pub const SET_CUSTOM_MODE: u32 = 1100;
+    pub const UPDATE_MODE: u32 = 1101;
+}
+
+pub(crate) struct Packet {
+    pub device: u32,
+    pub id: u32,
+    pub data: Vec<u8>,
+}
+
+impl Packet {
+    pub fn new(device: u32, id: u32, data: Vec<u8>) -> Self {
+        Self { device, id, data }
+    }
+
+    pub fn read(reader: &mut impl Read) -> Result<Self> {
+        let mut header = [0; 16];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(protocol_error(&"bad packet magic"));
+        }
+        let field = |i: usize| u32::from_le_bytes(header[i..i + 4].try_into().unwrap());
+        let len = field(12) as usize;
+        // Nothing we handle comes close, a bigger length is a broken stream
+        if len > 1 << 24 {
+            return Err(protocol_error(&format_args!("{len} byte packet")));
+        }
+
+        let mut data = vec![0; len];
+        reader.read_exact(&mut data)?;
+        Ok(Self::new(field(4), field(8), data))
+    }
+
+    pub fn write(&self, writer: &mut impl Write) -> Result<()> {
+        let mut bytes = Vec::with_capacity(16 + self.data.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&self.device.to_le_bytes());
+        bytes.extend_from_slice(&self.id.to_le_bytes());
+        bytes.extend_from_slice(&(self.data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.data);
+        writer.write_all(&bytes)?;
+        Ok(())
+    }
+}
+
+/// Builds packet data.
+#[derive(Default)]
+pub(crate) struct Writer(pub Vec<u8>);
+
+impl Writer {
+    pub fn u16(&mut self, value: u16) -> &mut Self {
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    pub fn u32(&mut self, value: u32) -> &mut Self {
+        self.0.extend_from_slice(&value.to_le_bytes());
+        self
+    }
+
+    /// Length prefixed and nul terminated.
+    pub fn string(&mut self, value: &str) -> &mut Self {
+        self.u16(value.len() as u16 + 1);
+        self.0.extend_from_slice(value.as_bytes());
+        self.0.push(0);
+        self
+    }
+
+    pub fn color(&mut self, color: Rgb) -> &mut Self {
+        self.0.extend_from_slice(&[color.r, color.g, color.b, 0]);
+        self
+    }
+}
+
+/// Reads packet data, failing on data that ends early.
+pub(crate) struct Reader<'a>(pub &'a [u8]);
+
+impl Reader<'_> {
+    pub fn bytes(&mut self, len: usize) -> Result<&[u8]> {
+        if self.0.len() < len {
+            return Err(protocol_error(&"packet ends early"));
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    pub fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    pub fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    pub fn string(&mut self) -> Result<String> {
+        let len = self.u16()? as usize;
+        let bytes = self.bytes(len)?;
+        let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+        Ok(String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    pub fn color(&mut self) -> Result<Rgb> {
+        let [r, g, b, _] = self.bytes(4)?.try_into().unwrap();
+        Ok(Rgb::new(r, g, b))
+    }
+
+    /// A u16 count followed by that many colors.
+    pub fn colors(&mut self) -> Result<Vec<Rgb>> {
+        let count = self.u16()?;
+        (0..count).map(|_| self.color()).collect()
+    }
+}
+
+pub(crate) fn protocol_error(err: &dyn std::fmt::Display) -> HidLightError {
+    HidLightError::OpenRgb(err.to_string())
+}
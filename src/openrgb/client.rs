@@ -0,0 +1,177 @@
+use std::{
+    net::{TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use super::{packet, protocol_error, Packet, Reader, Writer, PROTOCOL_VERSION};
+use crate::{color::Rgb, surface::LightSurface, Result, Ticker};
+
+/// What an OpenRGB server says about one of its controllers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Controller {
+    pub name: String,
+    pub vendor: String,
+    pub leds: Vec<String>,
+    /// What each LED is showing.
+    pub colors: Vec<Rgb>,
+}
+
+/// A connection to an OpenRGB SDK server.
+pub struct OpenRgbClient {
+    stream: TcpStream,
+    version: u32,
+}
+
+impl OpenRgbClient {
+    /// Connects and introduces itself as `name`, e.g. to `("127.0.0.1", DEFAULT_PORT)`.
+    pub fn connect(addr: impl ToSocketAddrs, name: &str) -> Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        let mut client = Self { stream, version: 0 };
+
+        let mut data = Writer::default();
+        data.u32(PROTOCOL_VERSION);
+        let reply = client.request(0, packet::REQUEST_PROTOCOL_VERSION, data)?;
+        client.version = Reader(&reply).u32()?.min(PROTOCOL_VERSION);
+
+        let mut data = Writer::default();
+        data.0.extend_from_slice(name.as_bytes());
+        data.0.push(0);
+        Packet::new(0, packet::SET_CLIENT_NAME, data.0).write(&mut client.stream)?;
+        Ok(client)
+    }
+
+    /// The protocol version agreed with the server.
+    pub fn protocol_version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn controller_count(&mut self) -> Result<u32> {
+        let reply = self.request(0, packet::REQUEST_CONTROLLER_COUNT, Writer::default())?;
+        Reader(&reply).u32()
+    }
+
+    pub fn controller(&mut self, index: u32) -> Result<Controller> {
+        let mut data = Writer::default();
+        data.u32(self.version);
+        let reply = self.request(index, packet::REQUEST_CONTROLLER_DATA, data)?;
+        parse_controller(&reply, self.version)
+    }
+
+    /// Finds a controller by name, ignoring case.
+    pub fn find_controller(&mut self, name: &str) -> Result<Option<u32>> {
+        for index in 0..self.controller_count()? {
+            if self.controller(index)?.name.eq_ignore_ascii_case(name) {
+                return Ok(Some(index));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Sends a request and waits for its reply, skipping any notifications.
+    fn request(&mut self, device: u32, id: u32, data: Writer) -> Result<Vec<u8>> {
+        Packet::new(device, id, data.0).write(&mut self.stream)?;
+        loop {
+            let reply = Packet::read(&mut self.stream)?;
+            if reply.id == id {
+                return Ok(reply.data);
+            }
+        }
+    }
+}
+
+fn parse_controller(data: &[u8], version: u32) -> Result<Controller> {
+    let mut data = Reader(data);
+    data.u32()?;
+    data.u32()?;
+    let name = data.string()?;
+    let vendor = if version >= 1 {
+        data.string()?
+    } else {
+        String::new()
+    };
+    // Description, version, serial and location
+    for _ in 0..4 {
+        data.string()?;
+    }
+
+    let modes = data.u16()?;
+    data.u32()?;
+    for _ in 0..modes {
+        data.string()?;
+        // Value, flags, speed range, and brightness range from version 3
+        data.bytes(if version >= 3 { 24 } else { 16 })?;
+        // Color count range, speed, brightness from version 3, direction and color mode
+        data.bytes(if version >= 3 { 24 } else { 20 })?;
+        data.colors()?;
+    }
+
+    let zones = data.u16()?;
+    for _ in 0..zones {
+        data.string()?;
+        // Type and LED count range
+        data.bytes(16)?;
+        let matrix = data.u16()? as usize;
+        data.bytes(matrix)?;
+        if version >= 4 {
+            return Err(protocol_error(&"zone segments aren't supported"));
+        }
+    }
+
+    let leds = (0..data.u16()?)
+        .map(|_| {
+            let name = data.string()?;
+            data.u32()?;
+            Ok(name)
+        })
+        .collect::<Result<_>>()?;
+    let colors = data.colors()?;
+
+    Ok(Controller {
+        name,
+        vendor,
+        leds,
+        colors,
+    })
+}
+
+/// Copies what an OpenRGB controller shows onto a surface. When their LED counts
+/// differ, each lamp takes the LED at the same fraction along the controller.
+pub fn mirror(colors: &[Rgb], surface: &mut dyn LightSurface) -> Result<()> {
+    let len = surface.len();
+    if colors.is_empty() {
+        return Ok(());
+    }
+    for lamp in 0..len {
+        surface.set(lamp, colors[lamp * colors.len() / len]);
+    }
+    surface.present()
+}
+
+/// Polls a controller at `fps` and mirrors it onto `surfaces` until `stop` is set.
+/// OpenRGB doesn't announce color changes, so it has to be polled.
+pub fn run_mirror(
+    client: &mut OpenRgbClient,
+    controller: u32,
+    surfaces: &mut [&mut dyn LightSurface],
+    fps: f32,
+    stop: &Arc<AtomicBool>,
+) -> Result<()> {
+    let mut ticker = Ticker::new(fps);
+    let mut last = vec![];
+    while !stop.load(Ordering::Relaxed) {
+        let colors = client.controller(controller)?.colors;
+        if colors != last {
+            for surface in surfaces.iter_mut() {
+                mirror(&colors, &mut **surface)?;
+            }
+            last = colors;
+        }
+        ticker.tick();
+    }
+    Ok(())
+}
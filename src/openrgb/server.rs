@@ -0,0 +1,231 @@
+use std::{
+    io::ErrorKind,
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use super::{packet, protocol_error, Packet, Reader, Writer, PROTOCOL_VERSION};
+use crate::{surface::LightSurface, HidLightError, Result};
+
+/// How often the server threads check the stop flag.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// What a device shows up as in OpenRGB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceType {
+    LedStrip = 4,
+    Keyboard = 5,
+    Mouse = 6,
+    Gamepad = 10,
+    Light = 11,
+    Speaker = 12,
+    Unknown = 20,
+}
+
+struct Served {
+    name: String,
+    device_type: DeviceType,
+    surface: Box<dyn LightSurface + Send>,
+}
+
+/// Serves surfaces as OpenRGB controllers, each with one zone and a direct mode.
+#[derive(Clone, Default)]
+pub struct OpenRgbServer {
+    devices: Arc<Mutex<Vec<Served>>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl OpenRgbServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a controller, numbered in the order they're added.
+    pub fn add_surface(
+        &self,
+        name: impl Into<String>,
+        device_type: DeviceType,
+        surface: impl LightSurface + Send + 'static,
+    ) {
+        self.devices().push(Served {
+            name: name.into(),
+            device_type,
+            surface: Box::new(surface),
+        });
+    }
+
+    /// Set to stop the server.
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    /// Accepts SDK clients, each handled on its own thread, e.g. on
+    /// `("127.0.0.1", DEFAULT_PORT)`.
+    pub fn serve(&self, addr: impl ToSocketAddrs) -> Result<(SocketAddr, JoinHandle<Result<()>>)> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let local = listener.local_addr()?;
+
+        let server = self.clone();
+        let thread = std::thread::spawn(move || {
+            while !server.stop.load(Ordering::Relaxed) {
+                let stream = match listener.accept() {
+                    Ok((stream, _)) => stream,
+                    Err(err) if matches!(err.kind(), ErrorKind::WouldBlock) => {
+                        std::thread::sleep(POLL_INTERVAL);
+                        continue;
+                    }
+                    Err(err) => return Err(err.into()),
+                };
+                let server = server.clone();
+                std::thread::spawn(move || {
+                    if let Err(_err) = server.handle_client(stream) {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!(%_err, "OpenRGB client dropped");
+                    }
+                });
+            }
+            Ok(())
+        });
+        Ok((local, thread))
+    }
+
+    fn handle_client(&self, mut stream: TcpStream) -> Result<()> {
+        stream.set_nonblocking(false)?;
+        let mut version = 0;
+
+        while !self.stop.load(Ordering::Relaxed) {
+            let request = match Packet::read(&mut stream) {
+                Ok(request) => request,
+                Err(HidLightError::Io(err)) if err.kind() == ErrorKind::UnexpectedEof => {
+                    return Ok(())
+                }
+                Err(err) => return Err(err),
+            };
+            if let Some(response) = self.respond(&request, &mut version)? {
+                response.write(&mut stream)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles a request, returning the reply if it has one.
+    fn respond(&self, request: &Packet, version: &mut u32) -> Result<Option<Packet>> {
+        let mut data = Reader(&request.data);
+        let reply = |data: Writer| Ok(Some(Packet::new(request.device, request.id, data.0)));
+
+        match request.id {
+            packet::REQUEST_PROTOCOL_VERSION => {
+                *version = data.u32().unwrap_or(0).min(PROTOCOL_VERSION);
+                let mut data = Writer::default();
+                data.u32(PROTOCOL_VERSION);
+                reply(data)
+            }
+            packet::REQUEST_CONTROLLER_COUNT => {
+                let mut data = Writer::default();
+                data.u32(self.devices().len() as u32);
+                reply(data)
+            }
+            packet::REQUEST_CONTROLLER_DATA => {
+                let version = data.u32().unwrap_or(0).min(*version);
+                let devices = self.devices();
+                let device = devices
+                    .get(request.device as usize)
+                    .ok_or_else(|| protocol_error(&"no such controller"))?;
+                reply(controller_data(device, version))
+            }
+            packet::UPDATE_LEDS | packet::UPDATE_ZONE_LEDS => {
+                data.u32()?;
+                // There's one zone, covering every LED
+                if request.id == packet::UPDATE_ZONE_LEDS && data.u32()? != 0 {
+                    return Ok(None);
+                }
+                let colors = data.colors()?;
+                self.update(request.device, |surface| {
+                    for (lamp, color) in colors.into_iter().enumerate() {
+                        surface.set(lamp, color);
+                    }
+                })
+            }
+            packet::UPDATE_SINGLE_LED => {
+                let lamp = data.u32()? as usize;
+                let color = data.color()?;
+                self.update(request.device, |surface| surface.set(lamp, color))
+            }
+            // There's only the one mode, and its one zone can't be resized
+            packet::SET_CLIENT_NAME
+            | packet::SET_CUSTOM_MODE
+            | packet::UPDATE_MODE
+            | packet::RESIZE_ZONE => Ok(None),
+            _ => Ok(None),
+        }
+    }
+
+    fn update(&self, device: u32, f: impl FnOnce(&mut dyn LightSurface)) -> Result<Option<Packet>> {
+        let mut devices = self.devices();
+        let served = devices
+            .get_mut(device as usize)
+            .ok_or_else(|| protocol_error(&"no such controller"))?;
+        f(served.surface.as_mut());
+        served.surface.present()?;
+        Ok(None)
+    }
+
+    fn devices(&self) -> MutexGuard<'_, Vec<Served>> {
+        self.devices.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A controller description, in the layout of protocol `version`.
+fn controller_data(device: &Served, version: u32) -> Writer {
+    const MODE_FLAG_HAS_PER_LED_COLOR: u32 = 1 << 5;
+    const MODE_COLORS_PER_LED: u32 = 1;
+    const ZONE_TYPE_LINEAR: u32 = 1;
+
+    let len = device.surface.len();
+    let mut data = Writer::default();
+    data.u32(device.device_type as u32).string(&device.name);
+    if version >= 1 {
+        data.string("hidlights");
+    }
+    data.string("HID device")
+        .string(env!("CARGO_PKG_VERSION"))
+        .string("")
+        .string("HID");
+
+    // One direct mode
+    data.u16(1).u32(0).string("Direct").u32(0);
+    data.u32(MODE_FLAG_HAS_PER_LED_COLOR).u32(0).u32(0);
+    if version >= 3 {
+        data.u32(0).u32(0);
+    }
+    data.u32(0).u32(0).u32(0);
+    if version >= 3 {
+        data.u32(0);
+    }
+    data.u32(0).u32(MODE_COLORS_PER_LED).u16(0);
+
+    // One zone with every LED
+    data.u16(1).string("All").u32(ZONE_TYPE_LINEAR);
+    data.u32(len as u32).u32(len as u32).u32(len as u32).u16(0);
+
+    data.u16(len as u16);
+    for lamp in 0..len {
+        data.string(&format!("LED {}", lamp + 1)).u32(lamp as u32);
+    }
+    data.u16(len as u16);
+    for lamp in 0..len {
+        data.color(device.surface.get(lamp));
+    }
+
+    // The data starts with its own size, counting the size itself
+    let mut sized = Writer::default();
+    sized.u32(data.0.len() as u32 + 4);
+    sized.0.extend_from_slice(&data.0);
+    sized
+}
@@ -0,0 +1,164 @@
+//! Setting outputs over [OSC](https://opensoundcontrol.stanford.edu), as live lighting
+//! tools speak it.
+//!
+//! Devices are added under a name, and a message to `/device/<name>/output/<output>`
+//! sets that output to its first argument, as a fraction of its range like
+//! [`DeviceHandle::set`]. `<output>` is the output's name, ignoring case, or its index.
+//! Floats, integers and booleans are all accepted. Devices are flushed once per packet,
+//! so a bundle lands as one update.
+
+use std::{
+    collections::{HashMap, HashSet},
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use rosc::{OscMessage, OscPacket, OscType};
+
+use crate::{DeviceHandle, HidLightError, Result};
+
+pub struct OscServer {
+    socket: UdpSocket,
+    devices: HashMap<String, DeviceHandle>,
+    stop: Arc<AtomicBool>,
+}
+
+impl OscServer {
+    /// Listens on `addr`, e.g. `"0.0.0.0:9000"`.
+    pub fn bind(addr: impl ToSocketAddrs) -> Result<Self> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_read_timeout(Some(Duration::from_millis(100)))?;
+        Ok(Self {
+            socket,
+            devices: HashMap::new(),
+            stop: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    /// Makes a device addressable as `/device/<name>`.
+    pub fn add_device(&mut self, name: impl Into<String>, handle: DeviceHandle) {
+        self.devices.insert(name.into(), handle);
+    }
+
+    pub fn remove_device(&mut self, name: &str) -> Option<DeviceHandle> {
+        self.devices.remove(name)
+    }
+
+    /// Set to stop [`Self::run`] from another thread.
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    /// Handles packets until the stop flag is set. Packets that can't be applied are
+    /// skipped, only device errors stop the server.
+    pub fn run(&mut self) -> Result<()> {
+        while !self.stop.load(Ordering::Relaxed) {
+            match self.poll() {
+                Err(HidLightError::Osc(_err)) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(err = %_err, "skipped OSC packet");
+                }
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Waits a short while for a packet and applies it.
+    pub fn poll(&mut self) -> Result<()> {
+        let mut buffer = [0; rosc::decoder::MTU];
+        let len = match self.socket.recv(&mut buffer) {
+            Ok(len) => len,
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                return Ok(());
+            }
+            Err(err) => return Err(err.into()),
+        };
+        self.handle_packet(&buffer[..len])
+    }
+
+    /// Applies an encoded OSC packet and flushes the devices it touched.
+    pub fn handle_packet(&mut self, data: &[u8]) -> Result<()> {
+        let (_, packet) = rosc::decoder::decode_udp(data).map_err(|x| osc_error(&x))?;
+        let mut touched = HashSet::new();
+        let result = self.apply(&packet, &mut touched);
+
+        for name in touched {
+            if let Some(handle) = self.devices.get_mut(&name) {
+                handle.flush()?;
+            }
+        }
+        result
+    }
+
+    fn apply(&mut self, packet: &OscPacket, touched: &mut HashSet<String>) -> Result<()> {
+        match packet {
+            OscPacket::Message(message) => self.apply_message(message, touched),
+            OscPacket::Bundle(bundle) => bundle
+                .content
+                .iter()
+                .try_for_each(|x| self.apply(x, touched)),
+        }
+    }
+
+    fn apply_message(&mut self, message: &OscMessage, touched: &mut HashSet<String>) -> Result<()> {
+        let mut parts = message.addr.split('/').skip(1);
+        let (Some("device"), Some(device), Some("output"), Some(output), None) = (
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+        ) else {
+            return Err(osc_error(&format_args!("unknown address {}", message.addr)));
+        };
+
+        let value = match message.args.first() {
+            Some(OscType::Float(x)) => *x,
+            Some(OscType::Double(x)) => *x as f32,
+            Some(OscType::Int(x)) => *x as f32,
+            Some(OscType::Long(x)) => *x as f32,
+            Some(OscType::Bool(x)) => *x as u8 as f32,
+            _ => {
+                return Err(osc_error(&format_args!(
+                    "{} needs a number or boolean",
+                    message.addr
+                )))
+            }
+        };
+
+        let handle = self
+            .devices
+            .get_mut(device)
+            .ok_or_else(|| osc_error(&format_args!("no device named {device}")))?;
+        let id = handle
+            .find_output(output)?
+            .ok_or_else(|| osc_error(&format_args!("{device} has no output {output}")))?;
+
+        handle.set(id, value)?;
+        touched.insert(device.to_owned());
+        Ok(())
+    }
+
+    /// Stops listening, giving back the devices.
+    pub fn into_devices(self) -> HashMap<String, DeviceHandle> {
+        self.devices
+    }
+}
+
+fn osc_error(err: &dyn std::fmt::Display) -> HidLightError {
+    HidLightError::Osc(err.to_string())
+}
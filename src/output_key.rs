@@ -0,0 +1,123 @@
+use std::{fmt, ops::Range, str::FromStr};
+
+use crate::{DeviceOutput, HidLightError, OutputId};
+
+/// Persistable reference to an output, e.g. `out/3/bits24-31/usage:0x08:0x4B`.
+///
+/// Unlike [`OutputId`] it also records the field's size and usage, so a reference
+/// saved against one firmware won't silently resolve to a different lamp on another.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "String", try_from = "String")
+)]
+pub struct OutputKey {
+    pub report_id: u32,
+    pub bits: Range<u32>,
+    pub usage_page: u16,
+    pub usage_id: u16,
+}
+
+impl OutputKey {
+    pub fn id(&self) -> OutputId {
+        OutputId {
+            report_id: self.report_id,
+            bit_offset: self.bits.start,
+        }
+    }
+
+    pub fn matches(&self, output: &DeviceOutput) -> bool {
+        *self == output.key()
+    }
+}
+
+impl DeviceOutput {
+    pub fn key(&self) -> OutputKey {
+        OutputKey {
+            report_id: self.report_id,
+            bits: self.bits.clone(),
+            usage_page: self.usage_page,
+            usage_id: self.usage_id,
+        }
+    }
+}
+
+impl fmt::Display for OutputKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "out/{}/bits{}-{}/usage:0x{:02X}:0x{:02X}",
+            self.report_id,
+            self.bits.start,
+            self.bits.end.saturating_sub(1),
+            self.usage_page,
+            self.usage_id
+        )
+    }
+}
+
+impl FromStr for OutputKey {
+    type Err = HidLightError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('/');
+        let (Some("out"), Some(report_id), Some(bits), Some(usage), None) = (
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+        ) else {
+            return Err(HidLightError::InvalidOutputKey);
+        };
+
+        let report_id = report_id
+            .parse()
+            .map_err(|_| HidLightError::InvalidOutputKey)?;
+
+        let (start, end) = bits
+            .strip_prefix("bits")
+            .and_then(|x| x.split_once('-'))
+            .ok_or(HidLightError::InvalidOutputKey)?;
+        let start: u32 = start.parse().map_err(|_| HidLightError::InvalidOutputKey)?;
+        let end: u32 = end.parse().map_err(|_| HidLightError::InvalidOutputKey)?;
+        if end < start {
+            return Err(HidLightError::InvalidOutputKey);
+        }
+
+        let (page, id) = usage
+            .strip_prefix("usage:")
+            .and_then(|x| x.split_once(':'))
+            .ok_or(HidLightError::InvalidOutputKey)?;
+
+        Ok(Self {
+            report_id,
+            bits: start..end + 1,
+            usage_page: parse_hex(page)?,
+            usage_id: parse_hex(id)?,
+        })
+    }
+}
+
+impl From<OutputKey> for String {
+    fn from(key: OutputKey) -> Self {
+        key.to_string()
+    }
+}
+
+impl TryFrom<String> for OutputKey {
+    type Error = HidLightError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+fn parse_hex(s: &str) -> Result<u16, HidLightError> {
+    let s = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .ok_or(HidLightError::InvalidOutputKey)?;
+    u16::from_str_radix(s, 16).map_err(|_| HidLightError::InvalidOutputKey)
+}
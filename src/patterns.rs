@@ -0,0 +1,141 @@
+//! Colors for every lamp of a surface computed from where the lamps are.
+
+use crate::{
+    color::Rgb,
+    surface::{normalized_positions, LightSurface},
+};
+
+/// A color for each point of a surface. Points are fractions of the surface's bounds,
+/// so `[0.5, 0.5, 0.5]` is its middle.
+pub trait Pattern {
+    fn color_at(&self, point: [f32; 3]) -> Rgb;
+
+    /// The color of every lamp of `surface`, in lamp order, see
+    /// [`normalized_positions`].
+    fn colors(&self, surface: &dyn LightSurface) -> Vec<Rgb> {
+        normalized_positions(surface)
+            .into_iter()
+            .map(|x| self.color_at(x))
+            .collect()
+    }
+
+    /// Sets every lamp of `surface` to the pattern, ready for
+    /// [`LightSurface::present`].
+    fn fill(&self, surface: &mut dyn LightSurface) {
+        for (lamp, color) in self.colors(surface).into_iter().enumerate() {
+            surface.set(lamp, color);
+        }
+    }
+}
+
+/// Color stops from 0 to 1, colors between stops are interpolated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    stops: Vec<(f32, Rgb)>,
+}
+
+impl Gradient {
+    pub fn new(stops: impl IntoIterator<Item = (f32, Rgb)>) -> Self {
+        let mut stops: Vec<_> = stops.into_iter().collect();
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { stops }
+    }
+
+    /// Evenly spaced stops.
+    pub fn even(colors: &[Rgb]) -> Self {
+        let last = colors.len().saturating_sub(1).max(1) as f32;
+        Self::new(
+            colors
+                .iter()
+                .enumerate()
+                .map(|(i, x)| (i as f32 / last, *x)),
+        )
+    }
+
+    pub fn sample(&self, t: f32) -> Rgb {
+        let Some(first) = self.stops.first() else {
+            return Rgb::BLACK;
+        };
+        if t <= first.0 {
+            return first.1;
+        }
+        for pair in self.stops.windows(2) {
+            let ((a, from), (b, to)) = (pair[0], pair[1]);
+            if t <= b {
+                let span = b - a;
+                return if span > 0.0 {
+                    from.lerp(to, (t - a) / span)
+                } else {
+                    to
+                };
+            }
+        }
+        self.stops[self.stops.len() - 1].1
+    }
+}
+
+/// A gradient across the surface at an angle, 0 running left to right and a quarter
+/// turn running top to bottom.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinearGradient {
+    pub gradient: Gradient,
+    /// Radians.
+    pub angle: f32,
+}
+
+impl Pattern for LinearGradient {
+    fn color_at(&self, [x, y, _]: [f32; 3]) -> Rgb {
+        let (sin, cos) = self.angle.sin_cos();
+        // Project onto the direction, then rescale so the corners reach 0 and 1
+        let extent = sin.abs() + cos.abs();
+        let t = ((x - 0.5) * cos + (y - 0.5) * sin) / extent + 0.5;
+        self.gradient.sample(t)
+    }
+}
+
+/// Rings around a center, moving outwards as `phase` increases. Animating `phase`
+/// from 0 to 1 moves the rings out by one wavelength.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RadialWave {
+    pub gradient: Gradient,
+    pub center: [f32; 2],
+    /// Distance between rings, as a fraction of the surface.
+    pub wavelength: f32,
+    pub phase: f32,
+}
+
+impl Pattern for RadialWave {
+    fn color_at(&self, [x, y, _]: [f32; 3]) -> Rgb {
+        let distance = (x - self.center[0]).hypot(y - self.center[1]);
+        let t = (distance / self.wavelength.max(f32::EPSILON) - self.phase).rem_euclid(1.0);
+        // Out and back through the gradient so rings have no hard edge
+        self.gradient.sample(1.0 - (2.0 * t - 1.0).abs())
+    }
+}
+
+/// Colors repeating in bands along the x axis.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stripes {
+    pub colors: Vec<Rgb>,
+    /// Width of each band, as a fraction of the surface.
+    pub width: f32,
+    /// Shifts the bands along, in bands.
+    pub offset: f32,
+}
+
+impl Pattern for Stripes {
+    fn color_at(&self, [x, _, _]: [f32; 3]) -> Rgb {
+        if self.colors.is_empty() {
+            return Rgb::BLACK;
+        }
+        let band = (x / self.width.max(f32::EPSILON) + self.offset).floor() as i64;
+        self.colors[band.rem_euclid(self.colors.len() as i64) as usize]
+    }
+}
+
+/// A single color everywhere.
+impl Pattern for Rgb {
+    fn color_at(&self, _: [f32; 3]) -> Rgb {
+        *self
+    }
+}
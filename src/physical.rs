@@ -0,0 +1,126 @@
+use std::ffi::CStr;
+
+use crate::{DeviceInfo, HidLights};
+
+/// A piece of hardware and every HID interface it exposes.
+pub struct PhysicalDevice {
+    pub vid: u16,
+    pub pid: u16,
+    pub name: Option<String>,
+    pub manufacturer: Option<String>,
+    pub serial: Option<String>,
+    pub interfaces: Vec<DeviceInfo>,
+}
+
+impl HidLights {
+    /// Devices grouped by the hardware they belong to, in enumeration order.
+    pub fn physical_devices(&self) -> Vec<PhysicalDevice> {
+        let mut groups: Vec<(String, PhysicalDevice)> = vec![];
+
+        for info in self.devices() {
+            let key = container_key(&info);
+            match groups.iter_mut().find(|(k, _)| *k == key) {
+                Some((_, group)) => {
+                    group.name = group.name.take().or_else(|| info.name.clone());
+                    group.manufacturer = group
+                        .manufacturer
+                        .take()
+                        .or_else(|| info.manufacturer.clone());
+                    group.interfaces.push(info);
+                }
+                None => groups.push((
+                    key,
+                    PhysicalDevice {
+                        vid: info.vid,
+                        pid: info.pid,
+                        name: info.name.clone(),
+                        manufacturer: info.manufacturer.clone(),
+                        serial: info.serial.clone(),
+                        interfaces: vec![info],
+                    },
+                )),
+            }
+        }
+
+        groups.into_iter().map(|(_, x)| x).collect()
+    }
+}
+
+fn container_key(info: &DeviceInfo) -> String {
+    if let Some(key) = location_key(info.path()) {
+        return key;
+    }
+    if let Some(serial) = info.serial.as_deref().filter(|x| !x.is_empty()) {
+        return format!("{:04x}:{:04x}:{serial}", info.vid, info.pid);
+    }
+
+    // Identical devices without a serial can't be told apart by their IDs, so they're
+    // only grouped as far as their paths show they're the same
+    let path = info.path().to_string_lossy();
+    if cfg!(windows) {
+        windows_interface_key(&path)
+    } else {
+        path.into_owned()
+    }
+}
+
+/// A Windows HID path without what tells the top-level collections of an interface
+/// apart, e.g. `\\?\hid#vid_046d&pid_c52b&mi_02&col01#8&2f1a3c4&0&0000#{guid}` becomes
+/// `\\?\hid#vid_046d&pid_c52b&mi_02#8&2f1a3c4&0`.
+fn windows_interface_key(path: &str) -> String {
+    let path = path.to_lowercase();
+    let parts: Vec<_> = path.split('#').collect();
+    let [prefix, hardware, instance, ..] = parts[..] else {
+        return path.clone();
+    };
+    let hardware: Vec<_> = hardware
+        .split('&')
+        .filter(|x| !x.starts_with("col"))
+        .collect();
+    let instance = instance.rsplit_once('&').map_or(instance, |x| x.0);
+    format!("{prefix}#{}#{instance}", hardware.join("&"))
+}
+
+/// Where the device is attached, when the platform exposes it without opening the device.
+pub(crate) fn location_key(path: &CStr) -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        sysfs_usb_device(path)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        _ = path;
+        None
+    }
+}
+
+/// Resolves a hidraw node to the sysfs path of the USB device it hangs off.
+#[cfg(target_os = "linux")]
+fn sysfs_usb_device(path: &CStr) -> Option<String> {
+    let path = path.to_str().ok()?;
+    let node = path.strip_prefix("/dev/")?;
+    let hid_device = std::fs::canonicalize(format!("/sys/class/hidraw/{node}/device")).ok()?;
+
+    // hid device -> usb interface -> usb device
+    let usb_device = hid_device.parent()?.parent()?;
+    Some(usb_device.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collections_of_an_interface_share_a_key() {
+        let guid = "{4d1e55b2-f16f-11cf-88cb-001111000030}";
+        let first = format!(r"\\?\HID#VID_046D&PID_C52B&MI_02&Col01#8&2f1a3c4&0&0000#{guid}");
+        let second = format!(r"\\?\HID#VID_046D&PID_C52B&MI_02&Col02#8&2f1a3c4&0&0001#{guid}");
+        let other = format!(r"\\?\HID#VID_046D&PID_C52B&MI_02&Col01#8&99aa00&0&0000#{guid}");
+        assert_eq!(
+            windows_interface_key(&first),
+            windows_interface_key(&second)
+        );
+        assert_ne!(windows_interface_key(&first), windows_interface_key(&other));
+    }
+}
@@ -0,0 +1,287 @@
+//! Effects shipped as shared libraries.
+//!
+//! A plugin is a `cdylib` implementing [`EffectPlugin`] and exporting it with
+//! [`export_effect_plugin!`]. The interface between host and plugin is plain C, so
+//! plugins don't need to be built with the same compiler as the host:
+//!
+//! ```ignore
+//! struct Rainbow;
+//!
+//! impl hidlights::plugin::EffectPlugin for Rainbow {
+//!     const NAME: &'static std::ffi::CStr = c"rainbow";
+//!
+//!     fn new() -> Self {
+//!         Rainbow
+//!     }
+//!
+//!     fn render(&mut self, t: f64, positions: &[[f32; 3]], colors: &mut [[u8; 3]]) {
+//!         // ...
+//!     }
+//! }
+//!
+//! hidlights::export_effect_plugin!(Rainbow);
+//! ```
+//!
+//! With the `plugins` feature, [`PluginLoader`] finds plugins in a directory and runs
+//! them against any [`crate::surface::LightSurface`].
+
+use std::ffi::{c_char, c_void, CStr};
+
+/// Bumped whenever [`PluginVTable`] changes, plugins built against another version
+/// are refused.
+pub const ABI_VERSION: u32 = 1;
+
+/// Name of the function every plugin exports, returning its [`PluginVTable`].
+pub const ENTRY_POINT: &str = "hidlights_effect_plugin";
+
+/// What a plugin hands the host.
+#[repr(C)]
+pub struct PluginVTable {
+    pub abi_version: u32,
+    /// Nul terminated, valid as long as the library is loaded.
+    pub name: *const c_char,
+    /// Creates an instance, passed to the other functions.
+    pub create: unsafe extern "C" fn() -> *mut c_void,
+    pub destroy: unsafe extern "C" fn(instance: *mut c_void),
+    /// Fills `count` RGB colors for lamps at `count` positions, `t` seconds in.
+    pub render: unsafe extern "C" fn(
+        instance: *mut c_void,
+        t: f64,
+        positions: *const [f32; 3],
+        colors: *mut [u8; 3],
+        count: usize,
+    ),
+}
+
+// The vtable only holds function pointers and a pointer to a static string
+unsafe impl Sync for PluginVTable {}
+
+/// The Rust side of a plugin, see the module docs.
+pub trait EffectPlugin: Sized {
+    const NAME: &'static CStr;
+
+    fn new() -> Self;
+
+    /// `positions` are fractions of the surface's bounds, see
+    /// [`crate::surface::normalized_positions`]. `colors` holds the last frame.
+    fn render(&mut self, t: f64, positions: &[[f32; 3]], colors: &mut [[u8; 3]]);
+}
+
+#[doc(hidden)]
+pub mod ffi {
+    use super::*;
+
+    pub unsafe extern "C" fn create<P: EffectPlugin>() -> *mut c_void {
+        Box::into_raw(Box::new(P::new())).cast()
+    }
+
+    pub unsafe extern "C" fn destroy<P: EffectPlugin>(instance: *mut c_void) {
+        drop(Box::from_raw(instance.cast::<P>()));
+    }
+
+    pub unsafe extern "C" fn render<P: EffectPlugin>(
+        instance: *mut c_void,
+        t: f64,
+        positions: *const [f32; 3],
+        colors: *mut [u8; 3],
+        count: usize,
+    ) {
+        let plugin = &mut *instance.cast::<P>();
+        let positions = std::slice::from_raw_parts(positions, count);
+        let colors = std::slice::from_raw_parts_mut(colors, count);
+        // Unwinding across the C boundary is undefined, a panicking plugin leaves the
+        // frame as it was
+        _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            plugin.render(t, positions, colors)
+        }));
+    }
+
+    pub const fn vtable<P: EffectPlugin>() -> PluginVTable {
+        PluginVTable {
+            abi_version: ABI_VERSION,
+            name: P::NAME.as_ptr(),
+            create: create::<P>,
+            destroy: destroy::<P>,
+            render: render::<P>,
+        }
+    }
+}
+
+/// Exports an [`EffectPlugin`] from a `cdylib`.
+#[macro_export]
+macro_rules! export_effect_plugin {
+    ($plugin:ty) => {
+        #[no_mangle]
+        pub extern "C" fn hidlights_effect_plugin() -> *const $crate::plugin::PluginVTable {
+            static VTABLE: $crate::plugin::PluginVTable = $crate::plugin::ffi::vtable::<$plugin>();
+            &VTABLE
+        }
+    };
+}
+
+#[cfg(feature = "plugins")]
+pub use loader::{Plugin, PluginEffect, PluginLoader};
+
+#[cfg(feature = "plugins")]
+mod loader {
+    use std::{
+        ffi::CStr,
+        path::{Path, PathBuf},
+        sync::Arc,
+        time::Duration,
+    };
+
+    use libloading::Library;
+
+    use super::{PluginVTable, ABI_VERSION, ENTRY_POINT};
+    use crate::{
+        color::Rgb,
+        surface::{normalized_positions, LightSurface},
+        HidLightError, Result,
+    };
+
+    /// A loaded plugin library.
+    #[derive(Clone)]
+    pub struct Plugin {
+        path: PathBuf,
+        name: String,
+        vtable: &'static PluginVTable,
+        _library: Arc<Library>,
+    }
+
+    impl Plugin {
+        /// Loads a plugin library.
+        ///
+        /// # Safety
+        ///
+        /// Loading a library runs its initialisers and trusts its exports, the library
+        /// has to be a hidlights plugin built for this [`ABI_VERSION`].
+        pub unsafe fn load(path: impl AsRef<Path>) -> Result<Self> {
+            let path = path.as_ref();
+            let error = |err: &dyn std::fmt::Display| {
+                HidLightError::Plugin(format!("{}: {err}", path.display()))
+            };
+
+            let library = Library::new(path).map_err(|x| error(&x))?;
+            let vtable: &'static PluginVTable = {
+                let entry = library
+                    .get::<unsafe extern "C" fn() -> *const PluginVTable>(ENTRY_POINT.as_bytes())
+                    .map_err(|x| error(&x))?;
+                // Lives as long as the library, which the plugin keeps loaded
+                entry().as_ref().ok_or_else(|| error(&"no plugin table"))?
+            };
+            if vtable.abi_version != ABI_VERSION {
+                return Err(error(&format_args!(
+                    "built for plugin ABI {}, expected {ABI_VERSION}",
+                    vtable.abi_version
+                )));
+            }
+
+            Ok(Self {
+                path: path.to_owned(),
+                name: CStr::from_ptr(vtable.name).to_string_lossy().into_owned(),
+                vtable,
+                _library: Arc::new(library),
+            })
+        }
+
+        pub fn name(&self) -> &str {
+            &self.name
+        }
+
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+
+        pub fn instantiate(&self) -> PluginEffect {
+            PluginEffect {
+                instance: unsafe { (self.vtable.create)() },
+                plugin: self.clone(),
+                colors: vec![],
+            }
+        }
+    }
+
+    /// A running instance of a plugin.
+    pub struct PluginEffect {
+        instance: *mut std::ffi::c_void,
+        plugin: Plugin,
+        colors: Vec<[u8; 3]>,
+    }
+
+    // Instances are only ever used through &mut, from one thread at a time
+    unsafe impl Send for PluginEffect {}
+
+    impl PluginEffect {
+        pub fn plugin(&self) -> &Plugin {
+            &self.plugin
+        }
+
+        /// Renders the frame `t` into the effect and presents `surface`.
+        pub fn render(&mut self, t: Duration, surface: &mut dyn LightSurface) -> Result<()> {
+            let positions = normalized_positions(surface);
+            self.colors.resize(positions.len(), [0; 3]);
+            unsafe {
+                (self.plugin.vtable.render)(
+                    self.instance,
+                    t.as_secs_f64(),
+                    positions.as_ptr(),
+                    self.colors.as_mut_ptr(),
+                    positions.len(),
+                );
+            }
+            for (lamp, color) in self.colors.iter().enumerate() {
+                surface.set(lamp, Rgb::from(*color));
+            }
+            surface.present()
+        }
+    }
+
+    impl Drop for PluginEffect {
+        fn drop(&mut self) {
+            unsafe { (self.plugin.vtable.destroy)(self.instance) }
+        }
+    }
+
+    /// Finds plugins in a set of directories.
+    #[derive(Default)]
+    pub struct PluginLoader {
+        dirs: Vec<PathBuf>,
+    }
+
+    impl PluginLoader {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn dir(mut self, dir: impl Into<PathBuf>) -> Self {
+            self.dirs.push(dir.into());
+            self
+        }
+
+        /// Loads every shared library in the directories. Ones that fail to load are
+        /// returned with their error rather than stopping the rest.
+        ///
+        /// # Safety
+        ///
+        /// See [`Plugin::load`], every library in the directories is loaded.
+        pub unsafe fn discover(&self) -> Vec<Result<Plugin>> {
+            let mut plugins = vec![];
+            for dir in &self.dirs {
+                let Ok(entries) = std::fs::read_dir(dir) else {
+                    continue;
+                };
+                let mut paths: Vec<_> = entries
+                    .filter_map(|x| x.ok().map(|x| x.path()))
+                    .filter(|x| {
+                        x.extension()
+                            .is_some_and(|x| x == std::env::consts::DLL_EXTENSION)
+                    })
+                    .collect();
+                paths.sort();
+                plugins.extend(paths.into_iter().map(|x| Plugin::load(x)));
+            }
+            plugins
+        }
+    }
+}
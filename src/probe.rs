@@ -0,0 +1,93 @@
+use std::{
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use hidparser::ReportField;
+
+use crate::{
+    drivers, indicators::LED_PAGE, lamp_array::LIGHTING_PAGE, DeviceInfo, HidLightError, HidLights,
+    Result,
+};
+
+/// What a device can light up, as far as its descriptor tells.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    pub output_reports: usize,
+    /// Outputs in output reports, as [`crate::DeviceHandle::reports`] gives them.
+    pub output_fields: usize,
+    /// Writable LED or Lighting and Illumination fields in feature reports.
+    pub feature_lamp_fields: usize,
+    pub lamp_array: bool,
+    pub has_driver: bool,
+}
+
+impl Capabilities {
+    pub fn is_controllable(&self) -> bool {
+        self.output_fields > 0 || self.feature_lamp_fields > 0 || self.has_driver
+    }
+}
+
+impl DeviceInfo {
+    /// Briefly opens the device and inspects its descriptor, without resolving any names.
+    pub fn probe(&self) -> Result<Capabilities> {
+        let handle = self.open()?;
+        let descriptor = handle.report_descriptor()?;
+        let output_fields = handle.reports()?.iter().map(|x| x.outputs.len()).sum();
+
+        let is_lamp_page = |page: u16| page == LED_PAGE || page == LIGHTING_PAGE;
+        let feature_fields = descriptor.features.iter().flat_map(|x| &x.fields);
+        let mut feature_lamp_fields = 0;
+        let mut lamp_array = false;
+        for field in feature_fields {
+            if let ReportField::Variable(x) = field {
+                if is_lamp_page(x.usage.page()) && !x.attributes.constant {
+                    feature_lamp_fields += 1;
+                }
+                lamp_array |= x.usage.page() == LIGHTING_PAGE;
+            }
+        }
+
+        Ok(Capabilities {
+            output_reports: descriptor.output_reports.len(),
+            output_fields,
+            feature_lamp_fields,
+            lamp_array,
+            has_driver: drivers::is_supported(self),
+        })
+    }
+}
+
+impl HidLights {
+    /// Probes every device concurrently. Devices that haven't answered within
+    /// `timeout` get [`HidLightError::Timeout`], their probe is left to finish in
+    /// the background.
+    pub fn probe_all(&self, timeout: Duration) -> Vec<(DeviceInfo, Result<Capabilities>)> {
+        let devices = self.devices();
+        let (tx, rx) = mpsc::channel();
+
+        for (i, info) in devices.iter().enumerate() {
+            let info = info.clone();
+            let tx = tx.clone();
+            std::thread::spawn(move || {
+                _ = tx.send((i, info.probe()));
+            });
+        }
+        drop(tx);
+
+        let mut results: Vec<Option<Result<Capabilities>>> = devices.iter().map(|_| None).collect();
+        let deadline = Instant::now() + timeout;
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            match rx.recv_timeout(remaining) {
+                Ok((i, result)) => results[i] = Some(result),
+                Err(_) => break,
+            }
+        }
+
+        devices
+            .into_iter()
+            .zip(results)
+            .map(|(info, result)| (info, result.unwrap_or(Err(HidLightError::Timeout))))
+            .collect()
+    }
+}
@@ -0,0 +1,133 @@
+//! Output names for controllers whose descriptors only provide generic ones.
+
+use crate::{DeviceInfo, Report};
+
+const SDVX_LAMPS: &[&str] = &[
+    "BT-A LED",
+    "BT-B LED",
+    "BT-C LED",
+    "BT-D LED",
+    "FX-L LED",
+    "FX-R LED",
+    "START LED",
+];
+
+const IIDX_LAMPS: &[&str] = &[
+    "Key 1 LED",
+    "Key 2 LED",
+    "Key 3 LED",
+    "Key 4 LED",
+    "Key 5 LED",
+    "Key 6 LED",
+    "Key 7 LED",
+    "E1 LED",
+    "E2 LED",
+    "E3 LED",
+    "E4 LED",
+];
+
+const POPN_LAMPS: &[&str] = &[
+    "Button 1 LED",
+    "Button 2 LED",
+    "Button 3 LED",
+    "Button 4 LED",
+    "Button 5 LED",
+    "Button 6 LED",
+    "Button 7 LED",
+    "Button 8 LED",
+    "Button 9 LED",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceMatch {
+    Ids {
+        vid: u16,
+        pid: u16,
+    },
+    /// Case insensitive match on the product string, for hobbyist firmware
+    /// that is commonly flashed with arbitrary IDs.
+    Product(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Profile {
+    pub name: &'static str,
+    pub device: DeviceMatch,
+    /// Output names in descriptor order, outputs past the end keep their own names.
+    pub outputs: &'static [&'static str],
+}
+
+pub const PROFILES: &[Profile] = &[
+    Profile {
+        name: "SOUND VOLTEX Entry Model",
+        device: DeviceMatch::Ids {
+            vid: 0x1ccf,
+            pid: 0x1014,
+        },
+        outputs: SDVX_LAMPS,
+    },
+    Profile {
+        name: "beatmania IIDX Entry Model",
+        device: DeviceMatch::Ids {
+            vid: 0x1ccf,
+            pid: 0x8048,
+        },
+        outputs: IIDX_LAMPS,
+    },
+    Profile {
+        name: "Pocket Voltex",
+        device: DeviceMatch::Product("pocket voltex"),
+        outputs: SDVX_LAMPS,
+    },
+    Profile {
+        name: "Faucetwo",
+        device: DeviceMatch::Product("faucetwo"),
+        outputs: SDVX_LAMPS,
+    },
+    Profile {
+        name: "DJ DAO",
+        device: DeviceMatch::Product("dj dao"),
+        outputs: IIDX_LAMPS,
+    },
+    Profile {
+        name: "Phoenixwan",
+        device: DeviceMatch::Product("phoenixwan"),
+        outputs: IIDX_LAMPS,
+    },
+    Profile {
+        name: "pop'n controller",
+        device: DeviceMatch::Product("pop'n"),
+        outputs: POPN_LAMPS,
+    },
+];
+
+impl DeviceMatch {
+    pub fn matches(&self, vid: u16, pid: u16, product: Option<&str>) -> bool {
+        match *self {
+            DeviceMatch::Ids { vid: v, pid: p } => v == vid && p == pid,
+            DeviceMatch::Product(name) => product.is_some_and(|x| x.to_lowercase().contains(name)),
+        }
+    }
+}
+
+impl Profile {
+    pub fn matches(&self, vid: u16, pid: u16, product: Option<&str>) -> bool {
+        self.device.matches(vid, pid, product)
+    }
+
+    pub fn apply(&self, reports: &mut [Report]) {
+        let outputs = reports.iter_mut().flat_map(|x| x.outputs.iter_mut());
+        for (output, name) in outputs.zip(self.outputs) {
+            output.name = Some(name.to_string());
+            output.strings.name = None;
+        }
+    }
+}
+
+pub fn find(vid: u16, pid: u16, product: Option<&str>) -> Option<&'static Profile> {
+    PROFILES.iter().find(|x| x.matches(vid, pid, product))
+}
+
+pub fn for_device(info: &DeviceInfo) -> Option<&'static Profile> {
+    find(info.vid, info.pid, info.name.as_deref())
+}
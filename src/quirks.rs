@@ -0,0 +1,132 @@
+//! Overrides for devices whose reports don't follow the packing the descriptor implies.
+
+use std::ops::Range;
+
+use bitvec::{
+    order::{Lsb0, Msb0},
+    view::BitView,
+};
+
+use crate::{profiles::DeviceMatch, DeviceInfo, WritePath};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BitOrder {
+    /// Bit 0 of a report is the most significant bit of its first byte, fields are
+    /// written most significant bit first.
+    #[default]
+    MsbFirst,
+    /// Bit 0 is the least significant bit of the first byte and fields are written
+    /// least significant bit first, as the HID spec lays them out.
+    LsbFirst,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Quirks {
+    /// How output reports are packed.
+    pub bit_order: BitOrder,
+    /// How input reports are packed, when it isn't the HID spec's
+    /// [`BitOrder::LsbFirst`].
+    pub input_bit_order: Option<BitOrder>,
+    /// Reverse the bytes of multi-byte fields after packing.
+    pub swap_bytes: bool,
+    /// Largest write the device accepts, including the report ID. Longer reports are
+    /// split into several writes of this size, each prefixed with the report ID.
+    ///
+    /// The hidraw and libusb backends send a short write as is, but the Windows
+    /// backend pads every write to the declared report length, so there this only
+    /// helps devices that declare a report of the chunk size.
+    pub max_write_size: Option<usize>,
+    /// Most reports per second the device keeps up with, frame loops are slowed to it.
+    pub max_report_rate: Option<u32>,
+    /// Lamp under each key as `(keyboard usage, lamp ID)`, for LampArrays that don't
+    /// report input bindings or get them wrong.
+    pub key_map: Option<&'static [(u16, u16)]>,
+    /// How reports are written, when the device needs something other than
+    /// [`WritePath::Interrupt`].
+    pub write_path: Option<WritePath>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceQuirks {
+    pub device: DeviceMatch,
+    pub quirks: Quirks,
+}
+
+/// Known devices that need something other than the default packing.
+pub const QUIRKS: &[DeviceQuirks] = &[];
+
+pub fn find(vid: u16, pid: u16, product: Option<&str>) -> Quirks {
+    QUIRKS
+        .iter()
+        .find(|x| x.device.matches(vid, pid, product))
+        .map(|x| x.quirks)
+        .unwrap_or_default()
+}
+
+pub fn for_device(info: &DeviceInfo) -> Quirks {
+    find(info.vid, info.pid, info.name.as_deref())
+}
+
+impl Quirks {
+    /// Writes `value` into `bits` of the report data.
+    pub(crate) fn pack(&self, data: &mut [u8], bits: Range<u32>, value: u32) {
+        let width = bits.len();
+        let value = if self.swap_bytes && width % 8 == 0 && width > 8 {
+            let bytes = (width / 8).min(4);
+            let mut swapped = 0;
+            for i in 0..bytes {
+                swapped |= ((value >> (i * 8)) & 0xff) << ((bytes - 1 - i) * 8);
+            }
+            swapped
+        } else {
+            value
+        };
+        let bit = |src: usize| src < 32 && (value >> src) & 1 != 0;
+
+        match self.bit_order {
+            BitOrder::MsbFirst => {
+                let view = data.view_bits_mut::<Msb0>();
+                for (src, dst) in bits.rev().enumerate() {
+                    view.set(dst as _, bit(src));
+                }
+            }
+            BitOrder::LsbFirst => {
+                let view = data.view_bits_mut::<Lsb0>();
+                for (src, dst) in bits.enumerate() {
+                    view.set(dst as _, bit(src));
+                }
+            }
+        }
+    }
+
+    /// Reads the value in `bits` of the report data, the reverse of [`Self::pack`].
+    pub(crate) fn unpack(&self, data: &[u8], bits: Range<u32>) -> u32 {
+        let width = bits.len();
+        let mut value = 0u32;
+        match self.bit_order {
+            BitOrder::MsbFirst => {
+                let view = data.view_bits::<Msb0>();
+                for (dst, src) in bits.rev().enumerate().take(32) {
+                    value |= (view.get(src as usize).is_some_and(|x| *x) as u32) << dst;
+                }
+            }
+            BitOrder::LsbFirst => {
+                let view = data.view_bits::<Lsb0>();
+                for (dst, src) in bits.enumerate().take(32) {
+                    value |= (view.get(src as usize).is_some_and(|x| *x) as u32) << dst;
+                }
+            }
+        }
+
+        if self.swap_bytes && width % 8 == 0 && width > 8 {
+            let bytes = (width / 8).min(4);
+            let mut swapped = 0;
+            for i in 0..bytes {
+                swapped |= ((value >> (i * 8)) & 0xff) << ((bytes - 1 - i) * 8);
+            }
+            swapped
+        } else {
+            value
+        }
+    }
+}
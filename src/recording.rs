@@ -0,0 +1,121 @@
+//! Logging written reports to a file and playing them back, for chasing device quirks
+//! and checking lighting sequences don't regress.
+//!
+//! A recording is plain text, one write per line: microseconds since recording
+//! started, then the bytes written with the report ID first, in hex.
+
+use std::{
+    fmt::Write as _,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use crate::{descriptor, DeviceHandle, HidLightError, Result};
+
+/// Receives every report a handle writes, see [`DeviceHandle::set_recorder`].
+pub struct Recorder {
+    out: Mutex<Box<dyn Write + Send>>,
+    start: Instant,
+}
+
+impl Recorder {
+    pub fn new(out: impl Write + Send + 'static) -> Self {
+        Self {
+            out: Mutex::new(Box::new(out)),
+            start: Instant::now(),
+        }
+    }
+
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+
+    pub fn record(&self, data: &[u8]) -> Result<()> {
+        let mut line = self.start.elapsed().as_micros().to_string();
+        for byte in data {
+            _ = write!(line, " {byte:02x}");
+        }
+        line.push('\n');
+        let mut out = self.out.lock().unwrap_or_else(PoisonError::into_inner);
+        out.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn flush(&self) -> Result<()> {
+        self.out
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        _ = self.flush();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedWrite {
+    /// Time since recording started.
+    pub at: Duration,
+    /// Bytes written, report ID first.
+    pub data: Vec<u8>,
+}
+
+impl RecordedWrite {
+    pub fn report_id(&self) -> u8 {
+        self.data.first().copied().unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recording {
+    pub writes: Vec<RecordedWrite>,
+}
+
+impl Recording {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut writes = vec![];
+        for line in text.lines().map(str::trim).filter(|x| !x.is_empty()) {
+            let (at, data) = line.split_once(' ').unwrap_or((line, ""));
+            let at = at.parse().map_err(|_| HidLightError::InvalidHex)?;
+            writes.push(RecordedWrite {
+                at: Duration::from_micros(at),
+                data: descriptor::from_hex(data)?,
+            });
+        }
+        Ok(Self { writes })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Plays the writes back to `sink` with their original timing, or as fast as
+    /// possible when `realtime` is false. The sink can be a device or a simulator.
+    pub fn replay_to(
+        &self,
+        realtime: bool,
+        mut sink: impl FnMut(&RecordedWrite) -> Result<()>,
+    ) -> Result<()> {
+        let start = Instant::now();
+        for write in &self.writes {
+            if realtime {
+                if let Some(wait) = write.at.checked_sub(start.elapsed()) {
+                    std::thread::sleep(wait);
+                }
+            }
+            sink(write)?;
+        }
+        Ok(())
+    }
+
+    pub fn replay(&self, handle: &DeviceHandle, realtime: bool) -> Result<()> {
+        self.replay_to(realtime, |x| handle.write_raw(&x.data))
+    }
+}
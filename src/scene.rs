@@ -0,0 +1,316 @@
+//! Named lighting setups across several devices, switched with one call.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    color::Rgb, surface::rgb_lamps, DeviceHandle, DeviceId, DeviceState, Easing, HidLightError,
+    OutputKey, Result, Ticker, Transition, Value,
+};
+
+/// A color for a device's RGB lamps, either fixed or taken from the scene's palette so
+/// that several scenes can be restyled at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SceneColor {
+    Rgb(Rgb),
+    /// Index into [`Scene::palette`], black when out of range.
+    Palette(usize),
+}
+
+impl From<Rgb> for SceneColor {
+    fn from(color: Rgb) -> Self {
+        SceneColor::Rgb(color)
+    }
+}
+
+impl SceneColor {
+    pub fn resolve(self, palette: &[Rgb]) -> Rgb {
+        match self {
+            SceneColor::Rgb(x) => x,
+            SceneColor::Palette(i) => palette.get(i).copied().unwrap_or_default(),
+        }
+    }
+}
+
+/// What a scene sets on one device.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceScene {
+    pub device: DeviceId,
+    pub outputs: Vec<(OutputKey, Value)>,
+    /// Applied to every group of red, green and blue outputs the device has, see
+    /// [`crate::surface::RgbOutputs`]. Values in `outputs` take precedence.
+    pub color: Option<SceneColor>,
+}
+
+impl DeviceScene {
+    pub fn new(device: DeviceId) -> Self {
+        Self {
+            device,
+            outputs: vec![],
+            color: None,
+        }
+    }
+
+    /// Everything the handle's outputs are currently set to.
+    pub fn capture(device: DeviceId, handle: &mut DeviceHandle) -> Result<Self> {
+        let state = handle.snapshot()?;
+        let mut outputs = vec![];
+        for (id, value) in state.values {
+            outputs.push((handle.output(id)?.key(), value));
+        }
+        Ok(Self {
+            device,
+            outputs,
+            color: None,
+        })
+    }
+
+    /// The scene resolved against the device's outputs, checking every value fits.
+    pub fn state(&self, handle: &mut DeviceHandle, palette: &[Rgb]) -> Result<DeviceState> {
+        let mut state = DeviceState::new();
+        if let Some(color) = self.color {
+            let color: [u8; 3] = color.resolve(palette).into();
+            for lamp in rgb_lamps(&handle.reports()?) {
+                for (id, value) in lamp.iter().zip(color) {
+                    state = state.set(*id, value as f32 / 255.0);
+                }
+            }
+        }
+        for (key, value) in &self.outputs {
+            let out = handle.output_by_key(key)?;
+            out.validate(*value)?;
+            state = state.set(out.id(), *value);
+        }
+        Ok(state)
+    }
+
+    pub fn apply(&self, handle: &mut DeviceHandle, palette: &[Rgb]) -> Result<()> {
+        let state = self.state(handle, palette)?;
+        handle.transition_to(&state, Duration::ZERO, Easing::Linear)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scene {
+    pub name: String,
+    pub palette: Vec<Rgb>,
+    pub devices: Vec<DeviceScene>,
+}
+
+impl Scene {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            palette: vec![],
+            devices: vec![],
+        }
+    }
+
+    pub fn with_palette(mut self, palette: impl Into<Vec<Rgb>>) -> Self {
+        self.palette = palette.into();
+        self
+    }
+
+    /// Sets an output on a device, replacing any earlier value for it.
+    pub fn set(mut self, device: &DeviceId, output: OutputKey, value: impl Into<Value>) -> Self {
+        let scene = self.device_mut(device);
+        let value = value.into();
+        match scene.outputs.iter_mut().find(|x| x.0 == output) {
+            Some(x) => x.1 = value,
+            None => scene.outputs.push((output, value)),
+        }
+        self
+    }
+
+    pub fn set_color(mut self, device: &DeviceId, color: impl Into<SceneColor>) -> Self {
+        self.device_mut(device).color = Some(color.into());
+        self
+    }
+
+    pub fn device(&self, device: &DeviceId) -> Option<&DeviceScene> {
+        self.devices.iter().find(|x| x.device == *device)
+    }
+
+    fn device_mut(&mut self, device: &DeviceId) -> &mut DeviceScene {
+        match self.devices.iter().position(|x| x.device == *device) {
+            Some(i) => &mut self.devices[i],
+            None => {
+                self.devices.push(DeviceScene::new(device.clone()));
+                self.devices.last_mut().expect("just pushed")
+            }
+        }
+    }
+
+    /// Applies the scene to every device of it in `devices`, devices that aren't
+    /// there are skipped. Every device is tried, the first error is returned.
+    pub fn apply(&self, devices: &mut HashMap<DeviceId, DeviceHandle>) -> Result<()> {
+        let mut result = Ok(());
+        for scene in &self.devices {
+            let Some(handle) = devices.get_mut(&scene.device) else {
+                continue;
+            };
+            let applied = scene.apply(handle, &self.palette);
+            if result.is_ok() {
+                result = applied;
+            }
+        }
+        result
+    }
+
+    /// Fades from `from`, usually the scene applied before, to this one over
+    /// `duration`, with every device on one clock. Outputs `from` sets that this
+    /// scene doesn't are faded out. Devices that aren't in `devices` are skipped.
+    pub fn crossfade(
+        &self,
+        from: Option<&Scene>,
+        devices: &mut HashMap<DeviceId, DeviceHandle>,
+        duration: Duration,
+        easing: Easing,
+    ) -> Result<()> {
+        let ids = self
+            .devices
+            .iter()
+            .chain(from.into_iter().flat_map(|x| &x.devices))
+            .map(|x| &x.device);
+
+        let mut transitions: Vec<(&DeviceId, Transition)> = vec![];
+        let mut interval = Duration::ZERO;
+        for id in ids {
+            if transitions.iter().any(|x| x.0 == id) {
+                continue;
+            }
+            let Some(handle) = devices.get_mut(id) else {
+                continue;
+            };
+
+            let mut target = match self.device(id) {
+                Some(scene) => scene.state(handle, &self.palette)?,
+                None => DeviceState::new(),
+            };
+            if let Some((outgoing, scene)) = from.and_then(|x| Some((x, x.device(id)?))) {
+                for out in scene.state(handle, &outgoing.palette)?.values.into_keys() {
+                    target.values.entry(out).or_insert(Value::Bool(false));
+                }
+            }
+
+            interval = interval.max(Ticker::for_device(handle, 50.0).interval());
+            transitions.push((id, Transition::new(handle, target, duration, easing)?));
+        }
+
+        let start = Instant::now();
+        let mut ticker = Ticker::with_interval(interval);
+        loop {
+            let elapsed = start.elapsed();
+            let mut done = true;
+            for (id, transition) in &transitions {
+                let handle = devices.get_mut(*id).expect("device was found above");
+                done &= transition.step(handle, elapsed)?;
+            }
+            if done {
+                return Ok(());
+            }
+            ticker.tick();
+        }
+    }
+}
+
+/// Scenes by name, remembering which was applied last.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SceneRegistry {
+    scenes: BTreeMap<String, Scene>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    active: Option<String>,
+}
+
+impl SceneRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a scene, replacing any with the same name.
+    pub fn insert(&mut self, scene: Scene) {
+        self.scenes.insert(scene.name.clone(), scene);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Scene> {
+        if self.active.as_deref() == Some(name) {
+            self.active = None;
+        }
+        self.scenes.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Scene> {
+        self.scenes.get(name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Scene> {
+        self.scenes.get_mut(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.scenes.keys().map(String::as_str)
+    }
+
+    /// The scene applied last.
+    pub fn active(&self) -> Option<&Scene> {
+        self.scenes.get(self.active.as_ref()?)
+    }
+
+    /// Applies the named scene, see [`Scene::apply`].
+    pub fn apply(
+        &mut self,
+        name: &str,
+        devices: &mut HashMap<DeviceId, DeviceHandle>,
+    ) -> Result<()> {
+        let scene = self
+            .scenes
+            .get(name)
+            .ok_or_else(|| HidLightError::UnknownScene(name.into()))?;
+        self.active = Some(name.into());
+        scene.apply(devices)
+    }
+
+    /// Crossfades from the active scene to the named one, see [`Scene::crossfade`].
+    pub fn crossfade(
+        &mut self,
+        name: &str,
+        devices: &mut HashMap<DeviceId, DeviceHandle>,
+        duration: Duration,
+        easing: Easing,
+    ) -> Result<()> {
+        let scene = self
+            .scenes
+            .get(name)
+            .ok_or_else(|| HidLightError::UnknownScene(name.into()))?;
+        let from = self.active.as_ref().and_then(|x| self.scenes.get(x));
+        scene.crossfade(from, devices, duration, easing)?;
+        self.active = Some(name.into());
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        Ok(std::fs::write(path, self.to_json()?)?)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+}
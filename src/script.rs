@@ -0,0 +1,195 @@
+//! Effects written as [rhai](https://rhai.rs) scripts, loaded at runtime.
+//!
+//! A script defines `fn tick(t)`, called every frame with the seconds since it
+//! started. From it the script sets outputs and lamps:
+//!
+//! ```text
+//! output_count()            lamp_count()
+//! output_name(i)            lamp_x(i), lamp_y(i)    position, 0 to 1
+//! set_output(i, value)      set_lamp(i, r, g, b)    0 to 255
+//! set_output(name, value)   fill(r, g, b)
+//! ```
+//!
+//! Output values are fractions of the output's range, as for
+//! [`DeviceHandle::set`]. Nothing the script sets is sent until `tick` returns.
+
+use std::{cell::RefCell, path::Path, rc::Rc, time::Duration};
+
+use rhai::{Engine, Scope, AST};
+
+use crate::{
+    color::Rgb,
+    surface::{normalized_positions, LightSurface},
+    DeviceHandle, HidLightError, OutputId, Result,
+};
+
+/// What the script can see of its target, and what it set during a tick.
+#[derive(Default)]
+struct Frame {
+    outputs: Vec<(OutputId, String)>,
+    lamps: Vec<[f64; 2]>,
+    output_values: Vec<(OutputId, f32)>,
+    lamp_colors: Vec<(usize, Rgb)>,
+}
+
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+    frame: Rc<RefCell<Frame>>,
+}
+
+impl Script {
+    pub fn compile(source: &str) -> Result<Self> {
+        let frame = Rc::new(RefCell::new(Frame::default()));
+        let engine = engine(&frame);
+        let ast = engine.compile(source).map_err(script_error)?;
+        let mut scope = Scope::new();
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .map_err(script_error)?;
+        Ok(Self {
+            engine,
+            ast,
+            scope,
+            frame,
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::compile(&std::fs::read_to_string(path)?)
+    }
+
+    /// Runs one frame against a device's outputs, then flushes it.
+    pub fn tick(&mut self, t: Duration, handle: &mut DeviceHandle) -> Result<()> {
+        // Only reads from the device on the first frame
+        handle.resolve_names()?;
+        let outputs = handle
+            .outputs()?
+            .map(|x| (x.id(), x.name.clone().unwrap_or_default()))
+            .collect();
+        {
+            let mut frame = self.frame.borrow_mut();
+            frame.outputs = outputs;
+            frame.lamps.clear();
+        }
+
+        let (values, _) = self.call(t)?;
+        for (id, value) in values {
+            handle.set(id, value)?;
+        }
+        handle.flush()
+    }
+
+    /// Runs one frame against a surface's lamps, then presents it.
+    pub fn tick_surface(&mut self, t: Duration, surface: &mut dyn LightSurface) -> Result<()> {
+        let lamps = normalized_positions(surface)
+            .into_iter()
+            .map(|[x, y, _]| [x as f64, y as f64])
+            .collect();
+        {
+            let mut frame = self.frame.borrow_mut();
+            frame.outputs.clear();
+            frame.lamps = lamps;
+        }
+
+        let (_, colors) = self.call(t)?;
+        for (lamp, color) in colors {
+            surface.set(lamp, color);
+        }
+        surface.present()
+    }
+
+    /// Calls the script's `tick`, returning the output values and lamp colors it set.
+    #[allow(clippy::type_complexity)]
+    fn call(&mut self, t: Duration) -> Result<(Vec<(OutputId, f32)>, Vec<(usize, Rgb)>)> {
+        let result =
+            self.engine
+                .call_fn::<()>(&mut self.scope, &self.ast, "tick", (t.as_secs_f64(),));
+
+        // Whatever a failed tick set is dropped
+        let mut frame = self.frame.borrow_mut();
+        let values = std::mem::take(&mut frame.output_values);
+        let colors = std::mem::take(&mut frame.lamp_colors);
+        result.map_err(script_error)?;
+        Ok((values, colors))
+    }
+}
+
+fn engine(frame: &Rc<RefCell<Frame>>) -> Engine {
+    let mut engine = Engine::new();
+
+    let f = frame.clone();
+    engine.register_fn("output_count", move || f.borrow().outputs.len() as i64);
+    let f = frame.clone();
+    engine.register_fn("output_name", move |i: i64| {
+        usize::try_from(i)
+            .ok()
+            .and_then(|i| f.borrow().outputs.get(i).map(|x| x.1.clone()))
+            .unwrap_or_default()
+    });
+    let f = frame.clone();
+    engine.register_fn("set_output", move |i: i64, value: f64| {
+        let mut frame = f.borrow_mut();
+        if let Some(id) = usize::try_from(i)
+            .ok()
+            .and_then(|i| frame.outputs.get(i).map(|x| x.0))
+        {
+            frame.output_values.push((id, value as f32));
+        }
+    });
+    let f = frame.clone();
+    engine.register_fn("set_output", move |name: &str, value: f64| {
+        let mut frame = f.borrow_mut();
+        let ids: Vec<_> = frame
+            .outputs
+            .iter()
+            .filter(|x| x.1 == name)
+            .map(|x| x.0)
+            .collect();
+        frame
+            .output_values
+            .extend(ids.into_iter().map(|id| (id, value as f32)));
+    });
+
+    let f = frame.clone();
+    engine.register_fn("lamp_count", move || f.borrow().lamps.len() as i64);
+    let f = frame.clone();
+    engine.register_fn("lamp_x", move |i: i64| {
+        usize::try_from(i)
+            .ok()
+            .and_then(|i| f.borrow().lamps.get(i).map(|x| x[0]))
+            .unwrap_or_default()
+    });
+    let f = frame.clone();
+    engine.register_fn("lamp_y", move |i: i64| {
+        usize::try_from(i)
+            .ok()
+            .and_then(|i| f.borrow().lamps.get(i).map(|x| x[1]))
+            .unwrap_or_default()
+    });
+    let f = frame.clone();
+    engine.register_fn("set_lamp", move |i: i64, r: i64, g: i64, b: i64| {
+        if let Ok(i) = usize::try_from(i) {
+            f.borrow_mut().lamp_colors.push((i, color(r, g, b)));
+        }
+    });
+    let f = frame.clone();
+    engine.register_fn("fill", move |r: i64, g: i64, b: i64| {
+        let mut frame = f.borrow_mut();
+        let color = color(r, g, b);
+        let len = frame.lamps.len();
+        frame.lamp_colors.extend((0..len).map(|i| (i, color)));
+    });
+
+    engine
+}
+
+fn color(r: i64, g: i64, b: i64) -> Rgb {
+    let channel = |x: i64| x.clamp(0, 255) as u8;
+    Rgb::new(channel(r), channel(g), channel(b))
+}
+
+fn script_error(err: impl std::fmt::Display) -> HidLightError {
+    HidLightError::Script(err.to_string())
+}
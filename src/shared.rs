@@ -0,0 +1,78 @@
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::{DeviceHandle, OutputId, Report, Result, Value};
+
+/// A report several threads set values on while one of them writes it, e.g. an
+/// animation thread and a UI both driving the same device. Clones share the report.
+#[derive(Debug, Clone)]
+pub struct SharedReport {
+    report: Arc<Mutex<Report>>,
+}
+
+impl SharedReport {
+    pub fn new(report: Report) -> Self {
+        Self {
+            report: Arc::new(Mutex::new(report)),
+        }
+    }
+
+    /// One shared report for each of the device's output reports.
+    pub fn for_device(handle: &DeviceHandle) -> Result<Vec<Self>> {
+        Ok(handle.reports()?.into_iter().map(Self::new).collect())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Report> {
+        self.report.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn id(&self) -> u32 {
+        self.lock().id
+    }
+
+    /// An output's value as a fraction of its range.
+    pub fn get(&self, id: OutputId) -> Result<f32> {
+        Ok(self.lock().output(id)?.real_value)
+    }
+
+    /// Sets an output, written on the next [`Self::flush`].
+    pub fn set(&self, id: OutputId, value: f32) -> Result<()> {
+        self.set_value(id, Value::Real(value))
+    }
+
+    pub fn set_value(&self, id: OutputId, value: Value) -> Result<()> {
+        self.update(|report| report.output_mut(id)?.set_value(value))
+    }
+
+    /// Changes the report in place, holding off other threads until `f` returns. The
+    /// report is written on the next flush even if `f` fails partway.
+    pub fn update<T>(&self, f: impl FnOnce(&mut Report) -> T) -> T {
+        let mut report = self.lock();
+        report.dirty = true;
+        f(&mut report)
+    }
+
+    /// A copy of the report as it is now.
+    pub fn snapshot(&self) -> Report {
+        self.lock().clone()
+    }
+
+    /// Writes the report if it changed since the last flush, returning whether it did.
+    /// The lock is only held to copy the report, so setters aren't kept waiting on
+    /// the device.
+    pub fn flush(&self, handle: &DeviceHandle) -> Result<bool> {
+        let report = {
+            let mut report = self.lock();
+            if !report.dirty {
+                return Ok(false);
+            }
+            report.dirty = false;
+            report.clone()
+        };
+        if let Err(err) = handle.write_report(&report) {
+            // Try again on the next flush
+            self.lock().dirty = true;
+            return Err(err);
+        }
+        Ok(true)
+    }
+}
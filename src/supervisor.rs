@@ -0,0 +1,140 @@
+use std::{
+    sync::PoisonError,
+    time::{Duration, Instant},
+};
+
+use crate::{DeviceHandle, DeviceId, DeviceInfo, HidLightError, HidLights, Result};
+
+/// Keeps a device open across unplugs. When an operation fails with
+/// [`HidLightError::Disconnected`], waits for the device to come back, reopens
+/// it and replays everything that was last written before retrying.
+pub struct Supervisor {
+    lights: HidLights,
+    id: DeviceId,
+    handle: DeviceHandle,
+    poll_interval: Duration,
+    timeout: Option<Duration>,
+}
+
+impl Supervisor {
+    pub fn new(info: &DeviceInfo) -> Result<Self> {
+        Ok(Self {
+            lights: HidLights {
+                hidapi: info.api.clone(),
+                cache: info.cache.clone(),
+            },
+            id: info.id(),
+            handle: info.open()?,
+            poll_interval: Duration::from_millis(500),
+            timeout: None,
+        })
+    }
+
+    /// How often to re-enumerate while waiting for the device.
+    pub fn set_poll_interval(&mut self, interval: Duration) {
+        self.poll_interval = interval;
+    }
+
+    /// How long to wait for the device before giving up with
+    /// [`HidLightError::Timeout`], by default forever.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub fn id(&self) -> &DeviceId {
+        &self.id
+    }
+
+    pub fn handle(&self) -> &DeviceHandle {
+        &self.handle
+    }
+
+    pub fn handle_mut(&mut self) -> &mut DeviceHandle {
+        &mut self.handle
+    }
+
+    /// Runs `f` on the handle, reconnecting and running it once more if the device
+    /// was unplugged.
+    pub fn run<T>(&mut self, mut f: impl FnMut(&mut DeviceHandle) -> Result<T>) -> Result<T> {
+        if !self.handle.is_disconnected() {
+            match f(&mut self.handle) {
+                Err(HidLightError::Disconnected) => {}
+                result => return result,
+            }
+        }
+        self.reconnect()?;
+        f(&mut self.handle)
+    }
+
+    /// Waits for the device to reappear and reopens it, keeping the handle's
+    /// settings and output state.
+    pub fn reconnect(&mut self) -> Result<()> {
+        let start = Instant::now();
+        let handle = loop {
+            self.lights.refresh()?;
+            if let Some(info) = self.lights.find(|x| x.id() == self.id) {
+                // It may still be settling right after enumeration
+                if let Ok(handle) = info.open() {
+                    break handle;
+                }
+            }
+            if self.timeout.is_some_and(|x| start.elapsed() >= x) {
+                return Err(HidLightError::Timeout);
+            }
+            std::thread::sleep(self.poll_interval);
+        };
+
+        let old = std::mem::replace(&mut self.handle, handle);
+        self.restore(old)
+    }
+
+    fn restore(&mut self, old: DeviceHandle) -> Result<()> {
+        let handle = &mut self.handle;
+        handle.profile = old.profile;
+        handle.quirks = old.quirks;
+        handle.write_path = old.write_path;
+        handle.retry_policy = old.retry_policy;
+        handle.metrics = old.metrics.clone();
+        handle.recorder = old.recorder.clone();
+        handle.observers = old.observers.clone();
+        handle.history = old.history.clone();
+
+        // Values set through `set` carry over to outputs that are still there
+        if let Some(old_state) = &old.state {
+            let mut state = handle.reports()?;
+            for report in &mut state {
+                for out in &mut report.outputs {
+                    let previous = old_state
+                        .iter()
+                        .flat_map(|x| &x.outputs)
+                        .find(|x| x.id() == out.id());
+                    if let Some(previous) = previous {
+                        out.real_value = previous.real_value;
+                        out.selected = previous.selected.clone();
+                    }
+                }
+            }
+            handle.state = Some(state);
+        }
+
+        // Everything written before is written again, byte for byte
+        let shadow = old
+            .shadow
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        for (id, data) in &shadow {
+            let mut buffer = Vec::with_capacity(data.len() + 1);
+            buffer.push(*id as u8);
+            buffer.extend_from_slice(data);
+            handle.write_raw(&buffer)?;
+        }
+        *handle.shadow.lock().unwrap_or_else(PoisonError::into_inner) = shadow;
+
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> DeviceHandle {
+        self.handle
+    }
+}
@@ -0,0 +1,338 @@
+//! Lights as a frame buffer: set lamps by index, then present the frame.
+
+use crate::{
+    color::Rgb,
+    indicators::LED_PAGE,
+    lamp_array::{LampArray, Position},
+    DeviceHandle, DeviceOutput, HidLightError, OutputId, Report, Result,
+};
+
+const RGB_LED: u16 = 0x52;
+const RED_LED_CHANNEL: u16 = 0x53;
+const BLUE_LED_CHANNEL: u16 = 0x54;
+const GREEN_LED_CHANNEL: u16 = 0x55;
+
+/// Spacing given to lamps whose device doesn't say where they are, 1cm.
+const DEFAULT_PITCH: u32 = 10_000;
+
+/// A set of color lamps that is drawn a frame at a time. Nothing reaches the device
+/// until [`Self::present`].
+pub trait LightSurface {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Extent of the surface in micrometers.
+    fn bounds(&self) -> Position;
+
+    /// Where a lamp sits within [`Self::bounds`].
+    fn position(&self, lamp: usize) -> Option<Position>;
+
+    /// The color last set on a lamp, black for lamps out of range.
+    fn get(&self, lamp: usize) -> Rgb;
+
+    /// Sets a lamp in the current frame, lamps out of range are ignored.
+    fn set(&mut self, lamp: usize, color: Rgb);
+
+    /// Sends the lamps changed since the last present to the device.
+    fn present(&mut self) -> Result<()>;
+
+    fn fill(&mut self, color: Rgb) {
+        for lamp in 0..self.len() {
+            self.set(lamp, color);
+        }
+    }
+}
+
+/// Where each lamp of `surface` sits as fractions of its bounds, so `[0.5, 0.5, 0.5]`
+/// is its middle. Lamps without a position are spread evenly along the x axis.
+pub fn normalized_positions(surface: &dyn LightSurface) -> Vec<[f32; 3]> {
+    let bounds = surface.bounds();
+    let fraction = |value: u32, extent: u32| {
+        if extent == 0 {
+            0.5
+        } else {
+            value as f32 / extent as f32
+        }
+    };
+    let len = surface.len();
+
+    (0..len)
+        .map(|lamp| match surface.position(lamp) {
+            Some(p) => [
+                fraction(p.x, bounds.x),
+                fraction(p.y, bounds.y),
+                fraction(p.z, bounds.z),
+            ],
+            None => [(lamp as f32 + 0.5) / len as f32, 0.5, 0.5],
+        })
+        .collect()
+}
+
+impl LightSurface for LampArray {
+    fn len(&self) -> usize {
+        self.lamps().len()
+    }
+
+    fn bounds(&self) -> Position {
+        self.attributes().bounding_box
+    }
+
+    fn position(&self, lamp: usize) -> Option<Position> {
+        self.lamps().get(lamp).map(|x| x.position)
+    }
+
+    fn get(&self, lamp: usize) -> Rgb {
+        self.frame.get(lamp).map(|x| x.0).unwrap_or_default()
+    }
+
+    fn set(&mut self, lamp: usize, color: Rgb) {
+        if let Some(x) = self.frame.get_mut(lamp) {
+            if x.0 != color {
+                *x = (color, true);
+            }
+        }
+    }
+
+    fn present(&mut self) -> Result<()> {
+        let changed: Vec<(u16, Rgb)> = self
+            .frame
+            .iter()
+            .enumerate()
+            .filter(|x| x.1 .1)
+            .map(|(i, x)| (i as u16, x.0))
+            .collect();
+        if changed.is_empty() {
+            return Ok(());
+        }
+
+        self.set_lamps(&changed)?;
+        for x in &mut self.frame {
+            x.1 = false;
+        }
+        Ok(())
+    }
+}
+
+/// Red, green and blue outputs in a device's output reports, grouped into lamps.
+///
+/// Channels are found by the LED page's RGB channel usages, or failing that by
+/// outputs named red, green and blue, so profiles can label vendor fields. Lamps
+/// have no position in the descriptor and are laid out in a row in report order.
+pub struct RgbOutputs {
+    handle: DeviceHandle,
+    lamps: Vec<[OutputId; 3]>,
+    frame: Vec<Rgb>,
+}
+
+impl RgbOutputs {
+    /// Fails with [`HidLightError::UnsupportedDevice`] if no complete RGB lamp is found.
+    pub fn new(handle: DeviceHandle) -> Result<Self> {
+        let mut reports = handle.reports()?;
+        for report in &mut reports {
+            report.resolve_names(&handle);
+        }
+        let lamps = rgb_lamps(&reports);
+        if lamps.is_empty() {
+            return Err(HidLightError::UnsupportedDevice);
+        }
+        Ok(Self {
+            handle,
+            frame: vec![Rgb::BLACK; lamps.len()],
+            lamps,
+        })
+    }
+
+    /// The red, green and blue outputs of each lamp.
+    pub fn lamps(&self) -> &[[OutputId; 3]] {
+        &self.lamps
+    }
+
+    pub fn into_inner(self) -> DeviceHandle {
+        self.handle
+    }
+}
+
+/// The red, green and blue outputs of each RGB lamp in `reports`, see [`RgbOutputs`].
+pub fn rgb_lamps(reports: &[Report]) -> Vec<[OutputId; 3]> {
+    let mut lamps = vec![];
+    for report in reports {
+        let mut current: [Option<OutputId>; 3] = [None; 3];
+        let mut collections = None;
+
+        for out in &report.outputs {
+            let Some(channel) = channel(out) else {
+                continue;
+            };
+            // A repeated channel or another collection starts the next lamp
+            if current[channel].is_some() || collections != Some(out.collections()) {
+                current = [None; 3];
+                collections = Some(out.collections());
+            }
+            current[channel] = Some(out.id());
+            if let [Some(r), Some(g), Some(b)] = current {
+                lamps.push([r, g, b]);
+                current = [None; 3];
+                collections = None;
+            }
+        }
+    }
+    lamps
+}
+
+/// Index of the color channel an output drives.
+fn channel(out: &DeviceOutput) -> Option<usize> {
+    if out.usage_page() == LED_PAGE {
+        match out.usage_id() {
+            RED_LED_CHANNEL => return Some(0),
+            GREEN_LED_CHANNEL => return Some(1),
+            BLUE_LED_CHANNEL => return Some(2),
+            RGB_LED => return None,
+            _ => {}
+        }
+    }
+    let name = out.name.as_deref()?.to_lowercase();
+    ["red", "green", "blue"]
+        .iter()
+        .position(|x| name.contains(x))
+}
+
+impl LightSurface for RgbOutputs {
+    fn len(&self) -> usize {
+        self.lamps.len()
+    }
+
+    fn bounds(&self) -> Position {
+        Position {
+            x: self.lamps.len() as u32 * DEFAULT_PITCH,
+            y: DEFAULT_PITCH,
+            z: 0,
+        }
+    }
+
+    fn position(&self, lamp: usize) -> Option<Position> {
+        (lamp < self.lamps.len()).then(|| Position {
+            x: lamp as u32 * DEFAULT_PITCH + DEFAULT_PITCH / 2,
+            y: DEFAULT_PITCH / 2,
+            z: 0,
+        })
+    }
+
+    fn get(&self, lamp: usize) -> Rgb {
+        self.frame.get(lamp).copied().unwrap_or_default()
+    }
+
+    fn set(&mut self, lamp: usize, color: Rgb) {
+        if let Some(x) = self.frame.get_mut(lamp) {
+            *x = color;
+        }
+    }
+
+    fn present(&mut self) -> Result<()> {
+        for (ids, color) in self.lamps.iter().zip(&self.frame) {
+            for (id, value) in ids.iter().zip(<[u8; 3]>::from(*color)) {
+                self.handle.set(*id, value as f32 / 255.0)?;
+            }
+        }
+        // Only reports that changed get written
+        self.handle.flush()
+    }
+}
+
+/// Several surfaces drawn as one, lamps numbered through each surface in turn.
+#[derive(Default)]
+pub struct Aggregate {
+    surfaces: Vec<(Box<dyn LightSurface + Send>, Position)>,
+}
+
+impl Aggregate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a surface to the right of the ones already added.
+    pub fn push(&mut self, surface: impl LightSurface + Send + 'static) {
+        let x = self
+            .surfaces
+            .iter()
+            .map(|(s, at)| at.x + s.bounds().x)
+            .max()
+            .unwrap_or_default();
+        self.push_at(surface, Position { x, y: 0, z: 0 });
+    }
+
+    /// Adds a surface with its origin at `offset`.
+    pub fn push_at(&mut self, surface: impl LightSurface + Send + 'static, offset: Position) {
+        self.surfaces.push((Box::new(surface), offset));
+    }
+
+    pub fn surfaces(&self) -> impl Iterator<Item = &(dyn LightSurface + Send)> {
+        self.surfaces.iter().map(|x| x.0.as_ref())
+    }
+
+    /// The surface a lamp belongs to and its index there.
+    fn locate(&self, mut lamp: usize) -> Option<(usize, usize)> {
+        for (i, (surface, _)) in self.surfaces.iter().enumerate() {
+            if lamp < surface.len() {
+                return Some((i, lamp));
+            }
+            lamp -= surface.len();
+        }
+        None
+    }
+}
+
+impl LightSurface for Aggregate {
+    fn len(&self) -> usize {
+        self.surfaces.iter().map(|x| x.0.len()).sum()
+    }
+
+    fn bounds(&self) -> Position {
+        self.surfaces
+            .iter()
+            .fold(Position::default(), |acc, (surface, at)| {
+                let bounds = surface.bounds();
+                Position {
+                    x: acc.x.max(at.x + bounds.x),
+                    y: acc.y.max(at.y + bounds.y),
+                    z: acc.z.max(at.z + bounds.z),
+                }
+            })
+    }
+
+    fn position(&self, lamp: usize) -> Option<Position> {
+        let (i, lamp) = self.locate(lamp)?;
+        let (surface, at) = &self.surfaces[i];
+        surface.position(lamp).map(|x| Position {
+            x: at.x + x.x,
+            y: at.y + x.y,
+            z: at.z + x.z,
+        })
+    }
+
+    fn get(&self, lamp: usize) -> Rgb {
+        self.locate(lamp)
+            .map(|(i, lamp)| self.surfaces[i].0.get(lamp))
+            .unwrap_or_default()
+    }
+
+    fn set(&mut self, lamp: usize, color: Rgb) {
+        if let Some((i, lamp)) = self.locate(lamp) {
+            self.surfaces[i].0.set(lamp, color);
+        }
+    }
+
+    /// Presents every surface, even after one fails, returning the first error.
+    fn present(&mut self) -> Result<()> {
+        let mut result = Ok(());
+        for (surface, _) in &mut self.surfaces {
+            let presented = surface.present();
+            if result.is_ok() {
+                result = presented;
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,111 @@
+//! Driving effects on several devices from one clock.
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    effects::{Blend, Effect, LayerId, Player},
+    DeviceHandle, HidLightError, OutputId, Result, Ticker,
+};
+
+/// Plays effects across devices in lockstep. Every frame is computed for all
+/// devices at the same instant, then all devices are written at once, so effects
+/// that cross from one device to the next stay aligned.
+pub struct Coordinator {
+    devices: Vec<(DeviceHandle, Player)>,
+    start: Instant,
+    fps: f32,
+}
+
+impl Coordinator {
+    pub fn new(fps: f32) -> Self {
+        Self {
+            devices: vec![],
+            start: Instant::now(),
+            fps,
+        }
+    }
+
+    /// Adds a device, returning its index for [`Self::add_layer`].
+    pub fn add_device(&mut self, handle: DeviceHandle) -> usize {
+        self.devices
+            .push((handle, Player::new().start_at(self.start)));
+        self.devices.len() - 1
+    }
+
+    pub fn device(&mut self, index: usize) -> Option<&mut DeviceHandle> {
+        self.devices.get_mut(index).map(|x| &mut x.0)
+    }
+
+    pub fn add_layer(
+        &mut self,
+        device: usize,
+        outputs: &[OutputId],
+        effect: impl Effect + Clone + 'static,
+        blend: Blend,
+        priority: i32,
+    ) -> Result<LayerId> {
+        let (handle, player) = self
+            .devices
+            .get_mut(device)
+            .ok_or(HidLightError::DeviceNotFound)?;
+        player.add_layer(handle, outputs, effect, blend, priority)
+    }
+
+    pub fn remove_layer(&mut self, device: usize, layer: LayerId) {
+        if let Some((_, player)) = self.devices.get_mut(device) {
+            player.remove_layer(layer);
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.devices.iter().all(|x| x.1.is_finished())
+    }
+
+    /// Computes the frame at the current time for every device, then writes them all
+    /// in parallel.
+    pub fn update(&mut self) -> Result<()> {
+        let t = self.start.elapsed();
+        for (handle, player) in &mut self.devices {
+            for (id, value) in player.frame(t) {
+                handle.set(id, value)?;
+            }
+        }
+
+        std::thread::scope(|scope| {
+            let writes: Vec<_> = self
+                .devices
+                .iter_mut()
+                .map(|(handle, _)| scope.spawn(move || handle.flush()))
+                .collect();
+            writes
+                .into_iter()
+                .map(|x| x.join().unwrap_or_else(|x| std::panic::resume_unwind(x)))
+                .collect::<Result<()>>()
+        })
+    }
+
+    /// Updates at the frame rate, slowed to the slowest device, until every effect
+    /// has ended.
+    pub fn run(&mut self) -> Result<()> {
+        let mut ticker = self.ticker();
+        while !self.is_finished() {
+            self.update()?;
+            ticker.tick();
+        }
+        Ok(())
+    }
+
+    fn ticker(&self) -> Ticker {
+        let interval = self
+            .devices
+            .iter()
+            .map(|x| Ticker::for_device(&x.0, self.fps).interval())
+            .max()
+            .unwrap_or(Duration::from_secs_f32(1.0 / self.fps));
+        Ticker::with_interval(interval)
+    }
+
+    pub fn into_devices(self) -> Vec<DeviceHandle> {
+        self.devices.into_iter().map(|x| x.0).collect()
+    }
+}
@@ -0,0 +1,108 @@
+//! Indicator helpers for headsets and speakerphones.
+
+use crate::{indicators::LED_PAGE, DeviceHandle, HidLightError, LedKind, Report, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TelephonyLed {
+    Mute,
+    OffHook,
+    Ring,
+    MessageWaiting,
+    Speaker,
+    Headset,
+    Hold,
+    Microphone,
+}
+
+impl TelephonyLed {
+    pub const ALL: [TelephonyLed; 8] = [
+        TelephonyLed::Mute,
+        TelephonyLed::OffHook,
+        TelephonyLed::Ring,
+        TelephonyLed::MessageWaiting,
+        TelephonyLed::Speaker,
+        TelephonyLed::Headset,
+        TelephonyLed::Hold,
+        TelephonyLed::Microphone,
+    ];
+
+    pub fn usage_id(self) -> u16 {
+        LedKind::from(self)
+            .usage_id()
+            .expect("telephony indicators have fixed usages")
+    }
+}
+
+impl From<TelephonyLed> for LedKind {
+    fn from(led: TelephonyLed) -> Self {
+        match led {
+            TelephonyLed::Mute => LedKind::Mute,
+            TelephonyLed::OffHook => LedKind::OffHook,
+            TelephonyLed::Ring => LedKind::Ring,
+            TelephonyLed::MessageWaiting => LedKind::MessageWaiting,
+            TelephonyLed::Speaker => LedKind::Speaker,
+            TelephonyLed::Headset => LedKind::Headset,
+            TelephonyLed::Hold => LedKind::Hold,
+            TelephonyLed::Microphone => LedKind::Microphone,
+        }
+    }
+}
+
+pub struct Telephony {
+    handle: DeviceHandle,
+    reports: Vec<Report>,
+}
+
+impl Telephony {
+    /// Fails with [`HidLightError::UnsupportedDevice`] if the device has none of the indicators.
+    pub fn new(handle: DeviceHandle) -> Result<Self> {
+        let reports = handle.reports()?;
+        let telephony = Self { handle, reports };
+
+        if !TelephonyLed::ALL.iter().any(|x| telephony.has_led(*x)) {
+            return Err(HidLightError::UnsupportedDevice);
+        }
+        Ok(telephony)
+    }
+
+    pub fn has_led(&self, led: TelephonyLed) -> bool {
+        self.find(led).is_some()
+    }
+
+    pub fn set_led(&mut self, led: TelephonyLed, on: bool) -> Result<()> {
+        let (report, output) = self.find(led).ok_or(HidLightError::UnsupportedDevice)?;
+        let report = &mut self.reports[report];
+        report.outputs[output].set_usage_active(LED_PAGE, led.usage_id(), on)?;
+        self.handle.write_report(report)
+    }
+
+    pub fn set_mute_led(&mut self, on: bool) -> Result<()> {
+        self.set_led(TelephonyLed::Mute, on)
+    }
+
+    pub fn set_off_hook_led(&mut self, on: bool) -> Result<()> {
+        self.set_led(TelephonyLed::OffHook, on)
+    }
+
+    pub fn set_ring_led(&mut self, on: bool) -> Result<()> {
+        self.set_led(TelephonyLed::Ring, on)
+    }
+
+    pub fn set_hold_led(&mut self, on: bool) -> Result<()> {
+        self.set_led(TelephonyLed::Hold, on)
+    }
+
+    pub fn into_inner(self) -> DeviceHandle {
+        self.handle
+    }
+
+    fn find(&self, led: TelephonyLed) -> Option<(usize, usize)> {
+        self.reports.iter().enumerate().find_map(|(i, report)| {
+            report
+                .outputs
+                .iter()
+                .position(|x| x.has_usage(LED_PAGE, led.usage_id()))
+                .map(|x| (i, x))
+        })
+    }
+}
@@ -0,0 +1,87 @@
+use std::time::{Duration, Instant};
+
+use crate::DeviceHandle;
+
+/// Paces a frame loop at a fixed rate. Deadlines are kept on a grid from the start,
+/// so time spent per frame doesn't accumulate into drift, and frames that can't be
+/// made in time are skipped rather than sent in a burst.
+#[derive(Debug, Clone)]
+pub struct Ticker {
+    interval: Duration,
+    start: Instant,
+    frame: u64,
+}
+
+impl Ticker {
+    pub fn new(fps: f32) -> Self {
+        Self::with_interval(Duration::from_secs_f32(1.0 / fps.max(f32::EPSILON)))
+    }
+
+    pub fn with_interval(interval: Duration) -> Self {
+        Self {
+            interval: interval.max(Duration::from_micros(1)),
+            start: Instant::now(),
+            frame: 0,
+        }
+    }
+
+    /// A ticker at `fps`, slowed to the device's max report rate if it has one.
+    pub fn for_device(handle: &DeviceHandle, fps: f32) -> Self {
+        let fps = match handle.quirks().max_report_rate {
+            Some(max) => fps.min(max as f32),
+            None => fps,
+        };
+        Self::new(fps)
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Number of the last frame ticked, counting skipped ones.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Time since the ticker started, of the last frame ticked.
+    pub fn frame_time(&self) -> Duration {
+        self.deadline(self.frame)
+    }
+
+    /// When `frame` is due, since the start. Computed wide, frame counts outgrow a u32
+    /// on long runs at high rates.
+    fn deadline(&self, frame: u64) -> Duration {
+        let nanos = self.interval.as_nanos().saturating_mul(frame as u128);
+        Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+    }
+
+    /// Sleeps until the next frame is due and returns its number.
+    pub fn tick(&mut self) -> u64 {
+        let now = self.start.elapsed();
+        let due = (now.as_nanos() / self.interval.as_nanos()) as u64 + 1;
+        self.frame = due.max(self.frame + 1);
+        if let Some(wait) = self.deadline(self.frame).checked_sub(now) {
+            std::thread::sleep(wait);
+        }
+        self.frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_time_past_u32_frames() {
+        let mut ticker = Ticker::with_interval(Duration::from_micros(1));
+        ticker.frame = 5_000_000_000;
+        assert_eq!(ticker.frame_time(), Duration::from_secs(5_000));
+
+        let mut ticker = Ticker::with_interval(Duration::from_millis(1));
+        ticker.frame = u64::from(u32::MAX) + 1;
+        assert_eq!(
+            ticker.frame_time(),
+            Duration::from_millis(u64::from(u32::MAX) + 1)
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! Keyframe animation of output values, the basis of a lighting show.
+
+use std::time::Duration;
+
+use crate::{
+    effects::{Effect, EffectThread, Player},
+    DeviceHandle, OutputId, Result,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Jump to the keyframe's value when it's reached.
+    Step,
+    #[default]
+    Linear,
+    /// Ease in and out.
+    Smooth,
+}
+
+impl Interpolation {
+    fn apply(self, from: f32, to: f32, progress: f32) -> f32 {
+        let progress = match self {
+            Interpolation::Step => (progress >= 1.0) as u8 as f32,
+            Interpolation::Linear => progress,
+            Interpolation::Smooth => progress * progress * (3.0 - 2.0 * progress),
+        };
+        from + (to - from) * progress
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PlayMode {
+    #[default]
+    Once,
+    Loop,
+    /// Forwards then backwards, forever.
+    PingPong,
+}
+
+/// Output values to reach `after` the previous keyframe. Outputs a keyframe
+/// doesn't mention carry on towards their next value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframe {
+    pub after: Duration,
+    pub values: Vec<(OutputId, f32)>,
+    pub interpolation: Interpolation,
+}
+
+impl Keyframe {
+    pub fn new(after: Duration) -> Self {
+        Self {
+            after,
+            values: vec![],
+            interpolation: Interpolation::default(),
+        }
+    }
+
+    pub fn set(mut self, output: OutputId, value: f32) -> Self {
+        self.values.push((output, value));
+        self
+    }
+
+    pub fn interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Timeline {
+    pub keyframes: Vec<Keyframe>,
+    pub mode: PlayMode,
+}
+
+impl Timeline {
+    pub fn new(mode: PlayMode) -> Self {
+        Self {
+            keyframes: vec![],
+            mode,
+        }
+    }
+
+    pub fn keyframe(mut self, keyframe: Keyframe) -> Self {
+        self.keyframes.push(keyframe);
+        self
+    }
+
+    /// Length of one pass through the keyframes.
+    pub fn length(&self) -> Duration {
+        self.keyframes.iter().map(|x| x.after).sum()
+    }
+
+    pub fn outputs(&self) -> Vec<OutputId> {
+        let mut outputs: Vec<_> = self
+            .keyframes
+            .iter()
+            .flat_map(|x| x.values.iter().map(|x| x.0))
+            .collect();
+        outputs.sort();
+        outputs.dedup();
+        outputs
+    }
+
+    /// The output's value at `t`, `None` once a timeline playing once is over or if
+    /// no keyframe sets the output.
+    pub fn sample(&self, output: OutputId, t: Duration, initial: f32) -> Option<f32> {
+        self.track(output).sample(t, initial)
+    }
+
+    fn track(&self, output: OutputId) -> Track {
+        let mut time = Duration::ZERO;
+        let mut points = vec![];
+        for keyframe in &self.keyframes {
+            time += keyframe.after;
+            if let Some((_, value)) = keyframe.values.iter().find(|x| x.0 == output) {
+                points.push((time, *value, keyframe.interpolation));
+            }
+        }
+        Track {
+            points,
+            length: self.length(),
+            mode: self.mode,
+        }
+    }
+
+    /// Plays the timeline, blocking until it's over.
+    pub fn play(&self, handle: &mut DeviceHandle) -> Result<()> {
+        self.player(handle)?.run(handle)
+    }
+
+    /// Plays the timeline on a writer thread.
+    pub fn spawn(&self, mut handle: DeviceHandle) -> Result<EffectThread> {
+        Ok(self.player(&mut handle)?.spawn(handle))
+    }
+
+    fn player(&self, handle: &mut DeviceHandle) -> Result<Player> {
+        let mut player = Player::new();
+        for output in self.outputs() {
+            player.add(handle, &[output], self.track(output))?;
+        }
+        Ok(player)
+    }
+}
+
+/// One output's keyframes, as an effect.
+#[derive(Debug, Clone)]
+struct Track {
+    points: Vec<(Duration, f32, Interpolation)>,
+    length: Duration,
+    mode: PlayMode,
+}
+
+impl Effect for Track {
+    fn sample(&self, t: Duration, initial: f32) -> Option<f32> {
+        if self.points.is_empty() {
+            return None;
+        }
+
+        let t = match self.mode {
+            PlayMode::Once if t > self.length => return None,
+            PlayMode::Once => t,
+            _ if self.length.is_zero() => Duration::ZERO,
+            PlayMode::Loop => Duration::from_nanos((t.as_nanos() % self.length.as_nanos()) as u64),
+            PlayMode::PingPong => {
+                let length = self.length.as_nanos();
+                let t = t.as_nanos() % (length * 2);
+                Duration::from_nanos(if t > length { length * 2 - t } else { t } as u64)
+            }
+        };
+
+        let mut previous = (Duration::ZERO, initial);
+        for (time, value, interpolation) in &self.points {
+            if t <= *time {
+                let span = (*time - previous.0).as_secs_f32();
+                let progress = if span > 0.0 {
+                    (t - previous.0).as_secs_f32() / span
+                } else {
+                    1.0
+                };
+                return Some(interpolation.apply(previous.1, *value, progress));
+            }
+            previous = (*time, *value);
+        }
+        Some(previous.1)
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        (self.mode == PlayMode::Once).then_some(self.length)
+    }
+}
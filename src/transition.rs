@@ -0,0 +1,148 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+use crate::{DeviceHandle, OutputId, Result, Ticker, Value};
+
+/// Values for some or all of a device's outputs.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeviceState {
+    pub values: BTreeMap<OutputId, Value>,
+}
+
+impl DeviceState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(mut self, output: OutputId, value: impl Into<Value>) -> Self {
+        self.values.insert(output, value.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    /// Maps linear progress, 0 to 1, onto the curve.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut if t < 0.5 => 4.0 * t * t * t,
+            Easing::EaseInOut => 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0,
+        }
+    }
+}
+
+impl DeviceHandle {
+    /// The current value of every output, as set through [`Self::set`].
+    pub fn snapshot(&mut self) -> Result<DeviceState> {
+        let mut state = DeviceState::new();
+        for out in self.outputs()? {
+            let value = match out.selected() {
+                Some(x) => Value::Select(x),
+                None if out.is_selector() => continue,
+                None if out.is_null() => Value::Null,
+                None => Value::Real(out.real_value),
+            };
+            state.values.insert(out.id(), value);
+        }
+        Ok(state)
+    }
+
+    /// Moves every output in `target` from its current value to the target over
+    /// `duration`, blocking until done. Numeric outputs are interpolated, toggles and
+    /// selectors switch at the end.
+    pub fn transition_to(
+        &mut self,
+        target: &DeviceState,
+        duration: Duration,
+        easing: Easing,
+    ) -> Result<()> {
+        let transition = Transition::new(self, target.clone(), duration, easing)?;
+        let start = Instant::now();
+        let mut ticker = Ticker::for_device(self, 50.0);
+        while !transition.step(self, start.elapsed())? {
+            ticker.tick();
+        }
+        Ok(())
+    }
+}
+
+/// A transition on one device that the caller steps, for running several on one
+/// clock. See [`DeviceHandle::transition_to`].
+#[derive(Debug, Clone)]
+pub struct Transition {
+    target: DeviceState,
+    numeric: Vec<(OutputId, f32, f32)>,
+    duration: Duration,
+    easing: Easing,
+}
+
+impl Transition {
+    /// Starts from the handle's current values.
+    pub fn new(
+        handle: &mut DeviceHandle,
+        target: DeviceState,
+        duration: Duration,
+        easing: Easing,
+    ) -> Result<Self> {
+        // Resolve the targets to real values up front, which validates them too
+        let mut numeric = vec![];
+        for (id, value) in &target.values {
+            let mut out = handle.output(*id)?.clone();
+            if out.is_toggle() || out.is_selector() || *value == Value::Null {
+                out.validate(*value)?;
+                continue;
+            }
+            let from = out.real_value;
+            out.set_value(*value)?;
+            // There's nothing to fade from when the output was null
+            let from = if from.is_nan() { out.real_value } else { from };
+            numeric.push((*id, from, out.real_value));
+        }
+
+        Ok(Self {
+            target,
+            numeric,
+            duration,
+            easing,
+        })
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Sets and flushes the outputs as they are `elapsed` into the transition.
+    /// Returns true once the end has been reached and the targets written.
+    pub fn step(&self, handle: &mut DeviceHandle, elapsed: Duration) -> Result<bool> {
+        if elapsed >= self.duration {
+            for (id, value) in &self.target.values {
+                handle.set_value(*id, *value)?;
+            }
+            handle.flush()?;
+            return Ok(true);
+        }
+
+        let progress = self
+            .easing
+            .apply(elapsed.as_secs_f32() / self.duration.as_secs_f32());
+        for (id, from, to) in &self.numeric {
+            handle.set(*id, from + (to - from) * progress)?;
+        }
+        handle.flush()?;
+        Ok(false)
+    }
+}
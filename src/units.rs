@@ -0,0 +1,143 @@
+use std::fmt;
+
+/// Unit system nibble of a HID unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitSystem {
+    None,
+    SiLinear,
+    SiRotation,
+    EnglishLinear,
+    EnglishRotation,
+    Reserved(u8),
+}
+
+/// A HID unit, a system followed by the exponent of each base unit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Unit(pub u32);
+
+impl Unit {
+    pub fn system(&self) -> UnitSystem {
+        match self.0 & 0xf {
+            0 => UnitSystem::None,
+            1 => UnitSystem::SiLinear,
+            2 => UnitSystem::SiRotation,
+            3 => UnitSystem::EnglishLinear,
+            4 => UnitSystem::EnglishRotation,
+            x => UnitSystem::Reserved(x as u8),
+        }
+    }
+
+    pub fn length(&self) -> i8 {
+        self.exponent(1)
+    }
+
+    pub fn mass(&self) -> i8 {
+        self.exponent(2)
+    }
+
+    pub fn time(&self) -> i8 {
+        self.exponent(3)
+    }
+
+    pub fn temperature(&self) -> i8 {
+        self.exponent(4)
+    }
+
+    pub fn current(&self) -> i8 {
+        self.exponent(5)
+    }
+
+    pub fn luminous_intensity(&self) -> i8 {
+        self.exponent(6)
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.0 == 0 || self.system() == UnitSystem::None
+    }
+
+    fn exponent(&self, nibble: u32) -> i8 {
+        nibble_to_signed((self.0 >> (nibble * 4)) & 0xf)
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (length, mass, temperature) = match self.system() {
+            UnitSystem::SiLinear => ("cm", "g", "K"),
+            UnitSystem::SiRotation => ("rad", "g", "K"),
+            UnitSystem::EnglishLinear => ("in", "slug", "°F"),
+            UnitSystem::EnglishRotation => ("deg", "slug", "°F"),
+            _ => return Ok(()),
+        };
+
+        let parts = [
+            (length, self.length()),
+            (mass, self.mass()),
+            ("s", self.time()),
+            (temperature, self.temperature()),
+            ("A", self.current()),
+            ("cd", self.luminous_intensity()),
+        ];
+
+        let mut first = true;
+        for (symbol, exponent) in parts {
+            if exponent == 0 {
+                continue;
+            }
+            if !first {
+                f.write_str("·")?;
+            }
+            first = false;
+            if exponent == 1 {
+                f.write_str(symbol)?;
+            } else {
+                write!(f, "{symbol}^{exponent}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Physical extent of an output, per the HID spec it equals the logical extent when
+/// the descriptor doesn't declare one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicalRange {
+    pub min: i32,
+    pub max: i32,
+    pub unit: Unit,
+    /// Power of ten applied to `min` and `max` to get values in `unit`.
+    pub exponent: i32,
+}
+
+impl PhysicalRange {
+    pub fn scaled_min(&self) -> f64 {
+        self.min as f64 * self.scale()
+    }
+
+    pub fn scaled_max(&self) -> f64 {
+        self.max as f64 * self.scale()
+    }
+
+    fn scale(&self) -> f64 {
+        10f64.powi(self.exponent)
+    }
+
+    /// Where `value`, in `unit`, sits between min and max.
+    pub fn fraction(&self, value: f64) -> f64 {
+        let (min, max) = (self.scaled_min(), self.scaled_max());
+        if max == min {
+            return 0.0;
+        }
+        ((value - min) / (max - min)).clamp(0.0, 1.0)
+    }
+}
+
+/// Unit exponents are stored as 4 bit two's complement.
+pub(crate) fn nibble_to_signed(x: u32) -> i8 {
+    let x = (x & 0xf) as i8;
+    if x > 7 {
+        x - 16
+    } else {
+        x
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{DeviceOutput, HidLightError, OutputId, Report, Result};
+
+/// A value for an output, in whichever terms are convenient.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Value {
+    /// On or off, full or nothing on multi-bit outputs.
+    Bool(bool),
+    /// Fraction of the output's range, 0 to 1, the same as `real_value`.
+    Real(f32),
+    /// Written as is, must be in the output's logical range.
+    Logical(i32),
+    /// In the output's physical unit, see [`DeviceOutput::physical_range`].
+    Physical(f64),
+    /// Index into a selector's options.
+    Select(usize),
+    /// No value, for fields with the null state flag, which leaves whatever the output
+    /// drives as it is. Written as a value outside the logical range, see
+    /// [`DeviceOutput::null_value`]. On selectors it selects nothing.
+    Null,
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Value::Real(value)
+    }
+}
+
+impl DeviceOutput {
+    /// Checks that `value` fits this output without changing anything.
+    pub fn validate(&self, value: Value) -> Result<()> {
+        let out_of_range = |value: i32, range| HidLightError::OutOfRange {
+            output: self.id(),
+            value,
+            range,
+        };
+        match value {
+            Value::Bool(_) => Ok(()),
+            Value::Real(x) if (0.0..=1.0).contains(&x) => Ok(()),
+            Value::Real(x) => Err(out_of_range((x * 100.0) as i32, 0..=100)),
+            Value::Logical(x) => {
+                let range = self.logical_range();
+                if range.contains(&x) {
+                    Ok(())
+                } else {
+                    Err(out_of_range(x, range))
+                }
+            }
+            Value::Physical(x) => {
+                let range = self.physical_range();
+                let (min, max) = (range.scaled_min(), range.scaled_max());
+                if (min.min(max)..=min.max(max)).contains(&x) {
+                    Ok(())
+                } else {
+                    Err(out_of_range(x as i32, range.min..=range.max))
+                }
+            }
+            Value::Select(x) if self.is_selector() => {
+                if x < self.options().len() {
+                    Ok(())
+                } else {
+                    Err(out_of_range(x as i32, 0..=self.options().len() as i32 - 1))
+                }
+            }
+            Value::Select(_) => Err(HidLightError::InvalidArgument),
+            Value::Null if self.is_selector() || self.null_value().is_some() => Ok(()),
+            Value::Null => Err(HidLightError::InvalidArgument),
+        }
+    }
+
+    pub fn set_value(&mut self, value: Value) -> Result<()> {
+        self.validate(value)?;
+        match value {
+            Value::Bool(x) if self.is_selector() => {
+                if x {
+                    self.select(0)?;
+                } else {
+                    self.deselect();
+                }
+            }
+            Value::Bool(x) => self.real_value = if x { 1.0 } else { 0.0 },
+            Value::Real(x) => self.real_value = x,
+            Value::Logical(x) if self.is_selector() => {
+                self.select((x - self.logical_range().start()) as usize)?
+            }
+            Value::Logical(x) => self.set_logical(x),
+            Value::Physical(x) => self.set_physical(x),
+            Value::Select(x) => self.select(x)?,
+            Value::Null if self.is_selector() => self.deselect(),
+            // Any real value written afterwards clears it
+            Value::Null => self.real_value = f32::NAN,
+        }
+        Ok(())
+    }
+}
+
+impl Report {
+    /// Sets several outputs at once. Every value is checked first, so on error the
+    /// report is left untouched.
+    pub fn set_values(&mut self, values: &[(OutputId, Value)]) -> Result<()> {
+        for (id, value) in values {
+            self.output(*id)?.validate(*value)?;
+        }
+        for (id, value) in values {
+            self.output_mut(*id)?.set_value(*value)?;
+        }
+        Ok(())
+    }
+
+    /// A copy of the report with the given values set, see [`Self::set_values`].
+    pub fn with_values(&self, values: &[(OutputId, Value)]) -> Result<Report> {
+        let mut report = self.clone();
+        report.set_values(values)?;
+        Ok(report)
+    }
+
+    pub fn output(&self, id: OutputId) -> Result<&DeviceOutput> {
+        self.outputs
+            .iter()
+            .find(|x| x.id() == id)
+            .ok_or(HidLightError::UnknownOutput(id))
+    }
+
+    pub fn output_mut(&mut self, id: OutputId) -> Result<&mut DeviceOutput> {
+        self.outputs
+            .iter_mut()
+            .find(|x| x.id() == id)
+            .ok_or(HidLightError::UnknownOutput(id))
+    }
+}
@@ -0,0 +1,410 @@
+//! How reports get to the device.
+
+use std::{
+    collections::HashMap,
+    ffi::{CStr, CString},
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex, OnceLock, PoisonError, Weak,
+    },
+    thread::{self, ThreadId},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    metrics::{WriteEvent, WriteMetrics},
+    recording::Recorder,
+    DeviceHandle, HidLightError, Result,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WritePath {
+    /// Output reports over the interrupt OUT endpoint, which hidapi sends as
+    /// SET_REPORT on the control pipe when the device has none.
+    #[default]
+    Interrupt,
+    /// SET_REPORT feature requests, for keyboards that ignore output reports.
+    Feature,
+    /// Interrupt first, switching to feature reports for good once a write fails
+    /// and the feature write succeeds. Only for devices whose feature report of the
+    /// same ID takes the same data, chosen per device or through
+    /// [`crate::Quirks::write_path`].
+    InterruptThenFeature,
+}
+
+/// Retries for transient write failures, mostly useful for Bluetooth devices.
+/// The default makes a single attempt, like a plain hidapi write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Gives up with [`HidLightError::Timeout`] once a write, including retries, has
+    /// taken this long. hidapi writes can't be interrupted, so a single write that
+    /// blocks still blocks, the timeout only stops further retries.
+    pub timeout: Option<Duration>,
+    /// Attempts after the first one.
+    pub retries: u32,
+    /// Wait before the first retry, doubling for each one after.
+    pub backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: None,
+            retries: 0,
+            backoff: Duration::from_millis(10),
+        }
+    }
+}
+
+/// Serializes writes from every handle open on the same device path, so sequences of
+/// reports written from different threads don't interleave. A thread holding it can
+/// take it again, so writes inside a held sequence go through.
+#[derive(Default)]
+pub(crate) struct DeviceLock {
+    /// The holding thread and how many times it has taken the lock.
+    owner: Mutex<Option<(ThreadId, usize)>>,
+    released: Condvar,
+}
+
+impl DeviceLock {
+    /// The lock shared by every handle open on `path`, in this process.
+    pub fn for_path(path: &CStr) -> Arc<Self> {
+        static LOCKS: OnceLock<Mutex<HashMap<CString, Weak<DeviceLock>>>> = OnceLock::new();
+        let mut locks = LOCKS
+            .get_or_init(Mutex::default)
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(lock) = locks.get(path).and_then(Weak::upgrade) {
+            return lock;
+        }
+        // Forget devices no handle is open on any more
+        locks.retain(|_, x| x.strong_count() > 0);
+        let lock = Arc::new(DeviceLock::default());
+        locks.insert(path.to_owned(), Arc::downgrade(&lock));
+        lock
+    }
+
+    fn acquire(self: &Arc<Self>) -> WriteGuard {
+        let id = thread::current().id();
+        let mut owner = self.owner.lock().unwrap_or_else(PoisonError::into_inner);
+        loop {
+            match &mut *owner {
+                Some((thread, count)) if *thread == id => {
+                    *count += 1;
+                    break;
+                }
+                Some(_) => {
+                    owner = self
+                        .released
+                        .wait(owner)
+                        .unwrap_or_else(PoisonError::into_inner);
+                }
+                None => {
+                    *owner = Some((id, 1));
+                    break;
+                }
+            }
+        }
+        WriteGuard {
+            lock: self.clone(),
+            _not_send: PhantomData,
+        }
+    }
+}
+
+/// Keeps other handles on the same device from writing until dropped, see
+/// [`DeviceHandle::lock_writes`].
+pub struct WriteGuard {
+    lock: Arc<DeviceLock>,
+    /// Released on the thread that took it.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for WriteGuard {
+    fn drop(&mut self) {
+        let mut owner = self
+            .lock
+            .owner
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some((_, count)) = &mut *owner {
+            *count -= 1;
+            if *count == 0 {
+                *owner = None;
+                self.lock.released.notify_one();
+            }
+        }
+    }
+}
+
+impl DeviceHandle {
+    /// Holds off writes from other handles on the same device, in any thread, until
+    /// the guard is dropped, for sequences of reports that must arrive together.
+    /// Single writes already take it, so this is only needed across several.
+    pub fn lock_writes(&self) -> WriteGuard {
+        self.write_lock.acquire()
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy
+    }
+
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry_policy = policy;
+    }
+
+    /// Reports every write, including failed ones, to `metrics`.
+    pub fn set_metrics(&mut self, metrics: Option<Arc<dyn WriteMetrics>>) {
+        self.metrics = metrics;
+    }
+
+    /// Logs every report written from now on to `recorder`.
+    pub fn set_recorder(&mut self, recorder: Option<Arc<Recorder>>) {
+        self.recorder = recorder;
+    }
+
+    pub fn write_path(&self) -> WritePath {
+        self.write_path
+    }
+
+    pub fn set_write_path(&mut self, path: WritePath) {
+        self.write_path = path;
+        self.use_feature.store(false, Ordering::Relaxed);
+    }
+
+    /// Sends a single report, the first byte being the report ID, along the configured
+    /// path and retrying as configured.
+    pub(crate) fn send(&self, data: &[u8]) -> Result<()> {
+        let start = Instant::now();
+        let (result, attempts) = self.send_retrying(data, start);
+        if let Some(metrics) = &self.metrics {
+            metrics.record_write(&WriteEvent {
+                vid: self.vid,
+                pid: self.pid,
+                report_id: data[0],
+                len: data.len(),
+                latency: start.elapsed(),
+                attempts,
+                error: result.as_ref().err(),
+            });
+        }
+        if let (Some(recorder), Ok(())) = (&self.recorder, &result) {
+            recorder.record(data)?;
+        }
+        result
+    }
+
+    fn send_retrying(&self, data: &[u8], start: Instant) -> (Result<()>, u32) {
+        let policy = self.retry_policy;
+        let mut backoff = policy.backoff;
+        let mut attempt = 0;
+        loop {
+            #[cfg(feature = "tracing")]
+            tracing::trace!(
+                path = ?self.write_path,
+                attempt,
+                bytes = %crate::descriptor::to_hex(data).trim_end().replace('\n', " "),
+                "writing report"
+            );
+
+            let result = self.send_once(data);
+            #[cfg(feature = "tracing")]
+            if let Err(err) = &result {
+                tracing::warn!(report_id = data[0], attempt, %err, "write failed");
+            }
+
+            let timed_out = policy.timeout.is_some_and(|x| start.elapsed() >= x);
+            let result = match result {
+                Ok(()) => Ok(()),
+                Err(HidLightError::Disconnected) => Err(HidLightError::Disconnected),
+                Err(_) if timed_out => Err(HidLightError::Timeout),
+                Err(err) if attempt >= policy.retries => Err(err),
+                Err(_) => {
+                    attempt += 1;
+                    if let Some(timeout) = policy.timeout {
+                        backoff = backoff.min(timeout.saturating_sub(start.elapsed()));
+                    }
+                    std::thread::sleep(backoff);
+                    backoff *= 2;
+                    continue;
+                }
+            };
+            return (result, attempt + 1);
+        }
+    }
+
+    fn send_once(&self, data: &[u8]) -> Result<()> {
+        if self.is_disconnected() {
+            return Err(HidLightError::Disconnected);
+        }
+
+        route(
+            self.write_path,
+            &self.use_feature,
+            || self.write_interrupt(data),
+            || self.checked(self.hid().send_feature_report(data)),
+        )
+    }
+
+    fn write_interrupt(&self, data: &[u8]) -> Result<()> {
+        let written = self.checked(self.hid().write(data))?;
+        if written < data.len() {
+            return Err(HidLightError::ShortWrite {
+                report_id: data[0] as u32,
+                expected: data.len(),
+                written,
+            });
+        }
+        Ok(())
+    }
+
+    /// Whether the device has gone away. Once it has, everything fails with
+    /// [`HidLightError::Disconnected`] and the device has to be opened again.
+    pub fn is_disconnected(&self) -> bool {
+        self.disconnected.load(Ordering::Relaxed)
+    }
+
+    /// Turns errors that mean the device is gone into [`HidLightError::Disconnected`],
+    /// marking the handle dead.
+    pub(crate) fn checked<T>(&self, result: hidapi::HidResult<T>) -> Result<T> {
+        result.map_err(|err| disconnect_error(err, &self.disconnected))
+    }
+}
+
+/// Sends a report along `path` with the given writes, `use_feature` remembering when
+/// [`WritePath::InterruptThenFeature`] has fallen back.
+fn route(
+    path: WritePath,
+    use_feature: &AtomicBool,
+    interrupt: impl FnOnce() -> Result<()>,
+    feature: impl FnOnce() -> Result<()>,
+) -> Result<()> {
+    match path {
+        WritePath::Interrupt => interrupt(),
+        WritePath::Feature => feature(),
+        WritePath::InterruptThenFeature if use_feature.load(Ordering::Relaxed) => feature(),
+        WritePath::InterruptThenFeature => match interrupt() {
+            Err(HidLightError::Disconnected) => Err(HidLightError::Disconnected),
+            Err(err) => {
+                feature().map_err(|_| err)?;
+                use_feature.store(true, Ordering::Relaxed);
+                Ok(())
+            }
+            Ok(()) => Ok(()),
+        },
+    }
+}
+
+/// Converts a hidapi error, setting `disconnected` when it means the device is gone.
+fn disconnect_error(err: hidapi::HidError, disconnected: &AtomicBool) -> HidLightError {
+    if is_disconnect(&err) {
+        disconnected.store(true, Ordering::Relaxed);
+        HidLightError::Disconnected
+    } else {
+        err.into()
+    }
+}
+
+fn is_disconnect(err: &hidapi::HidError) -> bool {
+    // ENODEV and ESHUTDOWN from hidraw and libusb, ERROR_DEVICE_NOT_CONNECTED on Windows
+    const CODES: &[i32] = if cfg!(windows) { &[1167] } else { &[19, 108] };
+
+    match err {
+        hidapi::HidError::IoError { error } => {
+            error.raw_os_error().is_some_and(|x| CODES.contains(&x))
+        }
+        hidapi::HidError::HidApiError { message } => {
+            let message = message.to_lowercase();
+            [
+                "no such device",
+                "not connected",
+                "disconnected",
+                "not attached",
+            ]
+            .iter()
+            .any(|x| message.contains(x))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_path_never_sends_feature_reports() {
+        let use_feature = AtomicBool::new(false);
+        for _ in 0..2 {
+            let result = route(
+                WritePath::default(),
+                &use_feature,
+                || Err(HidLightError::InvalidArgument),
+                || panic!("sent a feature report"),
+            );
+            assert!(matches!(result, Err(HidLightError::InvalidArgument)));
+        }
+        assert!(!use_feature.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn fallback_sticks_once_chosen() {
+        let use_feature = AtomicBool::new(false);
+        let result = route(
+            WritePath::InterruptThenFeature,
+            &use_feature,
+            || Err(HidLightError::InvalidArgument),
+            || Ok(()),
+        );
+        assert!(result.is_ok());
+        assert!(use_feature.load(Ordering::Relaxed));
+        let result = route(
+            WritePath::InterruptThenFeature,
+            &use_feature,
+            || panic!("went back to output reports"),
+            || Ok(()),
+        );
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn failed_write_marks_disconnected() {
+        let disconnected = AtomicBool::new(false);
+        let code = if cfg!(windows) { 1167 } else { 19 };
+        let err = hidapi::HidError::IoError {
+            error: std::io::Error::from_raw_os_error(code),
+        };
+        assert!(matches!(
+            disconnect_error(err, &disconnected),
+            HidLightError::Disconnected
+        ));
+        assert!(disconnected.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn backend_messages_mark_disconnected() {
+        let disconnected = AtomicBool::new(false);
+        let err = hidapi::HidError::HidApiError {
+            message: "WriteFile: (0x0000048F) The device is not connected.".into(),
+        };
+        assert!(matches!(
+            disconnect_error(err, &disconnected),
+            HidLightError::Disconnected
+        ));
+        assert!(disconnected.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn other_failures_keep_handle() {
+        let disconnected = AtomicBool::new(false);
+        let err = hidapi::HidError::HidApiError {
+            message: "hid_write: Broken pipe".into(),
+        };
+        assert!(!matches!(
+            disconnect_error(err, &disconnected),
+            HidLightError::Disconnected
+        ));
+        assert!(!disconnected.load(Ordering::Relaxed));
+    }
+}